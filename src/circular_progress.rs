@@ -1,19 +1,371 @@
-use iced::widget::canvas::{self, Canvas, Geometry, Path, Stroke};
-use iced::{mouse, Color, Element, Length, Radians, Rectangle, Renderer, Theme};
+use iced::alignment;
+use iced::widget::canvas::{self, Canvas, Geometry, LineCap, Path, Stroke};
+use iced::{
+    mouse, Color, Element, Length, Pixels, Point, Radians, Rectangle, Renderer, Size, Theme,
+};
 use std::f32::consts::PI;
 
+use crate::config::{RingConfig, RingDirection, RingFill, RingStyle, RingTicks};
+use crate::engine::{format_time, Snapshot, TimerState};
+use crate::theme::Palette;
+
+/// Twelve o'clock, where the arc starts.
+const START_ANGLE: f32 = -PI / 2.0;
+
+/// Beyond this many rounds the segments would be too thin to tell apart, so
+/// the rounds done are shown as one arc instead.
+const MAX_ROUND_SEGMENTS: u32 = 120;
+
+/// Where to draw the ring in an area of `size`: its center, radius and
+/// outline width.
+///
+/// Everything scales with the smaller side, so the ring keeps its proportions
+/// at any size and the outline always stays inside the area. At 150 pixels the
+/// outline is 2 pixels wide with a 10 pixel margin.
+fn geometry(size: Size) -> (Point, f32, f32) {
+    let diameter = size.width.min(size.height);
+    let stroke_width = (diameter / 75.0).max(1.0);
+    let margin = (diameter / 15.0).max(stroke_width / 2.0);
+    let radius = (diameter / 2.0 - margin).max(0.0);
+    (
+        Point::new(size.width / 2.0, size.height / 2.0),
+        radius,
+        stroke_width,
+    )
+}
+
+/// The shapes a ring is made of, shared by the window and image renderers.
+#[derive(Debug, Clone, PartialEq)]
+enum Primitive {
+    /// The outline of a whole circle.
+    Circle {
+        center: Point,
+        radius: f32,
+        width: f32,
+        color: Color,
+    },
+    /// An outlined arc from `start` to `end`, in radians.
+    Arc {
+        center: Point,
+        radius: f32,
+        start: f32,
+        end: f32,
+        width: f32,
+        rounded: bool,
+        color: Color,
+    },
+    /// A filled slice from `start` to `end`, in radians.
+    Pie {
+        center: Point,
+        radius: f32,
+        start: f32,
+        end: f32,
+        color: Color,
+    },
+    Line {
+        from: Point,
+        to: Point,
+        width: f32,
+        color: Color,
+    },
+    /// Text centred on `center`, in the theme's text color.
+    Text {
+        content: String,
+        center: Point,
+        size: f32,
+    },
+}
+
+fn on_circle(center: Point, radius: f32, angle: f32) -> Point {
+    Point::new(
+        center.x + radius * angle.cos(),
+        center.y + radius * angle.sin(),
+    )
+}
+
 pub struct CircularProgress {
     progress: f32,
     color: Color,
+    /// The unfilled part of the ring and the rounds still to go.
+    track_color: Color,
+    tick_color: Color,
+    style: RingStyle,
+    /// Width of the ring as a fraction of its radius.
+    thickness: f32,
+    rounded_caps: bool,
+    /// Marks at these fractions of the round remaining.
+    ticks: Vec<f32>,
+    /// Completed and total rounds, for the segmented outer ring.
+    rounds: Option<(u32, u32)>,
+    label: Option<String>,
+    direction: RingDirection,
+    fill: RingFill,
 }
 
 impl CircularProgress {
+    /// A pie showing `progress`, the fraction of the round remaining.
     pub fn new(progress: f32, color: Color) -> Self {
+        let palette = Palette::dark();
         Self {
             progress: progress.clamp(0.0, 1.0),
             color,
+            track_color: palette.track,
+            tick_color: palette.ticks,
+            style: RingStyle::Pie,
+            thickness: 0.3,
+            rounded_caps: false,
+            ticks: Vec::new(),
+            rounds: None,
+            label: None,
+            direction: RingDirection::Clockwise,
+            fill: RingFill::Drain,
+        }
+    }
+
+    /// The ring for the timer's `status`, drawn as configured in the
+    /// palette's colours.
+    pub fn for_status(status: &Snapshot, config: &RingConfig, palette: &Palette) -> Self {
+        let lead_in = status.lead_in_remaining_secs > 0;
+        let (remaining, length) = if lead_in {
+            (status.lead_in_remaining_secs, status.lead_in_secs)
+        } else {
+            (status.round_remaining_secs, status.interval_secs)
+        };
+        let progress = if length > 0 {
+            remaining as f32 / length as f32
+        } else {
+            0.0
+        };
+
+        let ticks = match config.ticks {
+            RingTicks::None => Vec::new(),
+            RingTicks::Seconds => second_marks(length),
+            RingTicks::Warning if status.warning_secs > 0 && !lead_in => {
+                vec![status.warning_secs as f32 / status.interval_secs as f32]
+            }
+            RingTicks::Warning => Vec::new(),
+        };
+
+        let finished = status.state == TimerState::Stopped
+            && status.elapsed_secs > 0
+            && status.elapsed_secs >= status.interval_secs.saturating_mul(status.rounds);
+        let completed = if finished {
+            status.rounds
+        } else {
+            status.round.saturating_sub(1)
+        };
+
+        let mut ring = Self::new(progress, palette.ring)
+            .colors(palette.track, palette.ticks)
+            .style(config.style, config.thickness)
+            .rounded_caps(config.rounded_caps)
+            .ticks(ticks)
+            .direction(config.direction)
+            .fill(config.fill);
+        if config.show_rounds {
+            ring = ring.rounds(completed, status.rounds);
+        }
+        if config.show_time {
+            ring = ring.label(format_time(remaining));
+        }
+        ring
+    }
+
+    /// Colours of the unfilled track and the tick marks.
+    pub fn colors(mut self, track: Color, ticks: Color) -> Self {
+        self.track_color = track;
+        self.tick_color = ticks;
+        self
+    }
+
+    pub fn style(mut self, style: RingStyle, thickness: f32) -> Self {
+        self.style = style;
+        self.thickness = thickness.clamp(0.05, 1.0);
+        self
+    }
+
+    /// Rounds off the ends of the ring's arc.
+    pub fn rounded_caps(mut self, rounded_caps: bool) -> Self {
+        self.rounded_caps = rounded_caps;
+        self
+    }
+
+    /// Marks positions on the ring, as fractions of the round remaining.
+    pub fn ticks(mut self, ticks: Vec<f32>) -> Self {
+        self.ticks = ticks;
+        self
+    }
+
+    /// Shows the rounds as segments of an outer ring.
+    pub fn rounds(mut self, completed: u32, total: u32) -> Self {
+        self.rounds = Some((completed.min(total), total));
+        self
+    }
+
+    /// Draws text, such as the time remaining, in the middle.
+    pub fn label(mut self, label: String) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    pub fn direction(mut self, direction: RingDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    pub fn fill(mut self, fill: RingFill) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    /// How much of the circle the arc covers when `remaining` of the round is
+    /// left: a draining arc shrinks with it, a filling one grows.
+    fn arc_fraction(&self, remaining: f32) -> f32 {
+        match self.fill {
+            RingFill::Drain => remaining,
+            RingFill::Fill => 1.0 - remaining,
+        }
+    }
+
+    /// The angle `fraction` of the way around from twelve o'clock.
+    fn angle(&self, fraction: f32) -> f32 {
+        let sweep = 2.0 * PI * fraction;
+        match self.direction {
+            RingDirection::Clockwise => START_ANGLE + sweep,
+            RingDirection::CounterClockwise => START_ANGLE - sweep,
         }
     }
+
+    fn primitives(&self, size: Size) -> Vec<Primitive> {
+        let (center, radius, stroke_width) = geometry(size);
+        let fraction = self.arc_fraction(self.progress);
+        let end = self.angle(fraction);
+        let mut primitives = Vec::new();
+
+        // Ticks span the ring, or reach a little way into the pie
+        let (tick_inner, text_size) = match self.style {
+            RingStyle::Pie => {
+                // Draw background circle outline
+                primitives.push(Primitive::Circle {
+                    center,
+                    radius,
+                    width: stroke_width,
+                    color: self.track_color,
+                });
+                // Draw filled pie
+                if fraction > 0.0 {
+                    primitives.push(Primitive::Pie {
+                        center,
+                        radius,
+                        start: START_ANGLE,
+                        end,
+                        color: self.color,
+                    });
+                }
+                (radius * 0.85, radius * 0.4)
+            }
+            RingStyle::Ring => {
+                let width = radius * self.thickness;
+                let middle = radius - width / 2.0;
+                primitives.push(Primitive::Circle {
+                    center,
+                    radius: middle,
+                    width,
+                    color: self.track_color,
+                });
+                if fraction > 0.0 {
+                    primitives.push(Primitive::Arc {
+                        center,
+                        radius: middle,
+                        start: START_ANGLE,
+                        end,
+                        width,
+                        rounded: self.rounded_caps,
+                        color: self.color,
+                    });
+                }
+                (radius - width, (radius - width) * 0.6)
+            }
+        };
+
+        for &tick in &self.ticks {
+            let angle = self.angle(self.arc_fraction(tick.clamp(0.0, 1.0)));
+            primitives.push(Primitive::Line {
+                from: on_circle(center, tick_inner, angle),
+                to: on_circle(center, radius, angle),
+                width: stroke_width.max(1.0),
+                color: self.tick_color,
+            });
+        }
+
+        // Rounds sit in the margin around the ring, with gaps between them
+        if let Some((completed, total)) = self.rounds.filter(|&(_, total)| total > 0) {
+            let margin = size.width.min(size.height) / 2.0 - radius;
+            let (radius, width) = (radius + margin * 0.55, margin * 0.35);
+            if total <= MAX_ROUND_SEGMENTS {
+                let gap = (0.2 / total as f32).min(0.02);
+                for round in 0..total {
+                    let color = if round < completed {
+                        self.color
+                    } else {
+                        self.track_color
+                    };
+                    primitives.push(Primitive::Arc {
+                        center,
+                        radius,
+                        start: self.angle(round as f32 / total as f32 + gap / 2.0),
+                        end: self.angle((round + 1) as f32 / total as f32 - gap / 2.0),
+                        width,
+                        rounded: false,
+                        color,
+                    });
+                }
+            } else {
+                primitives.push(Primitive::Circle {
+                    center,
+                    radius,
+                    width,
+                    color: self.track_color,
+                });
+                let done = completed.min(total) as f32 / total as f32;
+                if done > 0.0 {
+                    primitives.push(Primitive::Arc {
+                        center,
+                        radius,
+                        start: self.angle(0.0),
+                        end: self.angle(done),
+                        width,
+                        rounded: false,
+                        color: self.color,
+                    });
+                }
+            }
+        }
+
+        if let Some(label) = &self.label {
+            primitives.push(Primitive::Text {
+                content: label.clone(),
+                center,
+                size: text_size,
+            });
+        }
+
+        primitives
+    }
+}
+
+/// One mark per second for short rounds, coarser ones for long rounds.
+fn second_marks(length_secs: u32) -> Vec<f32> {
+    let Some(step) = [1, 5, 15, 60, 300]
+        .into_iter()
+        .find(|step| length_secs / step <= 120)
+    else {
+        return Vec::new();
+    };
+    (0..length_secs)
+        .step_by(step as usize)
+        .map(|secs| secs as f32 / length_secs as f32)
+        .collect()
 }
 
 impl canvas::Program<()> for CircularProgress {
@@ -23,58 +375,240 @@ impl canvas::Program<()> for CircularProgress {
         &self,
         _state: &Self::State,
         renderer: &Renderer,
-        _theme: &Theme,
+        theme: &Theme,
         bounds: Rectangle,
         _cursor: mouse::Cursor,
     ) -> Vec<Geometry> {
         let mut frame = canvas::Frame::new(renderer, bounds.size());
-        let center = frame.center();
-        let radius = frame.width().min(frame.height()) / 2.0 - 10.0;
-
-        // Draw background circle outline
-        let background_circle = Path::circle(center, radius);
-        frame.stroke(
-            &background_circle,
-            Stroke::default()
-                .with_width(2.0)
-                .with_color(Color::from_rgb(0.3, 0.3, 0.3)),
-        );
-
-        // Draw filled pie
-        // Start from top (12 o'clock position) and go clockwise
-        let start_angle = -PI / 2.0;
-        let end_angle = start_angle + (2.0 * PI * self.progress);
 
-        if self.progress > 0.0 {
-            let pie = Path::new(|builder| {
-                builder.move_to(center);
-                builder.arc(canvas::path::Arc {
+        for primitive in self.primitives(bounds.size()) {
+            match primitive {
+                Primitive::Circle {
                     center,
                     radius,
-                    start_angle: Radians(start_angle),
-                    end_angle: Radians(end_angle),
-                });
-                builder.line_to(center);
-                builder.close();
-            });
-
-            frame.fill(&pie, self.color);
+                    width,
+                    color,
+                } => frame.stroke(
+                    &Path::circle(center, radius),
+                    Stroke::default().with_width(width).with_color(color),
+                ),
+                Primitive::Arc {
+                    center,
+                    radius,
+                    start,
+                    end,
+                    width,
+                    rounded,
+                    color,
+                } => {
+                    let arc = Path::new(|builder| {
+                        builder.arc(canvas::path::Arc {
+                            center,
+                            radius,
+                            start_angle: Radians(start),
+                            end_angle: Radians(end),
+                        });
+                    });
+                    let cap = if rounded {
+                        LineCap::Round
+                    } else {
+                        LineCap::Butt
+                    };
+                    frame.stroke(
+                        &arc,
+                        Stroke::default()
+                            .with_width(width)
+                            .with_color(color)
+                            .with_line_cap(cap),
+                    );
+                }
+                Primitive::Pie {
+                    center,
+                    radius,
+                    start,
+                    end,
+                    color,
+                } => {
+                    let pie = Path::new(|builder| {
+                        builder.move_to(center);
+                        builder.arc(canvas::path::Arc {
+                            center,
+                            radius,
+                            start_angle: Radians(start),
+                            end_angle: Radians(end),
+                        });
+                        builder.line_to(center);
+                        builder.close();
+                    });
+                    frame.fill(&pie, color);
+                }
+                Primitive::Line {
+                    from,
+                    to,
+                    width,
+                    color,
+                } => frame.stroke(
+                    &Path::line(from, to),
+                    Stroke::default().with_width(width).with_color(color),
+                ),
+                Primitive::Text {
+                    content,
+                    center,
+                    size,
+                } => frame.fill_text(canvas::Text {
+                    content,
+                    position: center,
+                    color: theme.palette().text,
+                    size: Pixels(size),
+                    horizontal_alignment: alignment::Horizontal::Center,
+                    vertical_alignment: alignment::Vertical::Center,
+                    ..canvas::Text::default()
+                }),
+            }
         }
 
         vec![frame.into_geometry()]
     }
 }
 
-pub fn circular_progress(progress: f32, color: Color) -> Element<'static, ()> {
-    Canvas::new(CircularProgress::new(progress, color))
-        .width(Length::Fixed(150.0))
-        .height(Length::Fixed(150.0))
-        .into()
+/// The ring at `size`, which may fill its container; it fits the smaller of
+/// its width and height.
+pub fn circular_progress(ring: CircularProgress, size: Length) -> Element<'static, ()> {
+    Canvas::new(ring).width(size).height(size).into()
+}
+
+/// Traces an arc in short segments, since tiny-skia has no arcs.
+fn trace_arc(
+    builder: &mut tiny_skia::PathBuilder,
+    center: Point,
+    radius: f32,
+    start: f32,
+    end: f32,
+) {
+    let segments = ((end - start).abs() / (2.0 * PI) * 128.0).ceil().max(1.0) as u32;
+    for segment in 0..=segments {
+        let angle = start + (end - start) * segment as f32 / segments as f32;
+        let point = on_circle(center, radius, angle);
+        builder.line_to(point.x, point.y);
+    }
+}
+
+fn paint(color: Color) -> tiny_skia::Paint<'static> {
+    let [r, g, b, a] = color.into_rgba8();
+    let mut paint = tiny_skia::Paint {
+        anti_alias: true,
+        ..tiny_skia::Paint::default()
+    };
+    paint.set_color_rgba8(r, g, b, a);
+    paint
+}
+
+/// Renders the same ring as the window onto a transparent square image that
+/// is `size` pixels wide, for use outside the app.
+///
+/// Labels are left out, as there are no fonts to draw them with.
+pub fn render(ring: &CircularProgress, size: u32) -> Option<tiny_skia::Pixmap> {
+    let mut pixmap = tiny_skia::Pixmap::new(size, size)?;
+    let identity = tiny_skia::Transform::identity();
+
+    for primitive in ring.primitives(Size::new(size as f32, size as f32)) {
+        match primitive {
+            Primitive::Circle {
+                center,
+                radius,
+                width,
+                color,
+            } => {
+                let Some(circle) = tiny_skia::PathBuilder::from_circle(center.x, center.y, radius)
+                else {
+                    continue;
+                };
+                let stroke = tiny_skia::Stroke {
+                    width,
+                    ..tiny_skia::Stroke::default()
+                };
+                pixmap.stroke_path(&circle, &paint(color), &stroke, identity, None);
+            }
+            Primitive::Arc {
+                center,
+                radius,
+                start,
+                end,
+                width,
+                rounded,
+                color,
+            } => {
+                let mut builder = tiny_skia::PathBuilder::new();
+                let first = on_circle(center, radius, start);
+                builder.move_to(first.x, first.y);
+                trace_arc(&mut builder, center, radius, start, end);
+                let Some(arc) = builder.finish() else {
+                    continue;
+                };
+                let stroke = tiny_skia::Stroke {
+                    width,
+                    line_cap: if rounded {
+                        tiny_skia::LineCap::Round
+                    } else {
+                        tiny_skia::LineCap::Butt
+                    },
+                    ..tiny_skia::Stroke::default()
+                };
+                pixmap.stroke_path(&arc, &paint(color), &stroke, identity, None);
+            }
+            Primitive::Pie {
+                center,
+                radius,
+                start,
+                end,
+                color,
+            } => {
+                let mut builder = tiny_skia::PathBuilder::new();
+                builder.move_to(center.x, center.y);
+                trace_arc(&mut builder, center, radius, start, end);
+                builder.close();
+                let Some(pie) = builder.finish() else {
+                    continue;
+                };
+                pixmap.fill_path(
+                    &pie,
+                    &paint(color),
+                    tiny_skia::FillRule::Winding,
+                    identity,
+                    None,
+                );
+            }
+            Primitive::Line {
+                from,
+                to,
+                width,
+                color,
+            } => {
+                let mut builder = tiny_skia::PathBuilder::new();
+                builder.move_to(from.x, from.y);
+                builder.line_to(to.x, to.y);
+                let Some(line) = builder.finish() else {
+                    continue;
+                };
+                let stroke = tiny_skia::Stroke {
+                    width,
+                    ..tiny_skia::Stroke::default()
+                };
+                pixmap.stroke_path(&line, &paint(color), &stroke, identity, None);
+            }
+            Primitive::Text { .. } => {}
+        }
+    }
+
+    Some(pixmap)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::engine::Engine;
+
+    const COLOR: Color = Color::from_rgb(0.2, 0.7, 0.9);
 
     #[test]
     fn test_new_clamps_progress_to_zero() {
@@ -136,4 +670,285 @@ mod tests {
         let end_angle = start_angle + (2.0 * PI * progress.progress);
         assert_eq!(end_angle, start_angle + 2.0 * PI);
     }
+
+    #[test]
+    fn test_geometry_matches_the_original_ring() {
+        let (center, radius, stroke_width) = geometry(Size::new(150.0, 150.0));
+        assert_eq!(center, Point::new(75.0, 75.0));
+        assert_eq!(radius, 65.0);
+        assert_eq!(stroke_width, 2.0);
+    }
+
+    #[test]
+    fn test_geometry_scales_with_the_smaller_side() {
+        let (center, radius, stroke_width) = geometry(Size::new(600.0, 300.0));
+        assert_eq!(center, Point::new(300.0, 150.0));
+        assert_eq!(radius, 130.0);
+        assert_eq!(stroke_width, 4.0);
+        // The outline stays inside the area
+        assert!(radius + stroke_width / 2.0 <= 150.0);
+    }
+
+    #[test]
+    fn test_geometry_of_tiny_areas() {
+        let (_, radius, stroke_width) = geometry(Size::new(4.0, 40.0));
+        assert_eq!(stroke_width, 1.0);
+        assert!(radius >= 0.0 && radius + stroke_width / 2.0 <= 2.0);
+        assert_eq!(geometry(Size::ZERO).1, 0.0);
+    }
+
+    fn alpha(pixmap: &tiny_skia::Pixmap, x: u32, y: u32) -> u8 {
+        pixmap.pixel(x, y).unwrap().alpha()
+    }
+
+    #[test]
+    fn test_render_quarter_fills_top_right() {
+        let pixmap = render(&CircularProgress::new(0.25, COLOR), 150).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (150, 150));
+
+        // Inside the top-right quarter of the pie, and outside it
+        assert_eq!(alpha(&pixmap, 100, 50), 255);
+        assert_eq!(alpha(&pixmap, 50, 100), 0);
+        assert_eq!(alpha(&pixmap, 50, 50), 0);
+        // The corners stay transparent
+        assert_eq!(alpha(&pixmap, 0, 0), 0);
+    }
+
+    #[test]
+    fn test_render_full_and_empty() {
+        let full = render(&CircularProgress::new(1.0, COLOR), 60).unwrap();
+        assert_eq!(alpha(&full, 20, 40), 255);
+        let empty = render(&CircularProgress::new(0.0, COLOR), 60).unwrap();
+        assert_eq!(alpha(&empty, 20, 40), 0);
+        // Only the outline is drawn
+        assert!(alpha(&empty, 30, 4) > 0);
+    }
+
+    #[test]
+    fn test_render_rejects_empty_size() {
+        assert!(render(&CircularProgress::new(0.5, COLOR), 0).is_none());
+    }
+
+    const SIZE: Size = Size::new(150.0, 150.0);
+
+    fn arcs(primitives: &[Primitive]) -> Vec<(f32, f32, f32, bool)> {
+        primitives
+            .iter()
+            .filter_map(|primitive| match *primitive {
+                Primitive::Arc {
+                    start,
+                    end,
+                    width,
+                    rounded,
+                    ..
+                } => Some((start, end, width, rounded)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_counter_clockwise_angles() {
+        let ring = CircularProgress::new(0.25, COLOR).direction(RingDirection::CounterClockwise);
+        assert_eq!(ring.angle(0.0), -PI / 2.0);
+        assert_eq!(ring.angle(0.25), -PI);
+        assert_eq!(ring.angle(0.5), -PI / 2.0 - PI);
+    }
+
+    #[test]
+    fn test_fill_grows_as_the_round_runs_down() {
+        let drain = CircularProgress::new(0.75, COLOR);
+        assert_eq!(drain.arc_fraction(0.75), 0.75);
+        let fill = CircularProgress::new(0.75, COLOR).fill(RingFill::Fill);
+        assert_eq!(fill.arc_fraction(0.75), 0.25);
+        assert_eq!(fill.arc_fraction(1.0), 0.0);
+    }
+
+    #[test]
+    fn test_pie_is_drawn_by_default() {
+        let primitives = CircularProgress::new(0.5, COLOR).primitives(SIZE);
+        assert_eq!(primitives.len(), 2);
+        assert!(matches!(primitives[0], Primitive::Circle { radius, .. } if radius == 65.0));
+        assert!(matches!(
+            primitives[1],
+            Primitive::Pie { start, end, .. } if start == -PI / 2.0 && end == PI / 2.0
+        ));
+    }
+
+    #[test]
+    fn test_empty_pie_is_only_an_outline() {
+        let primitives = CircularProgress::new(0.0, COLOR).primitives(SIZE);
+        assert_eq!(primitives.len(), 1);
+    }
+
+    #[test]
+    fn test_ring_style_thickness_and_caps() {
+        let ring = CircularProgress::new(0.5, COLOR)
+            .style(RingStyle::Ring, 0.2)
+            .rounded_caps(true);
+        let primitives = ring.primitives(SIZE);
+
+        // The track and the arc share the middle of the ring
+        assert!(matches!(
+            primitives[0],
+            Primitive::Circle { radius, width, .. } if radius == 58.5 && width == 13.0
+        ));
+        assert_eq!(arcs(&primitives), vec![(-PI / 2.0, PI / 2.0, 13.0, true)]);
+    }
+
+    #[test]
+    fn test_ring_thickness_is_limited() {
+        let ring = CircularProgress::new(0.5, COLOR).style(RingStyle::Ring, 3.0);
+        assert_eq!(ring.thickness, 1.0);
+    }
+
+    #[test]
+    fn test_ticks_follow_the_direction() {
+        let primitives = CircularProgress::new(1.0, COLOR)
+            .ticks(vec![0.25])
+            .direction(RingDirection::CounterClockwise)
+            .primitives(SIZE);
+        let Some(Primitive::Line { from, to, .. }) = primitives.last() else {
+            panic!("no tick in {:?}", primitives);
+        };
+        // A quarter of the way round to the left, on the outer edge
+        assert!((to.x - 10.0).abs() < 0.01 && (to.y - 75.0).abs() < 0.01);
+        assert!(from.x > to.x);
+    }
+
+    #[test]
+    fn test_second_marks() {
+        assert_eq!(second_marks(4), vec![0.0, 0.25, 0.5, 0.75]);
+        assert_eq!(second_marks(60).len(), 60);
+        // Every 5 seconds in a 3 minute round
+        assert_eq!(second_marks(180).len(), 36);
+        assert!(second_marks(0).is_empty());
+    }
+
+    #[test]
+    fn test_round_segments() {
+        let primitives = CircularProgress::new(0.5, COLOR)
+            .rounds(1, 4)
+            .primitives(SIZE);
+        let segments: Vec<_> = primitives
+            .iter()
+            .filter_map(|primitive| match *primitive {
+                Primitive::Arc {
+                    radius,
+                    start,
+                    end,
+                    color,
+                    ..
+                } => Some((radius, start, end, color)),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(segments.len(), 4);
+        // Outside the pie, with a gap between neighbours
+        assert!(segments.iter().all(|&(radius, ..)| radius > 65.0));
+        assert!(segments[0].2 < segments[1].1);
+        assert_eq!(segments[0].3, COLOR);
+        assert_eq!(segments[1].3, Palette::dark().track);
+    }
+
+    #[test]
+    fn test_many_rounds_share_one_arc() {
+        let primitives = CircularProgress::new(0.5, COLOR)
+            .rounds(1_000_000_000, 4_000_000_000)
+            .primitives(SIZE);
+
+        // The outline, the pie, and the rounds' track and share done
+        assert_eq!(primitives.len(), 4);
+        assert!(matches!(
+            primitives[2],
+            Primitive::Circle { radius, .. } if radius > 65.0
+        ));
+        assert_eq!(arcs(&primitives).len(), 1);
+        let (start, end, ..) = arcs(&primitives)[0];
+        assert_eq!((start, end), (-PI / 2.0, 0.0));
+    }
+
+    #[test]
+    fn test_label_is_centred() {
+        let primitives = CircularProgress::new(0.5, COLOR)
+            .label(String::from("01:30"))
+            .primitives(SIZE);
+        assert_eq!(
+            primitives.last(),
+            Some(&Primitive::Text {
+                content: String::from("01:30"),
+                center: Point::new(75.0, 75.0),
+                size: 26.0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_for_status_while_running() {
+        let mut engine = Engine::new(60, 20, 0);
+        engine.warning_secs = 15;
+        engine.start();
+        engine.skip();
+        for _ in 0..15 {
+            engine.tick();
+        }
+        let config = RingConfig {
+            ticks: RingTicks::Warning,
+            show_rounds: true,
+            show_time: true,
+            ..RingConfig::default()
+        };
+        let ring = CircularProgress::for_status(&engine.snapshot(), &config, &Palette::dark());
+
+        assert_eq!(ring.progress, 0.75);
+        assert_eq!(ring.ticks, vec![0.25]);
+        assert_eq!(ring.rounds, Some((1, 20)));
+        assert_eq!(ring.label.as_deref(), Some("00:45"));
+    }
+
+    #[test]
+    fn test_for_status_during_lead_in() {
+        let mut engine = Engine::new(60, 20, 10);
+        engine.start();
+        engine.tick();
+        let ring = CircularProgress::for_status(
+            &engine.snapshot(),
+            &RingConfig::default(),
+            &Palette::dark(),
+        );
+
+        assert_eq!(ring.progress, 0.9);
+        assert_eq!(ring.rounds, None);
+        assert_eq!(ring.label, None);
+    }
+
+    #[test]
+    fn test_colors() {
+        let track = Color::from_rgb(0.9, 0.9, 0.9);
+        let ticks = Color::BLACK;
+        let primitives = CircularProgress::new(0.5, COLOR)
+            .colors(track, ticks)
+            .ticks(vec![0.5])
+            .primitives(SIZE);
+        assert!(matches!(primitives[0], Primitive::Circle { color, .. } if color == track));
+        assert!(matches!(primitives[2], Primitive::Line { color, .. } if color == ticks));
+
+        let palette = Palette::high_contrast();
+        let ring = CircularProgress::for_status(
+            &Engine::new(60, 2, 0).snapshot(),
+            &RingConfig::default(),
+            &palette,
+        );
+        assert_eq!(ring.color, palette.ring);
+        assert_eq!(ring.track_color, palette.track);
+    }
+
+    #[test]
+    fn test_render_ring_leaves_a_hole() {
+        let ring = CircularProgress::new(1.0, COLOR).style(RingStyle::Ring, 0.3);
+        let pixmap = render(&ring, 150).unwrap();
+        assert_eq!(alpha(&pixmap, 75, 75), 0);
+        assert_eq!(alpha(&pixmap, 75, 20), 255);
+    }
 }
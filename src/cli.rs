@@ -0,0 +1,511 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::presets;
+
+pub const HELP: &str = "\
+Round Timer - plays a chime at regular intervals
+
+Usage: round-timer [OPTIONS]
+       round-timer ctl <COMMAND>
+
+Commands:
+  ctl <COMMAND>           Control a running timer; COMMAND is one of start,
+                          pause, resume, toggle, stop, skip or status
+
+Options:
+  -i, --interval <TIME>   Time between chimes, as seconds or M:SS (default: 60)
+  -r, --rounds <N>        Number of rounds (default: 20)
+  -p, --preset <NAME>     Start from a built-in preset; --interval and
+                          --rounds override its values
+  -s, --start             Start the timer as soon as the window opens
+  -l, --lead-in <TIME>    Countdown before the first round, as seconds or M:SS
+      --warning <TIME>    Emit a warning event this long before each round ends
+  -m, --mute              Do not play the chime
+      --flash             Flash the window when the chime plays and on
+                          warnings
+  -w, --window <MODE>     Window mode: windowed, maximized or fullscreen
+  -t, --tui               Run in the terminal instead of opening a window
+      --events <FORMAT>   Print timer events on stdout; FORMAT is json
+  -c, --config <PATH>     Read settings from PATH instead of the default
+                          config file
+      --schedule <PATH>   Read round labels, notes and pictures from PATH
+                          instead of the [[rounds]] in the config file
+      --speakers <NAMES>  Meeting mode: one round per speaker, from a
+                          comma-separated list such as Ana,Bo,Cy
+      --shuffle           Put the speakers in a random order
+  -h, --help              Print this help and exit
+  -V, --version           Print the version and exit
+
+Presets:
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    Maximized,
+    Fullscreen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventFormat {
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub interval_secs: u32,
+    pub num_rounds: u32,
+    pub auto_start: bool,
+    pub lead_in_secs: u32,
+    pub warning_secs: u32,
+    pub mute: bool,
+    /// Visual alerts, turned on here or in the config file.
+    pub flash: bool,
+    pub window_mode: WindowMode,
+    pub tui: bool,
+    pub events: Option<EventFormat>,
+    pub config_path: Option<PathBuf>,
+    pub schedule_path: Option<PathBuf>,
+    /// Speakers for the meeting mode, overriding the config file's.
+    pub speakers: Vec<String>,
+    pub shuffle: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            interval_secs: 60,
+            num_rounds: 20,
+            auto_start: false,
+            lead_in_secs: 0,
+            warning_secs: 0,
+            mute: false,
+            flash: false,
+            window_mode: WindowMode::Windowed,
+            tui: false,
+            events: None,
+            config_path: None,
+            schedule_path: None,
+            speakers: Vec::new(),
+            shuffle: false,
+        }
+    }
+}
+
+/// Commands understood by the control socket of a running instance.
+pub const CONTROL_COMMANDS: &[&str] = &[
+    "start", "pause", "resume", "toggle", "stop", "skip", "status",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run(Options),
+    Control(String),
+    Help,
+    Version,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CliError(String);
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+pub fn help_text() -> String {
+    let mut help = String::from(HELP);
+    for preset in presets::PRESETS {
+        help.push_str(&format!("  {:<22}{}\n", preset.name, preset.description));
+    }
+    help
+}
+
+pub fn parse<I>(args: I) -> Result<Command, CliError>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut options = Options::default();
+    let mut interval = None;
+    let mut rounds = None;
+    let mut preset = None;
+
+    let mut args = args.into_iter().peekable();
+    if args.peek().map(String::as_str) == Some("ctl") {
+        args.next();
+        return parse_control(args);
+    }
+
+    while let Some(arg) = args.next() {
+        // Accept both `--flag value` and `--flag=value`
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let inline_value = inline_value.map(str::to_string);
+        let mut value = |name: &str| {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| CliError(format!("missing value for {}", name)))
+        };
+
+        match flag.as_str() {
+            "-i" | "--interval" => {
+                interval = Some(parse_time(&value("--interval")?, "--interval")?)
+            }
+            "-r" | "--rounds" => rounds = Some(parse_rounds(&value("--rounds")?)?),
+            "-p" | "--preset" => preset = Some(value("--preset")?),
+            "-l" | "--lead-in" => {
+                options.lead_in_secs = parse_time(&value("--lead-in")?, "--lead-in")?
+            }
+            "--warning" => options.warning_secs = parse_time(&value("--warning")?, "--warning")?,
+            "--events" => options.events = Some(parse_event_format(&value("--events")?)?),
+            "-c" | "--config" => options.config_path = Some(PathBuf::from(value("--config")?)),
+            "--schedule" => options.schedule_path = Some(PathBuf::from(value("--schedule")?)),
+            "--speakers" => options.speakers = parse_speakers(&value("--speakers")?)?,
+            "--shuffle" => options.shuffle = true,
+            "-w" | "--window" => options.window_mode = parse_window_mode(&value("--window")?)?,
+            "-s" | "--start" => options.auto_start = true,
+            "-m" | "--mute" => options.mute = true,
+            "--flash" => options.flash = true,
+            "-t" | "--tui" => options.tui = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            _ => return Err(CliError(format!("unexpected argument '{}'", arg))),
+        }
+    }
+
+    if let Some(name) = preset {
+        let preset =
+            presets::find(&name).ok_or_else(|| CliError(format!("unknown preset '{}'", name)))?;
+        options.interval_secs = preset.interval_secs;
+        options.num_rounds = preset.num_rounds;
+    }
+    if let Some(secs) = interval {
+        if secs == 0 {
+            return Err(CliError(String::from(
+                "--interval must be greater than zero",
+            )));
+        }
+        options.interval_secs = secs;
+    }
+    if let Some(num_rounds) = rounds {
+        options.num_rounds = num_rounds;
+    }
+    if options
+        .interval_secs
+        .checked_mul(options.num_rounds)
+        .is_none()
+    {
+        return Err(CliError(String::from(
+            "--interval and --rounds make a session too long to time",
+        )));
+    }
+    if options.warning_secs >= options.interval_secs {
+        return Err(CliError(String::from(
+            "--warning must be shorter than the interval",
+        )));
+    }
+    if !options.speakers.is_empty() && options.schedule_path.is_some() {
+        return Err(CliError(String::from(
+            "--speakers cannot be combined with --schedule",
+        )));
+    }
+    if !options.speakers.is_empty() && rounds.is_some() {
+        return Err(CliError(String::from(
+            "--speakers cannot be combined with --rounds: each speaker gets one round",
+        )));
+    }
+    if options.tui && options.events.is_some() {
+        return Err(CliError(String::from(
+            "--events cannot be combined with --tui",
+        )));
+    }
+
+    Ok(Command::Run(options))
+}
+
+/// Parses a duration written as `SECS`, `M:SS` or `H:MM:SS`.
+pub fn parse_time(value: &str, name: &str) -> Result<u32, CliError> {
+    let invalid = || {
+        CliError(format!(
+            "invalid value '{}' for {}: expected seconds or M:SS",
+            value, name
+        ))
+    };
+
+    let parts: Vec<&str> = value.split(':').collect();
+    if parts.len() > 3 {
+        return Err(invalid());
+    }
+
+    let mut total: u32 = 0;
+    for (index, part) in parts.iter().enumerate() {
+        let number = part.parse::<u32>().map_err(|_| invalid())?;
+        // Everything after the leading component is minutes or seconds
+        if index > 0 && (number >= 60 || part.len() != 2) {
+            return Err(invalid());
+        }
+        total = total
+            .checked_mul(60)
+            .and_then(|total| total.checked_add(number))
+            .ok_or_else(invalid)?;
+    }
+    Ok(total)
+}
+
+fn parse_control(mut args: impl Iterator<Item = String>) -> Result<Command, CliError> {
+    let command = args
+        .next()
+        .ok_or_else(|| CliError(String::from("missing command for ctl")))?;
+    if !CONTROL_COMMANDS.contains(&command.as_str()) {
+        return Err(CliError(format!(
+            "unknown ctl command '{}': expected one of {}",
+            command,
+            CONTROL_COMMANDS.join(", ")
+        )));
+    }
+    if let Some(extra) = args.next() {
+        return Err(CliError(format!("unexpected argument '{}'", extra)));
+    }
+    Ok(Command::Control(command))
+}
+
+fn parse_rounds(value: &str) -> Result<u32, CliError> {
+    match value.parse::<u32>() {
+        Ok(rounds) if rounds > 0 => Ok(rounds),
+        _ => Err(CliError(format!(
+            "invalid value '{}' for --rounds: expected a positive number",
+            value
+        ))),
+    }
+}
+
+fn parse_speakers(value: &str) -> Result<Vec<String>, CliError> {
+    let speakers: Vec<String> = value
+        .split(',')
+        .map(|name| name.trim().to_string())
+        .collect();
+    if speakers.iter().any(String::is_empty) {
+        return Err(CliError(format!(
+            "invalid value '{}' for --speakers: expected names separated by commas",
+            value
+        )));
+    }
+    Ok(speakers)
+}
+
+fn parse_window_mode(value: &str) -> Result<WindowMode, CliError> {
+    match value {
+        "windowed" => Ok(WindowMode::Windowed),
+        "maximized" => Ok(WindowMode::Maximized),
+        "fullscreen" => Ok(WindowMode::Fullscreen),
+        _ => Err(CliError(format!(
+            "invalid value '{}' for --window: expected windowed, maximized or fullscreen",
+            value
+        ))),
+    }
+}
+
+fn parse_event_format(value: &str) -> Result<EventFormat, CliError> {
+    match value {
+        "json" => Ok(EventFormat::Json),
+        _ => Err(CliError(format!(
+            "invalid value '{}' for --events: expected json",
+            value
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Command, CliError> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn parse_options(args: &[&str]) -> Options {
+        match parse_args(args) {
+            Ok(Command::Run(options)) => options,
+            other => panic!("expected options, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_no_arguments_uses_defaults() {
+        assert_eq!(parse_options(&[]), Options::default());
+    }
+
+    #[test]
+    fn test_interval_and_rounds() {
+        let options = parse_options(&["--interval", "1:30", "--rounds", "10", "--start"]);
+        assert_eq!(options.interval_secs, 90);
+        assert_eq!(options.num_rounds, 10);
+        assert!(options.auto_start);
+    }
+
+    #[test]
+    fn test_inline_values() {
+        let options = parse_options(&["--interval=45", "--lead-in=0:10", "--window=fullscreen"]);
+        assert_eq!(options.interval_secs, 45);
+        assert_eq!(options.lead_in_secs, 10);
+        assert_eq!(options.window_mode, WindowMode::Fullscreen);
+    }
+
+    #[test]
+    fn test_short_flags() {
+        let options = parse_options(&["-i", "30", "-r", "4", "-s", "-m", "-l", "5"]);
+        assert_eq!(options.interval_secs, 30);
+        assert_eq!(options.num_rounds, 4);
+        assert!(options.auto_start);
+        assert!(options.mute);
+        assert_eq!(options.lead_in_secs, 5);
+    }
+
+    #[test]
+    fn test_flash_flag() {
+        assert!(parse_options(&["--mute", "--flash"]).flash);
+        assert!(!parse_options(&[]).flash);
+    }
+
+    #[test]
+    fn test_tui_flag() {
+        assert!(parse_options(&["--tui"]).tui);
+        assert!(!parse_options(&[]).tui);
+    }
+
+    #[test]
+    fn test_preset_is_overridden_by_explicit_values() {
+        let options = parse_options(&["--rounds", "3", "--preset", "boxing"]);
+        assert_eq!(options.interval_secs, 180);
+        assert_eq!(options.num_rounds, 3);
+    }
+
+    #[test]
+    fn test_unknown_preset_is_rejected() {
+        assert!(parse_args(&["--preset", "marathon"]).is_err());
+    }
+
+    #[test]
+    fn test_zero_interval_is_rejected() {
+        assert!(parse_args(&["--interval", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_zero_rounds_is_rejected() {
+        assert!(parse_args(&["--rounds", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_missing_value_is_rejected() {
+        assert_eq!(
+            parse_args(&["--interval"]),
+            Err(CliError(String::from("missing value for --interval")))
+        );
+    }
+
+    #[test]
+    fn test_unknown_argument_is_rejected() {
+        assert!(parse_args(&["--loud"]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_window_mode_is_rejected() {
+        assert!(parse_args(&["--window", "tiny"]).is_err());
+    }
+
+    #[test]
+    fn test_events_and_warning() {
+        let options = parse_options(&["--events", "json", "--warning", "10"]);
+        assert_eq!(options.events, Some(EventFormat::Json));
+        assert_eq!(options.warning_secs, 10);
+    }
+
+    #[test]
+    fn test_invalid_event_format_is_rejected() {
+        assert!(parse_args(&["--events", "xml"]).is_err());
+    }
+
+    #[test]
+    fn test_session_length_must_fit() {
+        assert!(parse_args(&["--interval", "4294967295", "--rounds", "2"]).is_err());
+        assert!(parse_args(&["--interval", "1:00:00", "--rounds", "4294967295"]).is_err());
+        assert!(parse_args(&["--interval", "4294967295", "--rounds", "1"]).is_ok());
+    }
+
+    #[test]
+    fn test_warning_must_be_shorter_than_interval() {
+        assert!(parse_args(&["--interval", "30", "--warning", "30"]).is_err());
+    }
+
+    #[test]
+    fn test_events_cannot_be_combined_with_tui() {
+        assert!(parse_args(&["--tui", "--events", "json"]).is_err());
+    }
+
+    #[test]
+    fn test_config_path() {
+        let options = parse_options(&["--config", "/tmp/timer.toml"]);
+        assert_eq!(options.config_path, Some(PathBuf::from("/tmp/timer.toml")));
+    }
+
+    #[test]
+    fn test_schedule_path() {
+        let options = parse_options(&["--schedule=circuit.toml"]);
+        assert_eq!(options.schedule_path, Some(PathBuf::from("circuit.toml")));
+    }
+
+    #[test]
+    fn test_speakers() {
+        let options = parse_options(&["--speakers", "Ana, Bo,Cy", "--shuffle"]);
+        assert_eq!(options.speakers, ["Ana", "Bo", "Cy"]);
+        assert!(options.shuffle);
+
+        assert!(parse_args(&["--speakers", "Ana,,Cy"]).is_err());
+        assert!(parse_args(&["--speakers", ""]).is_err());
+        assert!(parse_args(&["--speakers", "Ana", "--schedule", "circuit.toml"]).is_err());
+        assert!(parse_args(&["--speakers", "Ana,Bo", "--rounds", "3"]).is_err());
+    }
+
+    #[test]
+    fn test_ctl_command() {
+        assert_eq!(
+            parse_args(&["ctl", "toggle"]),
+            Ok(Command::Control(String::from("toggle")))
+        );
+    }
+
+    #[test]
+    fn test_ctl_rejects_bad_input() {
+        assert!(parse_args(&["ctl"]).is_err());
+        assert!(parse_args(&["ctl", "dance"]).is_err());
+        assert!(parse_args(&["ctl", "stop", "now"]).is_err());
+    }
+
+    #[test]
+    fn test_help_and_version() {
+        assert_eq!(parse_args(&["--rounds", "3", "--help"]), Ok(Command::Help));
+        assert_eq!(parse_args(&["-V"]), Ok(Command::Version));
+    }
+
+    #[test]
+    fn test_parse_time_formats() {
+        assert_eq!(parse_time("90", "--interval"), Ok(90));
+        assert_eq!(parse_time("1:30", "--interval"), Ok(90));
+        assert_eq!(parse_time("1:00:05", "--interval"), Ok(3605));
+    }
+
+    #[test]
+    fn test_parse_time_rejects_malformed_input() {
+        assert!(parse_time("1:75", "--interval").is_err());
+        assert!(parse_time("1:5", "--interval").is_err());
+        assert!(parse_time("abc", "--interval").is_err());
+        assert!(parse_time("-5", "--interval").is_err());
+        assert!(parse_time("1:00:00:00", "--interval").is_err());
+    }
+}
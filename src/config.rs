@@ -0,0 +1,1025 @@
+use serde::Deserialize;
+use std::fmt;
+use std::net::SocketAddrV4;
+use std::path::{Path, PathBuf};
+
+use crate::events::EventKind;
+use crate::shortcuts::Shortcuts;
+use crate::webhooks;
+
+/// Settings read from `config.toml` in the user's configuration directory.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub hooks: HooksConfig,
+    pub notifications: NotificationsConfig,
+    pub tray: TrayConfig,
+    pub power: PowerConfig,
+    pub http: HttpConfig,
+    pub webhooks: WebhooksConfig,
+    pub mqtt: MqttConfig,
+    pub sync: SyncConfig,
+    pub overlay: OverlayConfig,
+    pub mini: MiniConfig,
+    pub ring: RingConfig,
+    pub theme: ThemeConfig,
+    pub keys: KeysConfig,
+    /// What each round is for, in order; see `schedule::Schedule`.
+    pub rounds: Vec<RoundConfig>,
+    pub meeting: MeetingConfig,
+    pub flash: FlashConfig,
+    pub speech: SpeechConfig,
+}
+
+/// Shell commands run when timer events occur.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    pub on_start: Option<String>,
+    pub on_round_start: Option<String>,
+    pub on_round_end: Option<String>,
+    pub on_warning: Option<String>,
+    pub on_pause: Option<String>,
+    pub on_resume: Option<String>,
+    pub on_stop: Option<String>,
+    pub on_finish: Option<String>,
+    pub timeout_secs: u64,
+}
+
+impl HooksConfig {
+    /// The command for events of `kind`, if one is set.
+    pub fn command(&self, kind: EventKind) -> Option<&str> {
+        let command = match kind {
+            EventKind::Start => &self.on_start,
+            EventKind::RoundStart => &self.on_round_start,
+            EventKind::RoundEnd => &self.on_round_end,
+            EventKind::Warning => &self.on_warning,
+            EventKind::Pause => &self.on_pause,
+            EventKind::Resume => &self.on_resume,
+            EventKind::Stop => &self.on_stop,
+            EventKind::Finish => &self.on_finish,
+        };
+        command.as_deref()
+    }
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            on_start: None,
+            on_round_start: None,
+            on_round_end: None,
+            on_warning: None,
+            on_pause: None,
+            on_resume: None,
+            on_stop: None,
+            on_finish: None,
+            timeout_secs: 30,
+        }
+    }
+}
+
+/// Desktop notifications shown for timer events.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    pub enabled: bool,
+    pub events: Vec<EventKind>,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            events: vec![EventKind::RoundStart, EventKind::Finish],
+        }
+    }
+}
+
+/// The system tray icon.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrayConfig {
+    pub enabled: bool,
+    /// Hide the window in the tray when it is closed instead of quitting.
+    pub close_to_tray: bool,
+}
+
+impl Default for TrayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            close_to_tray: false,
+        }
+    }
+}
+
+/// Keeping the computer awake during a session, and what to do when it
+/// slept anyway.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerConfig {
+    /// Keep the screen from blanking or locking while the timer runs.
+    pub inhibit_idle: bool,
+    /// Keep the computer from suspending while the timer runs.
+    pub inhibit_sleep: bool,
+    pub after_suspend: AfterSuspend,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            inhibit_idle: true,
+            inhibit_sleep: true,
+            after_suspend: AfterSuspend::Pause,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AfterSuspend {
+    /// Pause where the timer stopped and tell the user.
+    #[default]
+    Pause,
+    /// Count the time spent suspended, as if the timer had kept running.
+    CatchUp,
+}
+
+/// The built-in web server for remote displays.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub enabled: bool,
+    pub bind: String,
+    /// Bearer token required by the control endpoints, which are disabled
+    /// without one.
+    pub token: Option<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: String::from("127.0.0.1:8787"),
+            token: None,
+        }
+    }
+}
+
+/// URLs that receive a JSON POST when timer events occur.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhooksConfig {
+    pub on_start: Option<String>,
+    pub on_round_start: Option<String>,
+    pub on_round_end: Option<String>,
+    pub on_warning: Option<String>,
+    pub on_pause: Option<String>,
+    pub on_resume: Option<String>,
+    pub on_stop: Option<String>,
+    pub on_finish: Option<String>,
+    /// Further attempts after a failed delivery, waiting twice as long each time.
+    pub retries: u32,
+    pub retry_delay_secs: u64,
+    pub timeout_secs: u64,
+    /// Where deliveries are recorded; defaults to `webhooks.log` in the data directory.
+    pub log: Option<PathBuf>,
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self {
+            on_start: None,
+            on_round_start: None,
+            on_round_end: None,
+            on_warning: None,
+            on_pause: None,
+            on_resume: None,
+            on_stop: None,
+            on_finish: None,
+            retries: 3,
+            retry_delay_secs: 2,
+            timeout_secs: 10,
+            log: None,
+        }
+    }
+}
+
+impl WebhooksConfig {
+    /// The URL for events of `kind`, if one is set.
+    pub fn url(&self, kind: EventKind) -> Option<&str> {
+        let url = match kind {
+            EventKind::Start => &self.on_start,
+            EventKind::RoundStart => &self.on_round_start,
+            EventKind::RoundEnd => &self.on_round_end,
+            EventKind::Warning => &self.on_warning,
+            EventKind::Pause => &self.on_pause,
+            EventKind::Resume => &self.on_resume,
+            EventKind::Stop => &self.on_stop,
+            EventKind::Finish => &self.on_finish,
+        };
+        url.as_deref()
+    }
+
+    fn urls(&self) -> impl Iterator<Item = &str> {
+        EventKind::ALL
+            .into_iter()
+            .filter_map(move |kind| self.url(kind))
+    }
+}
+
+/// Publishing state to an MQTT broker and taking commands from it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    /// `host:port` of the broker.
+    pub broker: String,
+    pub client_id: String,
+    pub topic_prefix: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub keep_alive_secs: u16,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            broker: String::from("localhost:1883"),
+            client_id: String::from("round-timer"),
+            topic_prefix: String::from("round-timer"),
+            username: None,
+            password: None,
+            keep_alive_secs: 30,
+        }
+    }
+}
+
+/// Keeping several instances on the local network in step.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncConfig {
+    pub role: SyncRole,
+    /// IPv4 multicast `address:port` the leader announces itself on.
+    pub group: String,
+    /// Only instances with the same session name follow each other.
+    pub session: String,
+    /// Followers keep time on their own after hearing nothing for this long.
+    pub leader_timeout_secs: u64,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            role: SyncRole::Off,
+            group: String::from("239.255.77.77:47474"),
+            session: String::from("default"),
+            leader_timeout_secs: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncRole {
+    #[default]
+    Off,
+    Leader,
+    Follower,
+}
+
+/// Files for streaming software to overlay on a video.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OverlayConfig {
+    pub enabled: bool,
+    /// Where to write the files; `round-timer/overlay` in the local data
+    /// directory by default.
+    pub directory: Option<PathBuf>,
+    /// Write `round.txt`, `remaining.txt` and `status.txt`.
+    pub text: bool,
+    /// Write the ring to `ring.png`.
+    pub png: bool,
+    pub png_size: u32,
+    /// Write numbered frames (`ring-000001.png`, ...) instead of replacing
+    /// `ring.png`.
+    pub png_sequence: bool,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: None,
+            text: true,
+            png: true,
+            png_size: 256,
+            png_sequence: false,
+        }
+    }
+}
+
+/// The compact window for keeping an eye on the timer during desk work.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MiniConfig {
+    pub always_on_top: bool,
+    /// Width and height of the window in logical pixels, at least
+    /// [`MIN_MINI_SIZE`].
+    pub size: u32,
+}
+
+/// Smaller mini windows are too small to read, or to click to get back.
+const MIN_MINI_SIZE: u32 = 80;
+
+impl Default for MiniConfig {
+    fn default() -> Self {
+        Self {
+            always_on_top: true,
+            size: 160,
+        }
+    }
+}
+
+/// How the progress ring is drawn, in the window and in overlays.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RingConfig {
+    pub style: RingStyle,
+    /// Width of the ring as a fraction of its radius, for the `ring` style.
+    pub thickness: f32,
+    pub rounded_caps: bool,
+    pub ticks: RingTicks,
+    /// Show completed and remaining rounds as segments around the ring.
+    pub show_rounds: bool,
+    /// Show the time remaining in the middle of the ring.
+    pub show_time: bool,
+    pub direction: RingDirection,
+    pub fill: RingFill,
+}
+
+impl Default for RingConfig {
+    fn default() -> Self {
+        Self {
+            style: RingStyle::Pie,
+            thickness: 0.3,
+            rounded_caps: false,
+            ticks: RingTicks::None,
+            show_rounds: false,
+            show_time: false,
+            direction: RingDirection::Clockwise,
+            fill: RingFill::Drain,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RingStyle {
+    /// A filled slice over a thin outline.
+    #[default]
+    Pie,
+    /// A donut with a hole in the middle.
+    Ring,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RingTicks {
+    #[default]
+    None,
+    /// A mark every second, or every few seconds in long rounds.
+    Seconds,
+    /// A mark where the warning before the end of the round starts.
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RingDirection {
+    #[default]
+    Clockwise,
+    CounterClockwise,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RingFill {
+    /// The ring starts full and empties as the round runs down.
+    #[default]
+    Drain,
+    /// The ring starts empty and fills up.
+    Fill,
+}
+
+/// Colours of the window, the ring and the big clock.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub mode: ThemeMode,
+    pub colors: ColorsConfig,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeMode {
+    /// Light or dark, as set in the desktop when the app starts.
+    #[default]
+    System,
+    Light,
+    Dark,
+    HighContrast,
+}
+
+/// Colours that replace those of the theme's mode, as `#rrggbb` or
+/// `#rrggbbaa`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorsConfig {
+    pub background: Option<String>,
+    pub text: Option<String>,
+    /// Buttons, inputs and the progress bar.
+    pub accent: Option<String>,
+    pub ring: Option<String>,
+    /// The unfilled part of the ring.
+    pub track: Option<String>,
+    pub ticks: Option<String>,
+    /// Backgrounds of the big clock in each phase.
+    pub stopped: Option<String>,
+    pub paused: Option<String>,
+    pub lead_in: Option<String>,
+    pub running: Option<String>,
+    pub warning: Option<String>,
+    /// Text on the big clock's backgrounds.
+    pub phase_text: Option<String>,
+}
+
+impl ColorsConfig {
+    /// The colours that are set, by name.
+    pub fn entries(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            ("background", &self.background),
+            ("text", &self.text),
+            ("accent", &self.accent),
+            ("ring", &self.ring),
+            ("track", &self.track),
+            ("ticks", &self.ticks),
+            ("stopped", &self.stopped),
+            ("paused", &self.paused),
+            ("lead_in", &self.lead_in),
+            ("running", &self.running),
+            ("warning", &self.warning),
+            ("phase_text", &self.phase_text),
+        ]
+        .into_iter()
+        .filter_map(|(name, color)| color.as_deref().map(|color| (name, color)))
+    }
+}
+
+/// Whether `color` is written as `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`.
+fn is_hex_color(color: &str) -> bool {
+    color.strip_prefix('#').is_some_and(|hex| {
+        matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// Visual alerts in the window, for when the chime cannot be heard.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FlashConfig {
+    pub enabled: bool,
+    pub style: FlashStyle,
+    pub color: String,
+    pub warning_color: String,
+    /// Pattern when a round, or the lead-in, ends.
+    pub round_end: FlashPattern,
+    pub warning: FlashPattern,
+    /// Pattern when the last round ends, in place of `round_end`.
+    pub finish: FlashPattern,
+    /// Light up once and steadily instead of blinking.
+    pub reduced_motion: bool,
+}
+
+impl Default for FlashConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            style: FlashStyle::Background,
+            color: String::from("#ffffff"),
+            warning_color: String::from("#ff9900"),
+            round_end: FlashPattern::Double,
+            warning: FlashPattern::Single,
+            finish: FlashPattern::Long,
+            reduced_motion: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashStyle {
+    /// The whole window background.
+    #[default]
+    Background,
+    /// A thick border around the edges of the window.
+    Border,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashPattern {
+    None,
+    Single,
+    Double,
+    Triple,
+    Long,
+}
+
+/// Keyboard shortcuts in the window: a single character, or a key name such
+/// as `space`, `escape`, `enter`, `up` or `f1`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeysConfig {
+    pub toggle: KeyNames,
+    pub stop: KeyNames,
+    pub next: KeyNames,
+    pub previous: KeyNames,
+    pub more_time: KeyNames,
+    pub less_time: KeyNames,
+    pub big_clock: KeyNames,
+    pub mute: KeyNames,
+    pub help: KeyNames,
+    /// Seconds that `more_time` and `less_time` add or take away.
+    pub step_secs: u32,
+}
+
+impl Default for KeysConfig {
+    fn default() -> Self {
+        Self {
+            toggle: KeyNames::from("space"),
+            stop: KeyNames::from("escape"),
+            next: KeyNames::from("n"),
+            previous: KeyNames::from("p"),
+            more_time: KeyNames::from("+"),
+            less_time: KeyNames::from("-"),
+            big_clock: KeyNames::Several(vec![String::from("f"), String::from("f11")]),
+            mute: KeyNames::from("m"),
+            help: KeyNames::from("?"),
+            step_secs: 10,
+        }
+    }
+}
+
+/// The keys for one action: one, several, or none to leave it without a key.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum KeyNames {
+    One(String),
+    Several(Vec<String>),
+}
+
+impl KeyNames {
+    pub fn names(&self) -> &[String] {
+        match self {
+            KeyNames::One(name) => std::slice::from_ref(name),
+            KeyNames::Several(names) => names,
+        }
+    }
+}
+
+impl From<&str> for KeyNames {
+    fn from(name: &str) -> Self {
+        KeyNames::One(name.to_string())
+    }
+}
+
+/// One round of a schedule, such as an exercise in a circuit.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoundConfig {
+    pub label: String,
+    pub notes: Option<String>,
+    /// A PNG picture to show during the round.
+    pub image: Option<PathBuf>,
+}
+
+/// Stand-ups and other meetings where each round is one person's turn.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MeetingConfig {
+    /// One round each, in this order unless `shuffle` is set. Empty turns
+    /// the meeting mode off.
+    pub speakers: Vec<String>,
+    /// Draw a new order for every session.
+    pub shuffle: bool,
+}
+
+/// Reading each round's label aloud as the round starts.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpeechConfig {
+    pub enabled: bool,
+    /// A program that speaks the text it is given as its argument.
+    pub command: String,
+}
+
+impl Default for SpeechConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            command: String::from(if cfg!(target_os = "macos") {
+                "say"
+            } else {
+                "spd-say"
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigError {
+    path: PathBuf,
+    message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("round-timer").join("config.toml"))
+}
+
+/// Loads the configuration from `path`, or from the default location when no
+/// path is given. A missing default file is not an error.
+pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+    let (path, required) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(Config::default()),
+        },
+    };
+
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound && !required => {
+            return Ok(Config::default());
+        }
+        Err(err) => {
+            return Err(ConfigError {
+                path,
+                message: err.to_string(),
+            })
+        }
+    };
+
+    parse(&contents).map_err(|message| ConfigError { path, message })
+}
+
+pub fn parse(contents: &str) -> Result<Config, String> {
+    let config: Config = toml::from_str(contents).map_err(|err| err.message().to_string())?;
+
+    // Catch URLs that could never be delivered to before the first event
+    if let Some(url) = config
+        .webhooks
+        .urls()
+        .find(|url| !webhooks::is_supported_url(url))
+    {
+        return Err(format!(
+            "webhook URL '{}' is not supported: only http:// and https:// URLs are",
+            url
+        ));
+    }
+
+    match config.sync.group.parse::<SocketAddrV4>() {
+        Ok(group) if group.ip().is_multicast() => {}
+        _ => {
+            return Err(format!(
+                "sync group '{}' is not an IPv4 multicast address and port",
+                config.sync.group
+            ))
+        }
+    }
+
+    if let Some((name, color)) = config
+        .theme
+        .colors
+        .entries()
+        .find(|(_, color)| !is_hex_color(color))
+    {
+        return Err(format!(
+            "theme color {} = '{}' is not a color like '#33b3e6'",
+            name, color
+        ));
+    }
+
+    if let Some(color) = [&config.flash.color, &config.flash.warning_color]
+        .into_iter()
+        .find(|color| !is_hex_color(color))
+    {
+        return Err(format!(
+            "flash color '{}' is not a color like '#ffffff'",
+            color
+        ));
+    }
+
+    if config
+        .meeting
+        .speakers
+        .iter()
+        .any(|name| name.trim().is_empty())
+    {
+        return Err(String::from("meeting speakers must have names"));
+    }
+
+    if config.mini.size < MIN_MINI_SIZE {
+        return Err(format!(
+            "mini window size {} is too small: the smallest is {}",
+            config.mini.size, MIN_MINI_SIZE
+        ));
+    }
+
+    if config.speech.enabled && config.speech.command.trim().is_empty() {
+        return Err(String::from("speech needs a command to speak with"));
+    }
+
+    Shortcuts::from_config(&config.keys)?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_config_uses_defaults() {
+        let config = parse("").unwrap();
+        assert!(config.hooks.on_round_start.is_none());
+        assert_eq!(config.hooks.timeout_secs, 30);
+    }
+
+    #[test]
+    fn test_parse_hooks() {
+        let config = parse(
+            r#"
+            [hooks]
+            on_round_start = "echo start"
+            on_finish = "echo done"
+            timeout_secs = 5
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.hooks.on_round_start.as_deref(), Some("echo start"));
+        assert_eq!(config.hooks.on_finish.as_deref(), Some("echo done"));
+        assert_eq!(config.hooks.timeout_secs, 5);
+    }
+
+    #[test]
+    fn test_parse_notifications() {
+        let config = parse(
+            r#"
+            [notifications]
+            events = ["round_end", "warning"]
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.notifications.enabled);
+        assert_eq!(
+            config.notifications.events,
+            vec![EventKind::RoundEnd, EventKind::Warning]
+        );
+        assert!(parse("[notifications]\nevents = [\"lunch\"]").is_err());
+    }
+
+    #[test]
+    fn test_parse_tray() {
+        let config = parse("[tray]\nclose_to_tray = true").unwrap();
+        assert!(config.tray.enabled);
+        assert!(config.tray.close_to_tray);
+    }
+
+    #[test]
+    fn test_parse_power() {
+        let config = parse("[power]\ninhibit_sleep = false\nafter_suspend = \"catch_up\"").unwrap();
+        assert!(config.power.inhibit_idle);
+        assert!(!config.power.inhibit_sleep);
+        assert_eq!(config.power.after_suspend, AfterSuspend::CatchUp);
+    }
+
+    #[test]
+    fn test_parse_http() {
+        let config = parse("[http]\nenabled = true\ntoken = \"secret\"").unwrap();
+        assert!(config.http.enabled);
+        assert_eq!(config.http.bind, "127.0.0.1:8787");
+        assert_eq!(config.http.token.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn test_parse_webhooks() {
+        let config = parse(
+            r#"
+            [webhooks]
+            on_finish = "http://dashboard.local/sessions"
+            retries = 5
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.webhooks.on_finish.as_deref(),
+            Some("http://dashboard.local/sessions")
+        );
+        assert_eq!(config.webhooks.retries, 5);
+        assert_eq!(config.webhooks.timeout_secs, 10);
+    }
+
+    #[test]
+    fn test_webhook_urls_are_checked() {
+        let config = parse("[webhooks]\non_start = \"https://example.com/timer\"").unwrap();
+        assert_eq!(
+            config.webhooks.on_start.as_deref(),
+            Some("https://example.com/timer")
+        );
+
+        let err = parse("[webhooks]\non_start = \"ftp://example.com\"").unwrap_err();
+        assert!(err.contains("ftp://example.com"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_mqtt() {
+        let config = parse("[mqtt]\nenabled = true\nbroker = \"gym.local:1883\"").unwrap();
+        assert!(config.mqtt.enabled);
+        assert_eq!(config.mqtt.broker, "gym.local:1883");
+        assert_eq!(config.mqtt.topic_prefix, "round-timer");
+    }
+
+    #[test]
+    fn test_parse_sync() {
+        let config = parse("[sync]\nrole = \"follower\"\nsession = \"studio\"").unwrap();
+        assert_eq!(config.sync.role, SyncRole::Follower);
+        assert_eq!(config.sync.session, "studio");
+        assert_eq!(config.sync.group, "239.255.77.77:47474");
+    }
+
+    #[test]
+    fn test_parse_overlay() {
+        let config =
+            parse("[overlay]\nenabled = true\npng = false\ndirectory = \"/tmp/obs\"").unwrap();
+        assert!(config.overlay.enabled);
+        assert!(config.overlay.text);
+        assert!(!config.overlay.png);
+        assert_eq!(config.overlay.directory, Some(PathBuf::from("/tmp/obs")));
+        assert_eq!(config.overlay.png_size, 256);
+    }
+
+    #[test]
+    fn test_parse_mini() {
+        let config = parse("[mini]\nalways_on_top = false").unwrap();
+        assert!(!config.mini.always_on_top);
+        assert_eq!(config.mini.size, 160);
+    }
+
+    #[test]
+    fn test_tiny_mini_windows_are_rejected() {
+        assert!(parse("[mini]\nsize = 0").is_err());
+        assert!(parse("[mini]\nsize = 79").is_err());
+        assert_eq!(parse("[mini]\nsize = 80").unwrap().mini.size, 80);
+    }
+
+    #[test]
+    fn test_parse_ring() {
+        let config = parse(
+            "[ring]\nstyle = \"ring\"\nthickness = 0.5\nticks = \"warning\"\ndirection = \"counter_clockwise\"\nfill = \"fill\"",
+        )
+        .unwrap();
+        assert_eq!(config.ring.style, RingStyle::Ring);
+        assert_eq!(config.ring.thickness, 0.5);
+        assert_eq!(config.ring.ticks, RingTicks::Warning);
+        assert_eq!(config.ring.direction, RingDirection::CounterClockwise);
+        assert_eq!(config.ring.fill, RingFill::Fill);
+        assert!(!config.ring.show_rounds);
+        assert!(parse("[ring]\nstyle = \"donut\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_theme() {
+        let config = parse(
+            "[theme]\nmode = \"high_contrast\"\n[theme.colors]\nring = \"#ff8800\"\nwarning = \"#c00a\"",
+        )
+        .unwrap();
+        assert_eq!(config.theme.mode, ThemeMode::HighContrast);
+        assert_eq!(
+            config.theme.colors.entries().collect::<Vec<_>>(),
+            vec![("ring", "#ff8800"), ("warning", "#c00a")]
+        );
+        assert_eq!(parse("").unwrap().theme.mode, ThemeMode::System);
+    }
+
+    #[test]
+    fn test_theme_colors_must_be_hex() {
+        assert!(parse("[theme.colors]\nring = \"blue\"").is_err());
+        assert!(parse("[theme.colors]\nring = \"ff8800\"").is_err());
+        assert!(parse("[theme.colors]\nring = \"#ff88\"").is_ok());
+        assert!(parse("[theme.colors]\nring = \"#ffé\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        let config = parse("[keys]\ntoggle = \"enter\"\nstep_secs = 30").unwrap();
+        assert_eq!(config.keys.toggle, KeyNames::from("enter"));
+        assert_eq!(config.keys.stop, KeyNames::from("escape"));
+        assert_eq!(config.keys.step_secs, 30);
+
+        let config = parse("[keys]\nbig_clock = [\"b\", \"f12\"]\nhelp = []").unwrap();
+        assert_eq!(config.keys.big_clock.names(), ["b", "f12"]);
+        assert!(config.keys.help.names().is_empty());
+
+        assert!(parse("[keys]\nmute = \"space\"").is_err());
+        assert!(parse("[keys]\nmute = \"hyper\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_rounds() {
+        let config = parse(
+            "[[rounds]]\nlabel = \"Push-ups\"\nnotes = \"Elbows in\"\n\n[[rounds]]\nlabel = \"Squats\"\nimage = \"squat.png\"",
+        )
+        .unwrap();
+        assert_eq!(config.rounds.len(), 2);
+        assert_eq!(config.rounds[0].label, "Push-ups");
+        assert_eq!(config.rounds[0].notes.as_deref(), Some("Elbows in"));
+        assert_eq!(config.rounds[1].image, Some(PathBuf::from("squat.png")));
+        assert!(parse("").unwrap().rounds.is_empty());
+    }
+
+    #[test]
+    fn test_parse_meeting() {
+        let config =
+            parse("[meeting]\nspeakers = [\"Ana\", \"Bo\", \"Cy\"]\nshuffle = true").unwrap();
+        assert_eq!(config.meeting.speakers, ["Ana", "Bo", "Cy"]);
+        assert!(config.meeting.shuffle);
+
+        assert!(parse("").unwrap().meeting.speakers.is_empty());
+        assert!(parse("[meeting]\nspeakers = [\"Ana\", \" \"]").is_err());
+    }
+
+    #[test]
+    fn test_parse_flash() {
+        let config = parse(
+            r##"
+            [flash]
+            enabled = true
+            style = "border"
+            color = "#00ffff"
+            round_end = "triple"
+            warning = "none"
+            reduced_motion = true
+            "##,
+        )
+        .unwrap();
+        assert!(config.flash.enabled);
+        assert_eq!(config.flash.style, FlashStyle::Border);
+        assert_eq!(config.flash.color, "#00ffff");
+        assert_eq!(config.flash.round_end, FlashPattern::Triple);
+        assert_eq!(config.flash.warning, FlashPattern::None);
+        assert_eq!(config.flash.finish, FlashPattern::Long);
+        assert!(config.flash.reduced_motion);
+
+        assert!(!parse("").unwrap().flash.enabled);
+        assert!(parse("[flash]\nwarning_color = \"orange\"").is_err());
+        assert!(parse("[flash]\nwarning = \"strobe\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_speech() {
+        let config = parse("[speech]\nenabled = true\ncommand = \"espeak\"").unwrap();
+        assert!(config.speech.enabled);
+        assert_eq!(config.speech.command, "espeak");
+
+        assert!(!parse("").unwrap().speech.enabled);
+        assert!(parse("[speech]\nenabled = true\ncommand = \"\"").is_err());
+    }
+
+    #[test]
+    fn test_sync_group_must_be_multicast() {
+        assert!(parse("[sync]\ngroup = \"192.168.1.10:47474\"").is_err());
+        assert!(parse("[sync]\ngroup = \"239.255.77.77\"").is_err());
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        assert!(parse("[hooks]\non_lunch = \"eat\"").is_err());
+    }
+
+    #[test]
+    fn test_missing_explicit_file_is_an_error() {
+        let path = Path::new("/nonexistent/round-timer/config.toml");
+        assert!(load(Some(path)).is_err());
+    }
+}
@@ -0,0 +1,255 @@
+use iced::futures::channel::mpsc;
+use iced::futures::SinkExt;
+use iced::Subscription;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::watch;
+
+use crate::engine::Snapshot;
+use crate::Message;
+
+/// How long to wait after a failed `accept`, which would otherwise fail
+/// again straight away, for example while out of file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(500);
+
+pub fn socket_path() -> PathBuf {
+    // The temporary directory is shared, so keep each user's socket in a
+    // directory of their own
+    let dir = dirs::runtime_dir()
+        .unwrap_or_else(|| std::env::temp_dir().join(format!("round-timer-{}", uid())));
+    dir.join("round-timer.sock")
+}
+
+fn uid() -> u32 {
+    // SAFETY: getuid has no preconditions and cannot fail
+    unsafe { libc::getuid() }
+}
+
+/// Creates `dir` for this user alone, or checks that it already is theirs
+/// alone, so that no one else can connect to a socket inside it, even
+/// before its own permissions are set.
+fn make_private(dir: &Path) -> io::Result<()> {
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => return Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(err) => return Err(err),
+    }
+
+    let metadata = std::fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid() || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a private directory", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// The bound control socket, whose file is removed when the timer exits.
+struct Listener {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Listens on the control socket and turns incoming commands into [`Message`]s.
+pub fn subscription(status: watch::Receiver<Snapshot>) -> Subscription<Message> {
+    Subscription::run_with_id(
+        "control-socket",
+        iced::stream::channel(16, move |output| async move {
+            let path = socket_path();
+            match bind(&path).await {
+                Ok(listener) => serve(listener, status, output).await,
+                Err(err) => eprintln!(
+                    "round-timer: control socket {} unavailable: {}",
+                    path.display(),
+                    err
+                ),
+            }
+        }),
+    )
+}
+
+async fn bind(path: &Path) -> io::Result<Listener> {
+    if let Some(dir) = path.parent() {
+        make_private(dir)?;
+    }
+    if path.exists() {
+        // A socket left behind by a crashed instance refuses connections
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "another instance is already listening",
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+
+    let listener = Listener {
+        listener: UnixListener::bind(path)?,
+        path: path.to_path_buf(),
+    };
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+async fn serve(
+    listener: Listener,
+    status: watch::Receiver<Snapshot>,
+    output: mpsc::Sender<Message>,
+) {
+    loop {
+        match listener.listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_connection(stream, status.clone(), output.clone()));
+            }
+            Err(err) => {
+                eprintln!("round-timer: control socket error: {}", err);
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+            }
+        }
+    }
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    status: watch::Receiver<Snapshot>,
+    mut output: mpsc::Sender<Message>,
+) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let command = line.trim();
+        if command.is_empty() {
+            continue;
+        }
+
+        let reply = match command {
+            "status" => {
+                serde_json::to_string(&*status.borrow()).expect("snapshots always serialize")
+            }
+            command => match Message::from_command(command) {
+                Some(message) => match output.send(message).await {
+                    Ok(()) => String::from("ok"),
+                    Err(_) => String::from("error: timer is shutting down"),
+                },
+                None => format!("error: unknown command '{}'", command),
+            },
+        };
+
+        if writer
+            .write_all(format!("{}\n", reply).as_bytes())
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Sends one command to the running instance and returns its reply.
+pub fn send(command: &str) -> io::Result<String> {
+    send_to(&socket_path(), command)
+}
+
+fn send_to(path: &Path, command: &str) -> io::Result<String> {
+    let mut stream = std::os::unix::net::UnixStream::connect(path)?;
+    writeln!(stream, "{}", command)?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    Ok(reply.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use iced::futures::StreamExt;
+
+    #[test]
+    fn test_message_for_known_commands() {
+        assert!(matches!(
+            Message::from_command("start"),
+            Some(Message::Start)
+        ));
+        assert!(matches!(
+            Message::from_command("toggle"),
+            Some(Message::Toggle)
+        ));
+        assert!(matches!(Message::from_command("skip"), Some(Message::Skip)));
+    }
+
+    #[test]
+    fn test_message_for_unknown_command() {
+        assert!(Message::from_command("dance").is_none());
+        // Status is answered by the socket itself
+        assert!(Message::from_command("status").is_none());
+    }
+
+    #[test]
+    fn test_socket_round_trip() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let dir = std::env::temp_dir().join(format!("round-timer-test-{}", std::process::id()));
+        let path = dir.join("round-timer.sock");
+        let (_status_sender, status) = watch::channel(Engine::new(10, 3, 0).snapshot());
+        let (output, mut messages) = mpsc::channel(4);
+
+        let listener = runtime.block_on(bind(&path)).unwrap();
+        runtime.spawn(serve(listener, status, output));
+
+        assert_eq!(send_to(&path, "skip").unwrap(), "ok");
+        assert!(matches!(
+            runtime.block_on(messages.next()),
+            Some(Message::Skip)
+        ));
+
+        let status = send_to(&path, "status").unwrap();
+        assert!(status.contains(r#""state":"stopped""#), "{}", status);
+        assert!(status.contains(r#""rounds":3"#), "{}", status);
+
+        assert_eq!(
+            send_to(&path, "dance").unwrap(),
+            "error: unknown command 'dance'"
+        );
+
+        // A second instance must not steal the socket
+        assert!(runtime.block_on(bind(&path)).is_err());
+        assert!(path.exists());
+
+        // Shutting down cleans up after the first
+        drop(runtime);
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::metadata(&dir).unwrap().permissions().mode() & 0o777,
+            0o700
+        );
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_socket_refuses_a_shared_directory() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let dir = std::env::temp_dir().join(format!("round-timer-shared-{}", std::process::id()));
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o777)).unwrap();
+
+        let path = dir.join("round-timer.sock");
+        let err = runtime.block_on(bind(&path)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(!path.exists());
+
+        std::fs::remove_dir(&dir).unwrap();
+    }
+}
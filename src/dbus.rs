@@ -0,0 +1,362 @@
+use iced::futures::channel::mpsc;
+use iced::futures::SinkExt;
+use iced::Subscription;
+use tokio::sync::{broadcast, watch};
+use zbus::connection::Builder;
+use zbus::object_server::{InterfaceRef, SignalContext};
+use zbus::{fdo, interface, Connection};
+
+use crate::engine::Snapshot;
+use crate::events::TimerEvent;
+use crate::Message;
+
+pub const BUS_NAME: &str = "org.roundtimer";
+pub const OBJECT_PATH: &str = "/org/roundtimer";
+
+/// The `org.roundtimer.Timer` interface exported on the session bus.
+struct Timer {
+    status: watch::Receiver<Snapshot>,
+    output: mpsc::Sender<Message>,
+}
+
+/// Hands a method call on one of our interfaces over to the timer.
+pub async fn send(output: &mpsc::Sender<Message>, message: Message) -> fdo::Result<()> {
+    output
+        .clone()
+        .send(message)
+        .await
+        .map_err(|_| fdo::Error::Failed(String::from("timer is shutting down")))
+}
+
+#[interface(name = "org.roundtimer.Timer")]
+impl Timer {
+    async fn start(&self) -> fdo::Result<()> {
+        send(&self.output, Message::Start).await
+    }
+
+    async fn pause(&self) -> fdo::Result<()> {
+        send(&self.output, Message::Pause).await
+    }
+
+    async fn resume(&self) -> fdo::Result<()> {
+        send(&self.output, Message::Resume).await
+    }
+
+    async fn toggle(&self) -> fdo::Result<()> {
+        send(&self.output, Message::Toggle).await
+    }
+
+    async fn stop(&self) -> fdo::Result<()> {
+        send(&self.output, Message::Stop).await
+    }
+
+    async fn skip(&self) -> fdo::Result<()> {
+        send(&self.output, Message::Skip).await
+    }
+
+    #[zbus(property)]
+    fn state(&self) -> String {
+        self.status.borrow().state.as_str().to_string()
+    }
+
+    #[zbus(property)]
+    fn round(&self) -> u32 {
+        self.status.borrow().round
+    }
+
+    #[zbus(property)]
+    fn rounds(&self) -> u32 {
+        self.status.borrow().rounds
+    }
+
+    // The countdowns change every second, so clients poll them instead
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn round_remaining_seconds(&self) -> u32 {
+        self.status.borrow().round_remaining_secs
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn remaining_seconds(&self) -> u32 {
+        self.status.borrow().remaining_secs
+    }
+
+    #[zbus(signal)]
+    async fn session_started(
+        ctxt: &SignalContext<'_>,
+        rounds: u32,
+        interval_secs: u32,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn round_started(ctxt: &SignalContext<'_>, round: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn warning(ctxt: &SignalContext<'_>, round: u32, remaining_secs: u32)
+        -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn chime(ctxt: &SignalContext<'_>, round: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn paused(ctxt: &SignalContext<'_>, round: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn resumed(ctxt: &SignalContext<'_>, round: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn session_stopped(ctxt: &SignalContext<'_>, round: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn session_finished(ctxt: &SignalContext<'_>, rounds: u32) -> zbus::Result<()>;
+}
+
+/// Exports the timer on the session bus, turning method calls into [`Message`]s
+/// and engine events into signals.
+pub fn subscription(
+    status: watch::Receiver<Snapshot>,
+    events: broadcast::Receiver<TimerEvent>,
+) -> Subscription<Message> {
+    Subscription::run_with_id(
+        "dbus",
+        iced::stream::channel(16, move |output| async move {
+            let result = async {
+                let timer = Timer {
+                    status: status.clone(),
+                    output,
+                };
+                let connection = connect(Builder::session()?, timer).await?;
+                serve(&connection, status, events).await
+            }
+            .await;
+
+            if let Err(err) = result {
+                eprintln!("round-timer: D-Bus service unavailable: {}", err);
+            }
+        }),
+    )
+}
+
+async fn connect(builder: Builder<'_>, timer: Timer) -> zbus::Result<Connection> {
+    builder
+        .serve_at(OBJECT_PATH, timer)?
+        .name(BUS_NAME)?
+        .build()
+        .await
+}
+
+async fn serve(
+    connection: &Connection,
+    mut status: watch::Receiver<Snapshot>,
+    mut events: broadcast::Receiver<TimerEvent>,
+) -> zbus::Result<()> {
+    let iface: InterfaceRef<Timer> = connection.object_server().interface(OBJECT_PATH).await?;
+    let mut last = status.borrow().clone();
+
+    loop {
+        tokio::select! {
+            changed = status.changed() => {
+                if changed.is_err() {
+                    return Ok(());
+                }
+                let current = status.borrow_and_update().clone();
+                let ctxt = iface.signal_context();
+                let timer = iface.get().await;
+                if current.state != last.state {
+                    timer.state_changed(ctxt).await?;
+                }
+                if current.round != last.round {
+                    timer.round_changed(ctxt).await?;
+                }
+                if current.rounds != last.rounds {
+                    timer.rounds_changed(ctxt).await?;
+                }
+                last = current;
+            }
+            event = events.recv() => match event {
+                Ok(event) => emit(iface.signal_context(), &event).await?,
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
+
+async fn emit(ctxt: &SignalContext<'_>, event: &TimerEvent) -> zbus::Result<()> {
+    match *event {
+        TimerEvent::SessionStarted {
+            rounds,
+            interval_secs,
+        } => Timer::session_started(ctxt, rounds, interval_secs).await,
+        TimerEvent::RoundStarted { round, .. } => Timer::round_started(ctxt, round).await,
+        TimerEvent::Warning {
+            round,
+            remaining_secs,
+        } => Timer::warning(ctxt, round, remaining_secs).await,
+        TimerEvent::Chime { round } => Timer::chime(ctxt, round).await,
+        TimerEvent::Paused { round } => Timer::paused(ctxt, round).await,
+        TimerEvent::Resumed { round } => Timer::resumed(ctxt, round).await,
+        TimerEvent::SessionStopped { round } => Timer::session_stopped(ctxt, round).await,
+        TimerEvent::SessionFinished { rounds } => Timer::session_finished(ctxt, rounds).await,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::engine::{Engine, TimerState};
+    use iced::futures::StreamExt;
+    use std::future::Future;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use zbus::object_server::Interface;
+
+    #[zbus::proxy(
+        interface = "org.roundtimer.Timer",
+        default_service = "org.roundtimer",
+        default_path = "/org/roundtimer"
+    )]
+    trait RemoteTimer {
+        fn skip(&self) -> zbus::Result<()>;
+
+        #[zbus(property)]
+        fn state(&self) -> zbus::Result<String>;
+
+        #[zbus(property)]
+        fn round_remaining_seconds(&self) -> zbus::Result<u32>;
+
+        #[zbus(signal)]
+        fn round_started(&self, round: u32) -> zbus::Result<()>;
+    }
+
+    /// A private bus so tests never touch the user's session bus.
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        fn launch() -> Option<Self> {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address=1"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take()?)
+                .read_line(&mut address)
+                .ok()?;
+            Some(Self {
+                daemon,
+                address: address.trim().to_string(),
+            })
+        }
+    }
+
+    /// Runs `test` against a private bus at the address it is given, or skips
+    /// it where `dbus-daemon` is missing.
+    pub(crate) fn on_private_bus<F>(test: impl FnOnce(String) -> F)
+    where
+        F: Future<Output = ()>,
+    {
+        let Some(bus) = PrivateBus::launch() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(test(bus.address.clone()));
+    }
+
+    /// A plain connection to the bus at `address`, as a client would have.
+    pub(crate) async fn client(address: &str) -> Connection {
+        Builder::address(address).unwrap().build().await.unwrap()
+    }
+
+    /// Stands in for the desktop's own service `name`, serving `fake` at
+    /// `path` for as long as the returned connection lives.
+    pub(crate) async fn fake_service<I: Interface>(
+        address: &str,
+        name: &str,
+        path: &str,
+        fake: I,
+    ) -> Connection {
+        Builder::address(address)
+            .unwrap()
+            .serve_at(path, fake)
+            .unwrap()
+            .name(name)
+            .unwrap()
+            .build()
+            .await
+            .unwrap()
+    }
+
+    /// Round 3 of 20, with 42 seconds left.
+    pub(crate) fn running_snapshot() -> Snapshot {
+        let mut engine = Engine::new(60, 20, 0);
+        engine.start();
+        engine.skip();
+        engine.skip();
+        for _ in 0..18 {
+            engine.tick();
+        }
+        engine.snapshot()
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    #[test]
+    fn test_service_on_private_bus() {
+        on_private_bus(|address| async move {
+            let mut engine = Engine::new(10, 3, 0);
+            let (status_sender, status) = watch::channel(engine.snapshot());
+            let (event_sender, events) = broadcast::channel(16);
+            let (output, mut messages) = mpsc::channel(4);
+
+            let timer = Timer {
+                status: status.clone(),
+                output,
+            };
+            let server = connect(Builder::address(address.as_str()).unwrap(), timer)
+                .await
+                .unwrap();
+            tokio::spawn(async move { serve(&server, status, events).await });
+
+            let client = client(&address).await;
+            let proxy = RemoteTimerProxy::builder(&client)
+                .cache_properties(zbus::proxy::CacheProperties::No)
+                .build()
+                .await
+                .unwrap();
+
+            // Method calls become messages
+            proxy.skip().await.unwrap();
+            assert!(matches!(messages.next().await, Some(Message::Skip)));
+
+            // Properties follow the published snapshot
+            assert_eq!(proxy.state().await.unwrap(), "stopped");
+            engine.start();
+            engine.tick();
+            status_sender.send_replace(engine.snapshot());
+            assert_eq!(proxy.state().await.unwrap(), TimerState::Running.as_str());
+            assert_eq!(proxy.round_remaining_seconds().await.unwrap(), 9);
+
+            // Engine events become signals
+            let mut round_started = proxy.receive_round_started().await.unwrap();
+            event_sender
+                .send(TimerEvent::RoundStarted {
+                    round: 2,
+                    label: None,
+                })
+                .unwrap();
+            let signal = round_started.next().await.unwrap();
+            assert_eq!(signal.args().unwrap().round, 2);
+        });
+    }
+}
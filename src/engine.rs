@@ -0,0 +1,969 @@
+use serde::{Deserialize, Serialize};
+
+use crate::events::TimerEvent;
+use crate::schedule::Schedule;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimerState {
+    Stopped,
+    Running,
+    Paused,
+}
+
+impl TimerState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimerState::Stopped => "stopped",
+            TimerState::Running => "running",
+            TimerState::Paused => "paused",
+        }
+    }
+}
+
+/// A point-in-time view of the timer for integrations outside the UI.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot {
+    pub state: TimerState,
+    pub round: u32,
+    pub rounds: u32,
+    pub interval_secs: u32,
+    pub round_remaining_secs: u32,
+    pub remaining_secs: u32,
+    pub lead_in_remaining_secs: u32,
+    pub lead_in_secs: u32,
+    pub warning_secs: u32,
+    pub elapsed_secs: u32,
+    /// Label of the current round from the schedule; none during the lead-in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Label of the round after this one, or of the first during the lead-in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_label: Option<String>,
+}
+
+/// The timer state machine shared by the window and the terminal front-ends.
+pub struct Engine {
+    pub interval_secs: u32,
+    pub num_rounds: u32,
+    pub timer_state: TimerState,
+    pub elapsed_secs: u32,
+    pub total_duration_secs: u32,
+    pub round_number: u32,
+    pub lead_in_secs: u32,
+    pub lead_in_remaining: u32,
+    /// Seconds before the end of each round to warn; always shorter than a
+    /// round, and set with [`set_warning`](Self::set_warning).
+    pub warning_secs: u32,
+    /// The warning as last set, which comes back when a shortened interval
+    /// is made long enough for it again.
+    wanted_warning_secs: u32,
+    pub schedule: Schedule,
+    /// Seconds each finished round of the session actually ran, in order.
+    /// Skipped rounds count only the time before the skip, and time spent
+    /// paused does not count.
+    pub round_times: Vec<u32>,
+    /// Seconds the current round has run so far.
+    pub round_secs: u32,
+}
+
+impl Engine {
+    pub fn new(interval_secs: u32, num_rounds: u32, lead_in_secs: u32) -> Self {
+        Self {
+            interval_secs,
+            num_rounds,
+            timer_state: TimerState::Stopped,
+            elapsed_secs: 0,
+            total_duration_secs: interval_secs.saturating_mul(num_rounds),
+            round_number: 1,
+            lead_in_secs,
+            lead_in_remaining: 0,
+            warning_secs: 0,
+            wanted_warning_secs: 0,
+            schedule: Schedule::default(),
+            round_times: Vec::new(),
+            round_secs: 0,
+        }
+    }
+
+    pub fn set_interval(&mut self, secs: u32) {
+        self.interval_secs = secs;
+        self.total_duration_secs = self.interval_secs.saturating_mul(self.num_rounds);
+        self.fit_warning();
+    }
+
+    pub fn set_warning(&mut self, secs: u32) {
+        self.wanted_warning_secs = secs;
+        self.fit_warning();
+    }
+
+    /// A warning as long as a round could never go off, and would colour the
+    /// whole round, so it is off while the interval is that short.
+    fn fit_warning(&mut self) {
+        self.warning_secs = if self.wanted_warning_secs < self.interval_secs {
+            self.wanted_warning_secs
+        } else {
+            0
+        };
+    }
+
+    pub fn set_rounds(&mut self, rounds: u32) {
+        self.num_rounds = rounds;
+        self.total_duration_secs = self.interval_secs.saturating_mul(self.num_rounds);
+    }
+
+    /// Starts a new session. A session already under way carries on.
+    pub fn start(&mut self) -> Vec<TimerEvent> {
+        if self.timer_state != TimerState::Stopped {
+            return Vec::new();
+        }
+        self.timer_state = TimerState::Running;
+        self.elapsed_secs = 0;
+        self.round_number = 1;
+        self.total_duration_secs = self.interval_secs.saturating_mul(self.num_rounds);
+        self.lead_in_remaining = self.lead_in_secs;
+        self.round_times.clear();
+        self.round_secs = 0;
+
+        let mut events = vec![TimerEvent::SessionStarted {
+            rounds: self.num_rounds,
+            interval_secs: self.interval_secs,
+        }];
+        if !self.in_lead_in() {
+            events.push(self.round_started());
+        }
+        events
+    }
+
+    pub fn pause(&mut self) -> Vec<TimerEvent> {
+        if self.timer_state != TimerState::Running {
+            return Vec::new();
+        }
+        self.timer_state = TimerState::Paused;
+        vec![TimerEvent::Paused {
+            round: self.round_number,
+        }]
+    }
+
+    pub fn resume(&mut self) -> Vec<TimerEvent> {
+        if self.timer_state != TimerState::Paused {
+            return Vec::new();
+        }
+        self.timer_state = TimerState::Running;
+        vec![TimerEvent::Resumed {
+            round: self.round_number,
+        }]
+    }
+
+    pub fn stop(&mut self) -> Vec<TimerEvent> {
+        let was_stopped = self.timer_state == TimerState::Stopped;
+        let round = self.round_number;
+        // The round cut short still counts, but the times stay for a summary
+        if !was_stopped && self.round_secs > 0 {
+            self.round_times.push(self.round_secs);
+        }
+        self.round_secs = 0;
+
+        self.timer_state = TimerState::Stopped;
+        self.elapsed_secs = 0;
+        self.round_number = 1;
+        self.lead_in_remaining = 0;
+
+        if was_stopped {
+            Vec::new()
+        } else {
+            vec![TimerEvent::SessionStopped { round }]
+        }
+    }
+
+    /// Advances the timer by one second.
+    pub fn tick(&mut self) -> Vec<TimerEvent> {
+        if self.timer_state != TimerState::Running {
+            return Vec::new();
+        }
+
+        if self.lead_in_remaining > 0 {
+            // Count down the lead-in and chime when the first round begins
+            self.lead_in_remaining -= 1;
+            if self.lead_in_remaining == 0 {
+                return self.complete_lead_in();
+            }
+            return Vec::new();
+        }
+
+        self.elapsed_secs += 1;
+        self.round_secs += 1;
+
+        // Check if it's time to play a chime
+        if self.elapsed_secs % self.interval_secs == 0 {
+            return self.complete_round();
+        }
+
+        if self.warning_secs > 0 && self.round_remaining_secs() == self.warning_secs {
+            return vec![TimerEvent::Warning {
+                round: self.round_number,
+                remaining_secs: self.warning_secs,
+            }];
+        }
+
+        Vec::new()
+    }
+
+    /// Moves a running timer `secs` seconds on in one step, for time that
+    /// passed without ticks. Rather than every chime on the way, returns only
+    /// where it ended up: the start of a new round, or the end of the session.
+    pub fn advance(&mut self, secs: u32) -> Vec<TimerEvent> {
+        if self.timer_state != TimerState::Running || secs == 0 {
+            return Vec::new();
+        }
+
+        let lead_in = secs.min(self.lead_in_remaining);
+        self.lead_in_remaining -= lead_in;
+        if self.in_lead_in() {
+            return Vec::new();
+        }
+        let secs = secs - lead_in;
+        let target = self.elapsed_secs.saturating_add(secs);
+
+        if target >= self.total_duration_secs {
+            self.complete_rounds_until(self.num_rounds.saturating_add(1));
+            self.elapsed_secs = self.total_duration_secs;
+            self.round_number = self.num_rounds;
+            self.timer_state = TimerState::Stopped;
+            return vec![TimerEvent::SessionFinished {
+                rounds: self.num_rounds,
+            }];
+        }
+
+        let round = target / self.interval_secs + 1;
+        let new_round = round > self.round_number;
+        if new_round {
+            self.complete_rounds_until(round);
+            self.round_number = round;
+            self.round_secs = target - (round - 1) * self.interval_secs;
+        } else {
+            self.round_secs += secs;
+        }
+        self.elapsed_secs = target;
+
+        if new_round || lead_in > 0 {
+            vec![self.round_started()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Records the times of the current round and the whole ones after it,
+    /// up to but not including `round`.
+    fn complete_rounds_until(&mut self, round: u32) {
+        let round_end = self.round_number.saturating_mul(self.interval_secs);
+        self.round_times
+            .push(self.round_secs + round_end.saturating_sub(self.elapsed_secs));
+        for _ in self.round_number + 1..round {
+            self.round_times.push(self.interval_secs);
+        }
+        self.round_secs = 0;
+    }
+
+    /// Jumps to the end of the current round (or lead-in) while running or paused.
+    pub fn skip(&mut self) -> Vec<TimerEvent> {
+        if self.timer_state == TimerState::Stopped {
+            return Vec::new();
+        }
+
+        if self.lead_in_remaining > 0 {
+            self.lead_in_remaining = 0;
+            return self.complete_lead_in();
+        }
+
+        self.elapsed_secs = (self.elapsed_secs / self.interval_secs + 1) * self.interval_secs;
+        self.complete_round()
+    }
+
+    /// Goes back to the start of the previous round, or restarts the first.
+    pub fn previous(&mut self) -> Vec<TimerEvent> {
+        if self.timer_state == TimerState::Stopped || self.in_lead_in() {
+            return Vec::new();
+        }
+
+        self.restart_round(self.round_number.saturating_sub(1).max(1))
+    }
+
+    /// Goes to the start of `round` while paused, staying paused.
+    pub fn jump_to(&mut self, round: u32) -> Vec<TimerEvent> {
+        if self.timer_state != TimerState::Paused || round == 0 || round > self.num_rounds {
+            return Vec::new();
+        }
+
+        self.lead_in_remaining = 0;
+        self.restart_round(round)
+    }
+
+    fn restart_round(&mut self, round: u32) -> Vec<TimerEvent> {
+        self.round_number = round;
+        self.elapsed_secs = (round - 1) * self.interval_secs;
+        // Rounds from here on are run again, so their times no longer count
+        self.round_times.truncate(round as usize - 1);
+        self.round_secs = 0;
+        vec![self.round_started()]
+    }
+
+    /// Gives the current round `secs` more seconds, or fewer if negative,
+    /// without going past its start or end. Returns whether it changed.
+    pub fn adjust(&mut self, secs: i32) -> bool {
+        if self.timer_state == TimerState::Stopped || self.in_lead_in() || self.interval_secs == 0 {
+            return false;
+        }
+
+        let round_start = (self.round_number - 1) * self.interval_secs;
+        let adjusted = (self.elapsed_secs as i64 - secs as i64).clamp(
+            round_start as i64,
+            (round_start + self.interval_secs - 1) as i64,
+        ) as u32;
+        let changed = adjusted != self.elapsed_secs;
+        self.elapsed_secs = adjusted;
+        changed
+    }
+
+    fn round_started(&self) -> TimerEvent {
+        TimerEvent::RoundStarted {
+            round: self.round_number,
+            label: self.schedule.label(self.round_number),
+        }
+    }
+
+    fn complete_lead_in(&self) -> Vec<TimerEvent> {
+        vec![TimerEvent::Chime { round: 0 }, self.round_started()]
+    }
+
+    fn complete_round(&mut self) -> Vec<TimerEvent> {
+        let mut events = vec![TimerEvent::Chime {
+            round: self.round_number,
+        }];
+        self.round_times.push(self.round_secs);
+        self.round_secs = 0;
+
+        // Only increment round number if we're not at the final chime
+        if self.elapsed_secs < self.total_duration_secs {
+            self.round_number += 1;
+            events.push(self.round_started());
+        }
+
+        // Check if we've reached the total duration
+        if self.elapsed_secs >= self.total_duration_secs {
+            self.timer_state = TimerState::Stopped;
+            events.push(TimerEvent::SessionFinished {
+                rounds: self.num_rounds,
+            });
+        }
+
+        events
+    }
+
+    pub fn in_lead_in(&self) -> bool {
+        self.lead_in_remaining > 0
+    }
+
+    pub fn remaining_secs(&self) -> u32 {
+        self.total_duration_secs.saturating_sub(self.elapsed_secs)
+    }
+
+    pub fn round_remaining_secs(&self) -> u32 {
+        if self.interval_secs > 0 {
+            let time_in_round = self.elapsed_secs % self.interval_secs;
+            if time_in_round == 0 {
+                self.interval_secs
+            } else {
+                self.interval_secs - time_in_round
+            }
+        } else {
+            0
+        }
+    }
+
+    /// Fraction of the whole session that has elapsed.
+    pub fn progress(&self) -> f32 {
+        if self.total_duration_secs > 0 {
+            self.elapsed_secs as f32 / self.total_duration_secs as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// Fraction of the current round (or lead-in) that is still remaining.
+    pub fn round_progress(&self) -> f32 {
+        if self.in_lead_in() {
+            self.lead_in_remaining as f32 / self.lead_in_secs as f32
+        } else if self.interval_secs > 0 {
+            self.round_remaining_secs() as f32 / self.interval_secs as f32
+        } else {
+            0.0
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            state: self.timer_state,
+            round: self.round_number,
+            rounds: self.num_rounds,
+            interval_secs: self.interval_secs,
+            round_remaining_secs: self.round_remaining_secs(),
+            remaining_secs: self.remaining_secs(),
+            lead_in_remaining_secs: self.lead_in_remaining,
+            lead_in_secs: self.lead_in_secs,
+            warning_secs: self.warning_secs,
+            elapsed_secs: self.elapsed_secs,
+            label: self.current_label(),
+            next_label: self.next_label(),
+        }
+    }
+
+    /// The current round's label, from the schedule.
+    pub fn current_label(&self) -> Option<String> {
+        if self.in_lead_in() {
+            None
+        } else {
+            self.schedule.label(self.round_number)
+        }
+    }
+
+    /// The next round's label, which during the lead-in is the first.
+    pub fn next_label(&self) -> Option<String> {
+        if self.in_lead_in() {
+            self.schedule.label(1)
+        } else if self.round_number < self.num_rounds {
+            self.schedule.label(self.round_number + 1)
+        } else {
+            None
+        }
+    }
+
+    pub fn status_text(&self) -> &'static str {
+        match self.timer_state {
+            TimerState::Stopped => "Stopped",
+            TimerState::Running if self.in_lead_in() => "Get Ready",
+            TimerState::Running => "Running",
+            TimerState::Paused => "Paused",
+        }
+    }
+}
+
+pub fn format_time(secs: u32) -> String {
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_advances_to_next_round() {
+        let mut engine = Engine::new(10, 3, 0);
+        engine.start();
+        for _ in 0..4 {
+            engine.tick();
+        }
+
+        let events = engine.skip();
+
+        assert_eq!(
+            events,
+            vec![
+                TimerEvent::Chime { round: 1 },
+                TimerEvent::RoundStarted {
+                    round: 2,
+                    label: None
+                }
+            ]
+        );
+        assert_eq!(engine.elapsed_secs, 10);
+        assert_eq!(engine.round_number, 2);
+        assert_eq!(engine.timer_state, TimerState::Running);
+    }
+
+    #[test]
+    fn test_start_does_not_restart_a_session() {
+        let mut engine = Engine::new(10, 3, 0);
+        engine.start();
+        engine.tick();
+        engine.skip();
+        engine.tick();
+
+        assert!(engine.start().is_empty());
+        engine.pause();
+        assert!(engine.start().is_empty());
+        assert_eq!(engine.round_number, 2);
+        assert_eq!(engine.elapsed_secs, 11);
+        assert_eq!(engine.timer_state, TimerState::Paused);
+    }
+
+    #[test]
+    fn test_advance_within_a_round() {
+        let mut engine = Engine::new(10, 3, 0);
+        engine.start();
+        engine.tick();
+
+        assert!(engine.advance(5).is_empty());
+        assert_eq!(engine.elapsed_secs, 6);
+        assert_eq!(engine.round_number, 1);
+        assert_eq!(engine.round_secs, 6);
+    }
+
+    #[test]
+    fn test_advance_over_rounds_reports_only_the_last() {
+        let mut engine = Engine::new(10, 5, 0);
+        engine.start();
+        engine.tick();
+
+        let events = engine.advance(23);
+
+        assert_eq!(
+            events,
+            vec![TimerEvent::RoundStarted {
+                round: 3,
+                label: None
+            }]
+        );
+        assert_eq!(engine.elapsed_secs, 24);
+        assert_eq!(engine.round_times, [10, 10]);
+        assert_eq!(engine.round_secs, 4);
+        assert_eq!(engine.timer_state, TimerState::Running);
+    }
+
+    #[test]
+    fn test_advance_out_of_the_lead_in() {
+        let mut engine = Engine::new(10, 3, 5);
+        engine.start();
+
+        assert!(engine.advance(2).is_empty());
+        assert_eq!(engine.lead_in_remaining, 3);
+
+        let events = engine.advance(7);
+        assert_eq!(
+            events,
+            vec![TimerEvent::RoundStarted {
+                round: 1,
+                label: None
+            }]
+        );
+        assert_eq!(engine.lead_in_remaining, 0);
+        assert_eq!(engine.elapsed_secs, 4);
+    }
+
+    #[test]
+    fn test_advance_past_the_end_finishes() {
+        let mut engine = Engine::new(10, 3, 0);
+        engine.start();
+        engine.tick();
+
+        let events = engine.advance(u32::MAX);
+
+        assert_eq!(events, vec![TimerEvent::SessionFinished { rounds: 3 }]);
+        assert_eq!(engine.elapsed_secs, 30);
+        assert_eq!(engine.round_number, 3);
+        assert_eq!(engine.round_times, [10, 10, 10]);
+        assert_eq!(engine.timer_state, TimerState::Stopped);
+    }
+
+    #[test]
+    fn test_advance_only_while_running() {
+        let mut engine = Engine::new(10, 3, 0);
+        assert!(engine.advance(15).is_empty());
+        engine.start();
+        engine.pause();
+        assert!(engine.advance(15).is_empty());
+        assert_eq!(engine.elapsed_secs, 0);
+    }
+
+    #[test]
+    fn test_skip_final_round_finishes_session() {
+        let mut engine = Engine::new(10, 2, 0);
+        engine.start();
+        engine.skip();
+
+        let events = engine.skip();
+
+        assert_eq!(
+            events,
+            vec![
+                TimerEvent::Chime { round: 2 },
+                TimerEvent::SessionFinished { rounds: 2 }
+            ]
+        );
+        assert_eq!(engine.elapsed_secs, 20);
+        assert_eq!(engine.round_number, 2);
+        assert_eq!(engine.timer_state, TimerState::Stopped);
+    }
+
+    #[test]
+    fn test_previous_goes_back_a_round() {
+        let mut engine = Engine::new(10, 3, 0);
+        engine.start();
+        engine.skip();
+        engine.skip();
+        engine.tick();
+
+        assert_eq!(
+            engine.previous(),
+            vec![TimerEvent::RoundStarted {
+                round: 2,
+                label: None
+            }]
+        );
+        assert_eq!(engine.elapsed_secs, 10);
+        assert_eq!(engine.round_remaining_secs(), 10);
+
+        engine.previous();
+        // The first round restarts
+        assert_eq!(
+            engine.previous(),
+            vec![TimerEvent::RoundStarted {
+                round: 1,
+                label: None
+            }]
+        );
+        assert_eq!(engine.elapsed_secs, 0);
+        assert_eq!(engine.round_number, 1);
+    }
+
+    #[test]
+    fn test_previous_does_nothing_when_stopped_or_in_lead_in() {
+        let mut engine = Engine::new(10, 3, 5);
+        assert!(engine.previous().is_empty());
+        engine.start();
+        assert!(engine.previous().is_empty());
+        assert!(engine.in_lead_in());
+    }
+
+    #[test]
+    fn test_jump_to_a_round_while_paused() {
+        let mut engine = Engine::new(10, 5, 0);
+        engine.start();
+        for _ in 0..13 {
+            engine.tick();
+        }
+        assert!(engine.jump_to(4).is_empty());
+
+        engine.pause();
+        assert_eq!(
+            engine.jump_to(4),
+            vec![TimerEvent::RoundStarted {
+                round: 4,
+                label: None
+            }]
+        );
+        assert_eq!(engine.round_number, 4);
+        assert_eq!(engine.elapsed_secs, 30);
+        assert_eq!(engine.timer_state, TimerState::Paused);
+
+        engine.jump_to(1);
+        assert_eq!(engine.elapsed_secs, 0);
+        assert!(engine.round_times.is_empty());
+
+        assert!(engine.jump_to(0).is_empty());
+        assert!(engine.jump_to(6).is_empty());
+    }
+
+    #[test]
+    fn test_jump_ends_the_lead_in() {
+        let mut engine = Engine::new(10, 3, 5);
+        engine.start();
+        engine.pause();
+        engine.jump_to(2);
+        assert!(!engine.in_lead_in());
+        assert_eq!(engine.round_number, 2);
+    }
+
+    #[test]
+    fn test_adjust_stays_within_the_round() {
+        let mut engine = Engine::new(60, 3, 0);
+        engine.start();
+        engine.skip();
+        for _ in 0..30 {
+            engine.tick();
+        }
+        assert_eq!(engine.round_remaining_secs(), 30);
+
+        assert!(engine.adjust(10));
+        assert_eq!(engine.round_remaining_secs(), 40);
+        assert!(engine.adjust(-15));
+        assert_eq!(engine.round_remaining_secs(), 25);
+
+        // No further back than the start of the round, nor past its end
+        engine.adjust(600);
+        assert_eq!(engine.elapsed_secs, 60);
+        assert!(!engine.adjust(10));
+        engine.adjust(-600);
+        assert_eq!(engine.round_remaining_secs(), 1);
+        assert_eq!(engine.round_number, 2);
+    }
+
+    #[test]
+    fn test_adjust_does_nothing_when_stopped() {
+        let mut engine = Engine::new(60, 3, 0);
+        assert!(!engine.adjust(10));
+        assert_eq!(engine.elapsed_secs, 0);
+    }
+
+    #[test]
+    fn test_skip_ends_lead_in() {
+        let mut engine = Engine::new(10, 2, 5);
+        engine.start();
+
+        let events = engine.skip();
+
+        assert!(events.contains(&TimerEvent::RoundStarted {
+            round: 1,
+            label: None
+        }));
+        assert!(!engine.in_lead_in());
+        assert_eq!(engine.elapsed_secs, 0);
+        assert_eq!(engine.round_number, 1);
+    }
+
+    #[test]
+    fn test_skip_while_paused_stays_paused() {
+        let mut engine = Engine::new(10, 3, 0);
+        engine.start();
+        engine.pause();
+
+        engine.skip();
+
+        assert_eq!(engine.round_number, 2);
+        assert_eq!(engine.timer_state, TimerState::Paused);
+    }
+
+    #[test]
+    fn test_skip_does_nothing_when_stopped() {
+        let mut engine = Engine::new(10, 3, 0);
+
+        assert!(engine.skip().is_empty());
+        assert_eq!(engine.elapsed_secs, 0);
+    }
+
+    #[test]
+    fn test_start_emits_session_and_round_started() {
+        let mut engine = Engine::new(10, 3, 0);
+
+        assert_eq!(
+            engine.start(),
+            vec![
+                TimerEvent::SessionStarted {
+                    rounds: 3,
+                    interval_secs: 10
+                },
+                TimerEvent::RoundStarted {
+                    round: 1,
+                    label: None
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lead_in_delays_round_started() {
+        let mut engine = Engine::new(10, 3, 2);
+
+        let events = engine.start();
+        assert!(!events.contains(&TimerEvent::RoundStarted {
+            round: 1,
+            label: None
+        }));
+
+        assert!(engine.tick().is_empty());
+        assert_eq!(
+            engine.tick(),
+            vec![
+                TimerEvent::Chime { round: 0 },
+                TimerEvent::RoundStarted {
+                    round: 1,
+                    label: None
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_warning_emitted_before_round_ends() {
+        let mut engine = Engine::new(10, 3, 0);
+        engine.set_warning(3);
+        engine.start();
+
+        let events: Vec<TimerEvent> = (0..7).flat_map(|_| engine.tick()).collect();
+
+        assert_eq!(
+            events,
+            vec![TimerEvent::Warning {
+                round: 1,
+                remaining_secs: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn test_warning_always_fits_in_a_round() {
+        let mut engine = Engine::new(10, 3, 0);
+        engine.set_warning(15);
+        assert_eq!(engine.warning_secs, 0);
+
+        engine.set_interval(30);
+        assert_eq!(engine.warning_secs, 15);
+
+        // Shortening the interval turns the warning off, and lengthening it
+        // again brings it back
+        engine.set_interval(15);
+        assert_eq!(engine.warning_secs, 0);
+        engine.set_interval(60);
+        assert_eq!(engine.warning_secs, 15);
+    }
+
+    #[test]
+    fn test_pause_and_resume_only_emit_on_transition() {
+        let mut engine = Engine::new(10, 3, 0);
+        assert!(engine.pause().is_empty());
+        assert!(engine.resume().is_empty());
+
+        engine.start();
+        assert_eq!(engine.pause(), vec![TimerEvent::Paused { round: 1 }]);
+        assert!(engine.pause().is_empty());
+        assert_eq!(engine.resume(), vec![TimerEvent::Resumed { round: 1 }]);
+    }
+
+    #[test]
+    fn test_stop_emits_session_stopped_once() {
+        let mut engine = Engine::new(10, 3, 0);
+        engine.start();
+        engine.skip();
+
+        assert_eq!(engine.stop(), vec![TimerEvent::SessionStopped { round: 2 }]);
+        assert!(engine.stop().is_empty());
+    }
+
+    #[test]
+    fn test_round_remaining_secs() {
+        let mut engine = Engine::new(10, 3, 0);
+        engine.start();
+        assert_eq!(engine.round_remaining_secs(), 10);
+
+        for _ in 0..3 {
+            engine.tick();
+        }
+        assert_eq!(engine.round_remaining_secs(), 7);
+        assert_eq!(engine.remaining_secs(), 27);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut engine = Engine::new(10, 3, 0);
+        engine.start();
+        engine.skip();
+        engine.tick();
+
+        assert_eq!(
+            engine.snapshot(),
+            Snapshot {
+                state: TimerState::Running,
+                round: 2,
+                rounds: 3,
+                interval_secs: 10,
+                round_remaining_secs: 9,
+                remaining_secs: 19,
+                lead_in_remaining_secs: 0,
+                lead_in_secs: 0,
+                warning_secs: 0,
+                elapsed_secs: 11,
+                label: None,
+                next_label: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_round_times_record_how_long_each_round_ran() {
+        let mut engine = Engine::new(10, 3, 2);
+        engine.start();
+        // The lead-in is not part of any round
+        engine.tick();
+        engine.tick();
+
+        for _ in 0..4 {
+            engine.tick();
+        }
+        engine.skip();
+        engine.pause();
+        assert!(engine.tick().is_empty());
+        engine.resume();
+        for _ in 0..10 {
+            engine.tick();
+        }
+        for _ in 0..3 {
+            engine.tick();
+        }
+        assert_eq!(engine.round_times, [4, 10]);
+
+        engine.stop();
+        assert_eq!(engine.round_times, [4, 10, 3]);
+        assert_eq!(engine.round_secs, 0);
+
+        engine.start();
+        assert!(engine.round_times.is_empty());
+    }
+
+    #[test]
+    fn test_previous_forgets_the_time_of_the_round_run_again() {
+        let mut engine = Engine::new(10, 3, 0);
+        engine.start();
+        for _ in 0..10 {
+            engine.tick();
+        }
+        for _ in 0..10 {
+            engine.tick();
+        }
+        engine.tick();
+        assert_eq!(engine.round_times, [10, 10]);
+
+        engine.previous();
+        assert_eq!(engine.round_times, [10]);
+        assert_eq!(engine.round_secs, 0);
+    }
+
+    #[test]
+    fn test_labels_come_from_the_schedule() {
+        use crate::config::RoundConfig;
+        let round = |label: &str| RoundConfig {
+            label: label.to_string(),
+            ..RoundConfig::default()
+        };
+        let mut engine = Engine::new(10, 3, 2);
+        engine.schedule = Schedule::new(vec![round("Push-ups"), round("Squats")], None);
+
+        engine.start();
+        // The lead-in looks ahead to the first round
+        assert_eq!(engine.snapshot().label, None);
+        assert_eq!(engine.snapshot().next_label.as_deref(), Some("Push-ups"));
+
+        engine.tick();
+        assert!(engine.tick().contains(&TimerEvent::RoundStarted {
+            round: 1,
+            label: Some(String::from("Push-ups"))
+        }));
+        assert_eq!(engine.snapshot().next_label.as_deref(), Some("Squats"));
+
+        assert!(engine.skip().contains(&TimerEvent::RoundStarted {
+            round: 2,
+            label: Some(String::from("Squats"))
+        }));
+        engine.skip();
+        // The schedule starts over, and the last round has nothing after it
+        let status = engine.snapshot();
+        assert_eq!(status.label.as_deref(), Some("Push-ups"));
+        assert_eq!(status.next_label, None);
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "00:00");
+        assert_eq!(format_time(90), "01:30");
+        assert_eq!(format_time(3600), "60:00");
+    }
+}
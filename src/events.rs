@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Transitions reported by the [`Engine`](crate::engine::Engine).
+///
+/// Rounds are numbered from 1; a chime for round 0 marks the end of the lead-in.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TimerEvent {
+    SessionStarted {
+        rounds: u32,
+        interval_secs: u32,
+    },
+    RoundStarted {
+        round: u32,
+        /// From the schedule, for rounds that have one.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+    },
+    Warning {
+        round: u32,
+        remaining_secs: u32,
+    },
+    Chime {
+        round: u32,
+    },
+    Paused {
+        round: u32,
+    },
+    Resumed {
+        round: u32,
+    },
+    SessionStopped {
+        round: u32,
+    },
+    SessionFinished {
+        rounds: u32,
+    },
+}
+
+/// The kinds of event that hooks, webhooks and notifications are set up
+/// for. Notifications name them as they are here, hooks and webhooks with an
+/// `on_` in front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Start,
+    RoundStart,
+    RoundEnd,
+    Warning,
+    Pause,
+    Resume,
+    Stop,
+    Finish,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 8] = [
+        EventKind::Start,
+        EventKind::RoundStart,
+        EventKind::RoundEnd,
+        EventKind::Warning,
+        EventKind::Pause,
+        EventKind::Resume,
+        EventKind::Stop,
+        EventKind::Finish,
+    ];
+
+    /// The config key of the hook or webhook, such as `on_round_end`.
+    pub fn hook_name(self) -> &'static str {
+        match self {
+            EventKind::Start => "on_start",
+            EventKind::RoundStart => "on_round_start",
+            EventKind::RoundEnd => "on_round_end",
+            EventKind::Warning => "on_warning",
+            EventKind::Pause => "on_pause",
+            EventKind::Resume => "on_resume",
+            EventKind::Stop => "on_stop",
+            EventKind::Finish => "on_finish",
+        }
+    }
+}
+
+impl TimerEvent {
+    /// What hooks, webhooks and notifications know the event as. A chime for
+    /// round 0 ends the lead-in rather than a round, so it has no kind.
+    pub fn kind(&self) -> Option<EventKind> {
+        match self {
+            TimerEvent::SessionStarted { .. } => Some(EventKind::Start),
+            TimerEvent::RoundStarted { .. } => Some(EventKind::RoundStart),
+            TimerEvent::Chime { round: 0 } => None,
+            TimerEvent::Chime { .. } => Some(EventKind::RoundEnd),
+            TimerEvent::Warning { .. } => Some(EventKind::Warning),
+            TimerEvent::Paused { .. } => Some(EventKind::Pause),
+            TimerEvent::Resumed { .. } => Some(EventKind::Resume),
+            TimerEvent::SessionStopped { .. } => Some(EventKind::Stop),
+            TimerEvent::SessionFinished { .. } => Some(EventKind::Finish),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    timestamp_ms: u64,
+    #[serde(flatten)]
+    event: &'a TimerEvent,
+}
+
+pub fn timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Formats an event as a single line of JSON.
+pub fn to_json(event: &TimerEvent, timestamp_ms: u64) -> String {
+    serde_json::to_string(&Record {
+        timestamp_ms,
+        event,
+    })
+    .expect("timer events always serialize")
+}
+
+/// Writes an event to stdout as JSON Lines, flushing so consumers see it immediately.
+pub fn emit_json(event: &TimerEvent) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{}", to_json(event, timestamp_ms()));
+    let _ = stdout.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_uses_snake_case_event_name() {
+        let json = to_json(
+            &TimerEvent::RoundStarted {
+                round: 3,
+                label: None,
+            },
+            1000,
+        );
+        assert_eq!(
+            json,
+            r#"{"timestamp_ms":1000,"event":"round_started","round":3}"#
+        );
+    }
+
+    #[test]
+    fn test_json_includes_label_when_set() {
+        let json = to_json(
+            &TimerEvent::RoundStarted {
+                round: 1,
+                label: Some(String::from("Squats")),
+            },
+            1000,
+        );
+        assert_eq!(
+            json,
+            r#"{"timestamp_ms":1000,"event":"round_started","round":1,"label":"Squats"}"#
+        );
+    }
+
+    #[test]
+    fn test_event_kinds() {
+        assert_eq!(
+            TimerEvent::SessionStarted {
+                rounds: 3,
+                interval_secs: 60
+            }
+            .kind(),
+            Some(EventKind::Start)
+        );
+        assert_eq!(
+            TimerEvent::Chime { round: 2 }.kind(),
+            Some(EventKind::RoundEnd)
+        );
+        assert_eq!(
+            TimerEvent::SessionFinished { rounds: 3 }.kind(),
+            Some(EventKind::Finish)
+        );
+    }
+
+    #[test]
+    fn test_lead_in_chime_has_no_kind() {
+        assert_eq!(TimerEvent::Chime { round: 0 }.kind(), None);
+    }
+
+    #[test]
+    fn test_hook_names_match_the_config_names() {
+        for kind in EventKind::ALL {
+            let name = kind.hook_name().strip_prefix("on_").unwrap();
+            let parsed: EventKind = serde_json::from_str(&format!("\"{}\"", name)).unwrap();
+            assert_eq!(parsed, kind);
+        }
+    }
+
+    #[test]
+    fn test_json_includes_all_fields() {
+        let json = to_json(
+            &TimerEvent::Warning {
+                round: 2,
+                remaining_secs: 10,
+            },
+            42,
+        );
+        assert_eq!(
+            json,
+            r#"{"timestamp_ms":42,"event":"warning","round":2,"remaining_secs":10}"#
+        );
+    }
+
+    #[test]
+    fn test_json_is_single_line() {
+        let json = to_json(
+            &TimerEvent::SessionStarted {
+                rounds: 20,
+                interval_secs: 60,
+            },
+            0,
+        );
+        assert!(!json.contains('\n'));
+    }
+}
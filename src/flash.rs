@@ -0,0 +1,175 @@
+use iced::Color;
+use std::time::{Duration, Instant};
+
+use crate::config::{FlashConfig, FlashPattern};
+use crate::events::TimerEvent;
+
+/// How long a flash stays lit with `reduced_motion`.
+const STEADY: Duration = Duration::from_millis(1500);
+
+/// A visual alert in progress, lighting the window up in a pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flash {
+    started: Instant,
+    /// Alternately lit and dark, starting lit.
+    steps: Vec<Duration>,
+    color: Color,
+}
+
+/// Lit and dark times of each pattern, in milliseconds.
+fn steps(pattern: FlashPattern) -> &'static [u64] {
+    match pattern {
+        FlashPattern::None => &[],
+        FlashPattern::Single => &[300],
+        FlashPattern::Double => &[200, 150, 200],
+        FlashPattern::Triple => &[150, 100, 150, 100, 150],
+        FlashPattern::Long => &[1000],
+    }
+}
+
+impl Flash {
+    /// The flash for `event`, if it is one that chimes or warns and its
+    /// pattern is not `none`.
+    pub fn for_event(event: &TimerEvent, config: &FlashConfig, now: Instant) -> Option<Self> {
+        let (pattern, color) = match event {
+            TimerEvent::Chime { .. } => (config.round_end, &config.color),
+            TimerEvent::SessionFinished { .. } => (config.finish, &config.color),
+            TimerEvent::Warning { .. } => (config.warning, &config.warning_color),
+            _ => return None,
+        };
+        if pattern == FlashPattern::None {
+            return None;
+        }
+
+        let steps = if config.reduced_motion {
+            vec![STEADY]
+        } else {
+            steps(pattern)
+                .iter()
+                .map(|&millis| Duration::from_millis(millis))
+                .collect()
+        };
+        Some(Self {
+            started: now,
+            steps,
+            // The config was checked when it was read
+            color: Color::parse(color).unwrap_or(Color::WHITE),
+        })
+    }
+
+    /// The colour to light the window with at `now`, or `None` between
+    /// blinks and once the flash is over.
+    pub fn color_at(&self, now: Instant) -> Option<Color> {
+        let mut elapsed = now.saturating_duration_since(self.started);
+        for (index, &step) in self.steps.iter().enumerate() {
+            if elapsed < step {
+                return (index % 2 == 0).then_some(self.color);
+            }
+            elapsed -= step;
+        }
+        None
+    }
+
+    pub fn is_over(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.started) >= self.steps.iter().sum()
+    }
+}
+
+/// Black or white, whichever reads better on `background`.
+pub fn text_color(background: Color) -> Color {
+    let luminance = 0.299 * background.r + 0.587 * background.g + 0.114 * background.b;
+    if luminance > 0.5 {
+        Color::BLACK
+    } else {
+        Color::WHITE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> FlashConfig {
+        FlashConfig {
+            enabled: true,
+            ..FlashConfig::default()
+        }
+    }
+
+    fn after(flash: &Flash, millis: u64) -> Option<Color> {
+        flash.color_at(flash.started + Duration::from_millis(millis))
+    }
+
+    #[test]
+    fn test_events_that_flash() {
+        let now = Instant::now();
+        let config = config();
+
+        let chime = Flash::for_event(&TimerEvent::Chime { round: 1 }, &config, now).unwrap();
+        assert_eq!(chime.color, Color::WHITE);
+        assert_eq!(chime.steps.len(), 3);
+
+        let warning = TimerEvent::Warning {
+            round: 1,
+            remaining_secs: 10,
+        };
+        let warning = Flash::for_event(&warning, &config, now).unwrap();
+        assert_eq!(warning.color, Color::from_rgb8(0xff, 0x99, 0x00));
+
+        let finished = TimerEvent::SessionFinished { rounds: 3 };
+        let finished = Flash::for_event(&finished, &config, now).unwrap();
+        assert_eq!(finished.steps, [Duration::from_millis(1000)]);
+
+        assert_eq!(
+            Flash::for_event(&TimerEvent::Paused { round: 1 }, &config, now),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pattern_none_does_not_flash() {
+        let config = FlashConfig {
+            round_end: FlashPattern::None,
+            ..config()
+        };
+        let chime = TimerEvent::Chime { round: 1 };
+        assert_eq!(Flash::for_event(&chime, &config, Instant::now()), None);
+    }
+
+    #[test]
+    fn test_double_flash_blinks_twice() {
+        let chime = TimerEvent::Chime { round: 1 };
+        let flash = Flash::for_event(&chime, &config(), Instant::now()).unwrap();
+
+        assert_eq!(after(&flash, 0), Some(Color::WHITE));
+        assert_eq!(after(&flash, 199), Some(Color::WHITE));
+        assert_eq!(after(&flash, 250), None);
+        assert_eq!(after(&flash, 400), Some(Color::WHITE));
+        assert_eq!(after(&flash, 550), None);
+        assert!(!flash.is_over(flash.started + Duration::from_millis(549)));
+        assert!(flash.is_over(flash.started + Duration::from_millis(550)));
+    }
+
+    #[test]
+    fn test_reduced_motion_stays_lit() {
+        let config = FlashConfig {
+            round_end: FlashPattern::Triple,
+            reduced_motion: true,
+            ..config()
+        };
+        let chime = TimerEvent::Chime { round: 1 };
+        let flash = Flash::for_event(&chime, &config, Instant::now()).unwrap();
+
+        for millis in (0..1500).step_by(100) {
+            assert_eq!(after(&flash, millis), Some(Color::WHITE));
+        }
+        assert_eq!(after(&flash, 1500), None);
+    }
+
+    #[test]
+    fn test_text_color_contrasts() {
+        assert_eq!(text_color(Color::WHITE), Color::BLACK);
+        assert_eq!(text_color(Color::from_rgb(1.0, 0.6, 0.0)), Color::BLACK);
+        assert_eq!(text_color(Color::from_rgb(0.5, 0.0, 0.0)), Color::WHITE);
+    }
+}
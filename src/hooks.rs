@@ -0,0 +1,255 @@
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::config::HooksConfig;
+use crate::events::{self, TimerEvent};
+
+/// Runs the user's shell commands for timer events in the background.
+pub struct Hooks {
+    config: HooksConfig,
+    quiet: bool,
+}
+
+impl Hooks {
+    /// When `quiet` is set, hook output and failures are discarded instead of
+    /// going to stderr, e.g. while the terminal front-end owns the screen.
+    pub fn new(config: HooksConfig, quiet: bool) -> Self {
+        Self { config, quiet }
+    }
+
+    fn command_for(&self, event: &TimerEvent) -> Option<(&'static str, &str)> {
+        let kind = event.kind()?;
+        self.config
+            .command(kind)
+            .map(|command| (kind.hook_name(), command))
+    }
+
+    /// Spawns the hook for `event`, if one is configured, on the current tokio
+    /// runtime. Never waits for the command to finish.
+    pub fn run(&self, event: &TimerEvent) {
+        let Some((name, command)) = self.command_for(event) else {
+            return;
+        };
+        let quiet = self.quiet;
+
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            if !quiet {
+                eprintln!("round-timer: {} hook skipped: no async runtime", name);
+            }
+            return;
+        };
+
+        let command = command.to_string();
+        let env = environment(event, events::timestamp_ms());
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+
+        runtime.spawn(async move {
+            if let Err(message) = run_command(&command, env, timeout, quiet).await {
+                if !quiet {
+                    eprintln!("round-timer: {} hook {}", name, message);
+                }
+            }
+        });
+    }
+}
+
+/// Exposes the event's fields as `ROUND_TIMER_*` variables, plus the whole
+/// event as `ROUND_TIMER_JSON`.
+fn environment(event: &TimerEvent, timestamp_ms: u64) -> Vec<(String, String)> {
+    let json = events::to_json(event, timestamp_ms);
+    let mut env = vec![(String::from("ROUND_TIMER_JSON"), json.clone())];
+
+    if let Ok(serde_json::Value::Object(fields)) = serde_json::from_str(&json) {
+        for (key, value) in fields {
+            let value = match value {
+                serde_json::Value::String(value) => value,
+                value => value.to_string(),
+            };
+            env.push((format!("ROUND_TIMER_{}", key.to_uppercase()), value));
+        }
+    }
+    env
+}
+
+async fn run_command(
+    command: &str,
+    env: Vec<(String, String)>,
+    timeout: Duration,
+    quiet: bool,
+) -> Result<(), String> {
+    let stderr = if quiet {
+        Stdio::null()
+    } else {
+        Stdio::inherit()
+    };
+
+    // stdout may carry the JSON event stream, so hooks never write to it
+    let mut child = shell(command)
+        .envs(env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(stderr)
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| format!("could not be started: {}", err))?;
+
+    match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(Ok(status)) if status.success() => Ok(()),
+        Ok(Ok(status)) => Err(format!("failed: {}", status)),
+        Ok(Err(err)) => Err(format!("failed: {}", err)),
+        Err(_) => {
+            let _ = child.kill().await;
+            Err(format!("timed out after {:?}", timeout))
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hooks_config() -> HooksConfig {
+        HooksConfig {
+            on_round_start: Some(String::from("echo start")),
+            on_round_end: Some(String::from("echo end")),
+            ..HooksConfig::default()
+        }
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_command_for_configured_event() {
+        let hooks = Hooks::new(hooks_config(), true);
+        assert_eq!(
+            hooks.command_for(&TimerEvent::RoundStarted {
+                round: 2,
+                label: None
+            }),
+            Some(("on_round_start", "echo start"))
+        );
+        assert_eq!(
+            hooks.command_for(&TimerEvent::Chime { round: 2 }),
+            Some(("on_round_end", "echo end"))
+        );
+    }
+
+    #[test]
+    fn test_command_for_unconfigured_event() {
+        let hooks = Hooks::new(hooks_config(), true);
+        assert_eq!(hooks.command_for(&TimerEvent::Paused { round: 1 }), None);
+    }
+
+    #[test]
+    fn test_lead_in_chime_is_not_a_round_end() {
+        let hooks = Hooks::new(hooks_config(), true);
+        assert_eq!(hooks.command_for(&TimerEvent::Chime { round: 0 }), None);
+    }
+
+    #[test]
+    fn test_environment_exposes_event_fields() {
+        let env = environment(
+            &TimerEvent::Warning {
+                round: 3,
+                remaining_secs: 10,
+            },
+            1234,
+        );
+
+        let get = |key: &str| {
+            env.iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(get("ROUND_TIMER_EVENT"), Some("warning"));
+        assert_eq!(get("ROUND_TIMER_ROUND"), Some("3"));
+        assert_eq!(get("ROUND_TIMER_REMAINING_SECS"), Some("10"));
+        assert_eq!(get("ROUND_TIMER_TIMESTAMP_MS"), Some("1234"));
+        assert!(get("ROUND_TIMER_JSON").unwrap().contains("\"warning\""));
+    }
+
+    #[test]
+    fn test_environment_exposes_round_label() {
+        let label = |event: &TimerEvent| {
+            environment(event, 0)
+                .into_iter()
+                .find(|(name, _)| name == "ROUND_TIMER_LABEL")
+                .map(|(_, value)| value)
+        };
+        let labelled = TimerEvent::RoundStarted {
+            round: 1,
+            label: Some(String::from("Push-ups")),
+        };
+        assert_eq!(label(&labelled).as_deref(), Some("Push-ups"));
+
+        let unlabelled = TimerEvent::RoundStarted {
+            round: 1,
+            label: None,
+        };
+        assert_eq!(label(&unlabelled), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_passes_environment() {
+        let env = environment(
+            &TimerEvent::RoundStarted {
+                round: 3,
+                label: None,
+            },
+            0,
+        );
+        let result = block_on(run_command(
+            "test \"$ROUND_TIMER_ROUND\" = 3",
+            env,
+            Duration::from_secs(5),
+            true,
+        ));
+        assert_eq!(result, Ok(()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_reports_failure() {
+        let result = block_on(run_command(
+            "exit 3",
+            Vec::new(),
+            Duration::from_secs(5),
+            true,
+        ));
+        assert!(result.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_times_out() {
+        let result = block_on(run_command(
+            "sleep 5",
+            Vec::new(),
+            Duration::from_millis(100),
+            true,
+        ));
+        assert_eq!(result, Err(String::from("timed out after 100ms")));
+    }
+}
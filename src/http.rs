@@ -0,0 +1,483 @@
+use iced::futures::channel::mpsc;
+use iced::futures::SinkExt;
+use iced::Subscription;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, watch};
+
+use crate::config::HttpConfig;
+use crate::engine::Snapshot;
+use crate::events::{self, TimerEvent};
+use crate::Message;
+
+const REMOTE_PAGE: &str = include_str!("../assets/remote.html");
+const OVERLAY_PAGE: &str = include_str!("../assets/overlay.html");
+
+/// Requests with longer heads than this are rejected.
+const MAX_HEAD_BYTES: usize = 8 * 1024;
+
+/// Proxies drop idle connections, so the event stream sends a comment this often.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Clients that take longer than this to send their request are dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait after a failed `accept`, which would otherwise fail
+/// again straight away, for example while out of file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(500);
+
+#[derive(Debug, PartialEq)]
+struct Request {
+    method: String,
+    path: String,
+    bearer_token: Option<String>,
+}
+
+/// Reads the request line and headers; request bodies are never needed.
+async fn read_request<R: AsyncRead + Unpin>(reader: R) -> io::Result<Request> {
+    let mut reader = BufReader::new(reader);
+    let mut head_bytes = 0;
+    let mut lines = Vec::new();
+
+    loop {
+        let mut line = String::new();
+        let read = reader.read_line(&mut line).await?;
+        head_bytes += read;
+        if read == 0 || head_bytes > MAX_HEAD_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "incomplete request",
+            ));
+        }
+        let line = line.trim_end().to_string();
+        if line.is_empty() {
+            break;
+        }
+        lines.push(line);
+    }
+
+    parse_head(&lines)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed request"))
+}
+
+fn parse_head(lines: &[String]) -> Option<Request> {
+    let (request_line, headers) = lines.split_first()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?;
+    let path = target.split('?').next().unwrap_or(target).to_string();
+
+    let bearer_token = headers.iter().find_map(|header| {
+        let (name, value) = header.split_once(':')?;
+        if !name.trim().eq_ignore_ascii_case("authorization") {
+            return None;
+        }
+        value
+            .trim()
+            .strip_prefix("Bearer ")
+            .map(|token| token.trim().to_string())
+    });
+
+    Some(Request {
+        method,
+        path,
+        bearer_token,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum Route {
+    Page,
+    Overlay,
+    Status,
+    EventStream,
+    Command(String),
+    MethodNotAllowed,
+    NotFound,
+}
+
+fn route(request: &Request) -> Route {
+    let command = request.path.strip_prefix("/api/");
+    match (request.method.as_str(), request.path.as_str(), command) {
+        ("GET", "/", _) => Route::Page,
+        ("GET", "/overlay", _) => Route::Overlay,
+        ("GET", "/api/status", _) => Route::Status,
+        ("GET", "/events", _) => Route::EventStream,
+        ("POST", _, Some(command)) if Message::from_command(command).is_some() => {
+            Route::Command(command.to_string())
+        }
+        (_, "/" | "/overlay" | "/api/status" | "/events", _) => Route::MethodNotAllowed,
+        (_, _, Some(command)) if Message::from_command(command).is_some() => {
+            Route::MethodNotAllowed
+        }
+        _ => Route::NotFound,
+    }
+}
+
+/// Compares tokens in time that depends only on their lengths, so the reply
+/// time gives nothing away about how much of a guess was right.
+fn tokens_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn response(status: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+fn json_error(status: &str, message: &str) -> String {
+    let body = serde_json::json!({ "error": message }).to_string();
+    response(status, "application/json", &body)
+}
+
+/// Serves the remote display page, its live event stream and the control API.
+pub fn subscription(
+    config: HttpConfig,
+    status: watch::Receiver<Snapshot>,
+    events: broadcast::Receiver<TimerEvent>,
+) -> Subscription<Message> {
+    Subscription::run_with_id(
+        "http",
+        iced::stream::channel(16, move |output| async move {
+            match TcpListener::bind(&config.bind).await {
+                Ok(listener) => serve(listener, config.token, status, events, output).await,
+                Err(err) => eprintln!(
+                    "round-timer: HTTP server on {} unavailable: {}",
+                    config.bind, err
+                ),
+            }
+        }),
+    )
+}
+
+async fn serve(
+    listener: TcpListener,
+    token: Option<String>,
+    status: watch::Receiver<Snapshot>,
+    events: broadcast::Receiver<TimerEvent>,
+    output: mpsc::Sender<Message>,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_connection(
+                    stream,
+                    token.clone(),
+                    status.clone(),
+                    events.resubscribe(),
+                    output.clone(),
+                ));
+            }
+            Err(err) => {
+                eprintln!("round-timer: HTTP server error: {}", err);
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+            }
+        }
+    }
+}
+
+async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    token: Option<String>,
+    status: watch::Receiver<Snapshot>,
+    events: broadcast::Receiver<TimerEvent>,
+    mut output: mpsc::Sender<Message>,
+) {
+    let request = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => request,
+        Err(_) => {
+            let reply = response("408 Request Timeout", "text/plain", "Request Timeout\n");
+            let _ = stream.write_all(reply.as_bytes()).await;
+            return;
+        }
+        Ok(Err(_)) => {
+            let reply = response("400 Bad Request", "text/plain", "Bad Request\n");
+            let _ = stream.write_all(reply.as_bytes()).await;
+            return;
+        }
+    };
+
+    let reply = match route(&request) {
+        Route::Page => response("200 OK", "text/html; charset=utf-8", REMOTE_PAGE),
+        Route::Overlay => response("200 OK", "text/html; charset=utf-8", OVERLAY_PAGE),
+        Route::Status => {
+            let body =
+                serde_json::to_string(&*status.borrow()).expect("snapshots always serialize");
+            response("200 OK", "application/json", &body)
+        }
+        Route::EventStream => {
+            let _ = stream_events(stream, status, events).await;
+            return;
+        }
+        Route::Command(command) => match (&token, &request.bearer_token) {
+            (None, _) => json_error(
+                "403 Forbidden",
+                "remote control is disabled; set a token in the [http] configuration",
+            ),
+            (Some(expected), Some(given)) if tokens_match(expected, given) => {
+                let message =
+                    Message::from_command(&command).expect("routes only accept known commands");
+                match output.send(message).await {
+                    Ok(()) => response("204 No Content", "text/plain", ""),
+                    Err(_) => json_error("503 Service Unavailable", "timer is shutting down"),
+                }
+            }
+            _ => json_error("401 Unauthorized", "missing or wrong bearer token"),
+        },
+        Route::MethodNotAllowed => json_error("405 Method Not Allowed", "method not allowed"),
+        Route::NotFound => json_error("404 Not Found", "not found"),
+    };
+
+    let _ = stream.write_all(reply.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Streams snapshots as `status` and engine events as `timer` server-sent
+/// events until the client goes away.
+async fn stream_events<S: AsyncWrite + Unpin>(
+    mut stream: S,
+    mut status: watch::Receiver<Snapshot>,
+    mut events: broadcast::Receiver<TimerEvent>,
+) -> io::Result<()> {
+    stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\nConnection: keep-alive\r\n\r\n",
+        )
+        .await?;
+
+    let snapshot = status.borrow_and_update().clone();
+    write_event(&mut stream, "status", &serde_json::to_string(&snapshot)?).await?;
+
+    let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+    loop {
+        tokio::select! {
+            changed = status.changed() => {
+                if changed.is_err() {
+                    return Ok(());
+                }
+                let snapshot = status.borrow_and_update().clone();
+                write_event(&mut stream, "status", &serde_json::to_string(&snapshot)?).await?;
+            }
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let json = events::to_json(&event, events::timestamp_ms());
+                    write_event(&mut stream, "timer", &json).await?;
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            _ = keepalive.tick() => {
+                stream.write_all(b": keepalive\n\n").await?;
+                stream.flush().await?;
+            }
+        }
+    }
+}
+
+async fn write_event<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    name: &str,
+    data: &str,
+) -> io::Result<()> {
+    stream
+        .write_all(format!("event: {}\ndata: {}\n\n", name, data).as_bytes())
+        .await?;
+    stream.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use iced::futures::StreamExt;
+    use std::io::{Read, Write};
+
+    fn request(method: &str, path: &str) -> Request {
+        Request {
+            method: String::from(method),
+            path: String::from(path),
+            bearer_token: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_head() {
+        let lines = [
+            String::from("POST /api/skip?now=1 HTTP/1.1"),
+            String::from("Host: localhost"),
+            String::from("authorization: Bearer secret"),
+        ];
+        assert_eq!(
+            parse_head(&lines),
+            Some(Request {
+                method: String::from("POST"),
+                path: String::from("/api/skip"),
+                bearer_token: Some(String::from("secret")),
+            })
+        );
+        assert_eq!(parse_head(&[String::from("GET")]), None);
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secret", "secreT"));
+        assert!(!tokens_match("secret", "secret2"));
+        assert!(!tokens_match("secret", ""));
+    }
+
+    #[test]
+    fn test_routes() {
+        assert_eq!(route(&request("GET", "/")), Route::Page);
+        assert_eq!(route(&request("GET", "/overlay")), Route::Overlay);
+        assert_eq!(route(&request("GET", "/events")), Route::EventStream);
+        assert_eq!(
+            route(&request("POST", "/api/toggle")),
+            Route::Command(String::from("toggle"))
+        );
+        assert_eq!(route(&request("GET", "/api/skip")), Route::MethodNotAllowed);
+        assert_eq!(route(&request("POST", "/api/dance")), Route::NotFound);
+        assert_eq!(route(&request("GET", "/favicon.ico")), Route::NotFound);
+    }
+
+    /// Sends a raw request and returns the whole response.
+    fn exchange(address: std::net::SocketAddr, request: &str) -> String {
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_server_round_trip() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (_status_sender, status) = watch::channel(Engine::new(10, 3, 0).snapshot());
+        let (_event_sender, events) = broadcast::channel(16);
+        let (output, mut messages) = mpsc::channel(4);
+
+        let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+        let address = listener.local_addr().unwrap();
+        runtime.spawn(serve(
+            listener,
+            Some(String::from("secret")),
+            status,
+            events,
+            output,
+        ));
+
+        let page = exchange(address, "GET / HTTP/1.1\r\n\r\n");
+        assert!(page.starts_with("HTTP/1.1 200 OK"), "{}", page);
+        assert!(page.contains("<html"), "{}", page);
+
+        let status = exchange(address, "GET /api/status HTTP/1.1\r\n\r\n");
+        assert!(status.contains(r#""rounds":3"#), "{}", status);
+
+        let unauthorized = exchange(address, "POST /api/skip HTTP/1.1\r\n\r\n");
+        assert!(unauthorized.starts_with("HTTP/1.1 401"), "{}", unauthorized);
+
+        let accepted = exchange(
+            address,
+            "POST /api/skip HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n",
+        );
+        assert!(accepted.starts_with("HTTP/1.1 204"), "{}", accepted);
+        assert!(matches!(
+            runtime.block_on(messages.next()),
+            Some(Message::Skip)
+        ));
+    }
+
+    #[test]
+    fn test_control_is_forbidden_without_token() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (_status_sender, status) = watch::channel(Engine::new(10, 3, 0).snapshot());
+        let (_event_sender, events) = broadcast::channel(16);
+        let (output, _messages) = mpsc::channel(4);
+
+        let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+        let address = listener.local_addr().unwrap();
+        runtime.spawn(serve(listener, None, status, events, output));
+
+        let reply = exchange(
+            address,
+            "POST /api/stop HTTP/1.1\r\nAuthorization: Bearer anything\r\n\r\n",
+        );
+        assert!(reply.starts_with("HTTP/1.1 403"), "{}", reply);
+    }
+
+    #[test]
+    fn test_slow_request_times_out() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .start_paused(true)
+            .build()
+            .unwrap();
+        let (_status_sender, status) = watch::channel(Engine::new(10, 3, 0).snapshot());
+        let (_event_sender, events) = broadcast::channel(16);
+        let (output, _messages) = mpsc::channel(4);
+
+        runtime.block_on(async {
+            let (mut client, server) = tokio::io::duplex(1024);
+            client.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+            // The rest of the head never comes
+            handle_connection(server, None, status, events, output).await;
+
+            let mut reply = String::new();
+            tokio::io::AsyncReadExt::read_to_string(&mut client, &mut reply)
+                .await
+                .unwrap();
+            assert!(reply.starts_with("HTTP/1.1 408"), "{}", reply);
+        });
+    }
+
+    async fn next_data<R: AsyncBufReadExt + Unpin>(lines: &mut tokio::io::Lines<R>) -> String {
+        loop {
+            let line = lines.next_line().await.unwrap().unwrap();
+            if let Some(data) = line.strip_prefix("data: ") {
+                return data.to_string();
+            }
+        }
+    }
+
+    #[test]
+    fn test_event_stream() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut engine = Engine::new(10, 3, 0);
+        let (status_sender, status) = watch::channel(engine.snapshot());
+        let (event_sender, events) = broadcast::channel(16);
+
+        runtime.block_on(async {
+            let (client, server) = tokio::io::duplex(4096);
+            tokio::spawn(stream_events(server, status, events));
+            let mut lines = BufReader::new(client).lines();
+
+            assert!(next_data(&mut lines).await.contains(r#""state":"stopped""#));
+
+            engine.start();
+            status_sender.send_replace(engine.snapshot());
+            assert!(next_data(&mut lines).await.contains(r#""state":"running""#));
+
+            event_sender
+                .send(TimerEvent::RoundStarted {
+                    round: 2,
+                    label: None,
+                })
+                .unwrap();
+            assert!(next_data(&mut lines)
+                .await
+                .contains(r#""event":"round_started""#));
+        });
+    }
+}
@@ -0,0 +1,143 @@
+use iced::Subscription;
+use zbus::zvariant::OwnedFd;
+use zbus::Connection;
+
+use crate::config::PowerConfig;
+use crate::Message;
+
+const REASON: &str = "A timer session is running";
+
+#[zbus::proxy(
+    interface = "org.freedesktop.ScreenSaver",
+    default_service = "org.freedesktop.ScreenSaver",
+    default_path = "/org/freedesktop/ScreenSaver"
+)]
+trait ScreenSaver {
+    fn inhibit(&self, application_name: &str, reason_for_inhibit: &str) -> zbus::Result<u32>;
+
+    fn un_inhibit(&self, cookie: u32) -> zbus::Result<()>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Login1Manager {
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+}
+
+/// Keeps the screen awake until dropped.
+struct IdleInhibitor {
+    proxy: ScreenSaverProxy<'static>,
+    cookie: u32,
+}
+
+impl IdleInhibitor {
+    async fn acquire(connection: &Connection) -> zbus::Result<Self> {
+        let proxy = ScreenSaverProxy::new(connection).await?;
+        let cookie = proxy.inhibit("Round Timer", REASON).await?;
+        Ok(Self { proxy, cookie })
+    }
+}
+
+impl Drop for IdleInhibitor {
+    fn drop(&mut self) {
+        // The screensaver also forgets the cookie when our connection closes,
+        // but releasing it explicitly lets the screen blank right away
+        let proxy = self.proxy.clone();
+        let cookie = self.cookie;
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move { proxy.un_inhibit(cookie).await });
+        }
+    }
+}
+
+/// logind holds the lock for as long as the returned descriptor stays open.
+async fn inhibit_sleep(connection: &Connection) -> zbus::Result<OwnedFd> {
+    Login1ManagerProxy::new(connection)
+        .await?
+        .inhibit("sleep:idle", "Round Timer", REASON, "block")
+        .await
+}
+
+/// Holds idle and sleep inhibitors for as long as it is subscribed, which the
+/// window does while the timer is running.
+pub fn subscription(config: PowerConfig) -> Subscription<Message> {
+    Subscription::run_with_id(
+        "inhibit",
+        iced::stream::channel(1, move |_output| async move {
+            let _idle = if config.inhibit_idle {
+                let result = async { IdleInhibitor::acquire(&Connection::session().await?).await };
+                result
+                    .await
+                    .map_err(|err| eprintln!("round-timer: cannot keep the screen on: {}", err))
+                    .ok()
+            } else {
+                None
+            };
+
+            let _sleep = if config.inhibit_sleep {
+                let result = async { inhibit_sleep(&Connection::system().await?).await };
+                result
+                    .await
+                    .map_err(|err| eprintln!("round-timer: cannot prevent suspend: {}", err))
+                    .ok()
+            } else {
+                None
+            };
+
+            // Dropping the subscription drops the inhibitors
+            std::future::pending::<()>().await
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus::tests::{client, fake_service, on_private_bus};
+    use iced::futures::channel::mpsc;
+    use iced::futures::StreamExt;
+
+    struct FakeScreenSaver {
+        calls: mpsc::UnboundedSender<String>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.ScreenSaver")]
+    impl FakeScreenSaver {
+        fn inhibit(&self, application_name: String, _reason: String) -> u32 {
+            let _ = self
+                .calls
+                .unbounded_send(format!("inhibit {}", application_name));
+            42
+        }
+
+        fn un_inhibit(&self, cookie: u32) {
+            let _ = self.calls.unbounded_send(format!("uninhibit {}", cookie));
+        }
+    }
+
+    #[test]
+    fn test_idle_inhibitor_is_released_on_drop() {
+        on_private_bus(|address| async move {
+            let (calls_sender, mut calls) = mpsc::unbounded();
+            let _server = fake_service(
+                &address,
+                "org.freedesktop.ScreenSaver",
+                "/org/freedesktop/ScreenSaver",
+                FakeScreenSaver {
+                    calls: calls_sender,
+                },
+            )
+            .await;
+
+            let client = client(&address).await;
+            let inhibitor = IdleInhibitor::acquire(&client).await.unwrap();
+            assert_eq!(calls.next().await.unwrap(), "inhibit Round Timer");
+
+            drop(inhibitor);
+            assert_eq!(calls.next().await.unwrap(), "uninhibit 42");
+        });
+    }
+}
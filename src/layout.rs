@@ -0,0 +1,83 @@
+use iced::Size;
+
+/// The window size the base font sizes were chosen for.
+const BASE_SIZE: f32 = 768.0;
+
+/// Windows this much wider than tall put the controls beside the clock.
+const WIDE_ASPECT: f32 = 1.2;
+
+/// How the main view fits a window of a given size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    /// Controls on the left and the clock on the right, rather than stacked.
+    pub wide: bool,
+    /// Factor for font sizes and spacing.
+    pub scale: f32,
+    /// Width and height of the progress ring.
+    pub ring_size: f32,
+}
+
+impl Layout {
+    pub fn for_window(size: Size) -> Self {
+        let wide = size.width > size.height * WIDE_ASPECT;
+        let scale = (size.width.min(size.height) / BASE_SIZE).clamp(0.6, 3.0);
+
+        // The ring takes what the text leaves over in its column
+        let ring_size = if wide {
+            (size.width * 0.35).min(size.height * 0.45)
+        } else {
+            (size.width * 0.6).min(size.height * 0.25)
+        };
+
+        Self {
+            wide,
+            scale,
+            ring_size: ring_size.max(80.0).round(),
+        }
+    }
+
+    /// Scales a font size or spacing chosen for the base window size.
+    pub fn size(&self, base: f32) -> f32 {
+        (base * self.scale).round()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_window_is_wide_at_base_scale() {
+        let layout = Layout::for_window(Size::new(1024.0, 768.0));
+        assert!(layout.wide);
+        assert_eq!(layout.scale, 1.0);
+        assert_eq!(layout.size(28.0), 28.0);
+        assert_eq!(layout.ring_size, 346.0);
+    }
+
+    #[test]
+    fn test_tall_window_stacks() {
+        let layout = Layout::for_window(Size::new(500.0, 900.0));
+        assert!(!layout.wide);
+        assert_eq!(layout.ring_size, 225.0);
+    }
+
+    #[test]
+    fn test_everything_grows_with_the_window() {
+        let small = Layout::for_window(Size::new(800.0, 600.0));
+        let large = Layout::for_window(Size::new(2560.0, 1440.0));
+        assert!(large.scale > small.scale);
+        assert!(large.ring_size > small.ring_size);
+        assert!(large.size(16.0) > small.size(16.0));
+    }
+
+    #[test]
+    fn test_limits() {
+        let tiny = Layout::for_window(Size::new(100.0, 100.0));
+        assert_eq!(tiny.scale, 0.6);
+        assert_eq!(tiny.ring_size, 80.0);
+
+        let huge = Layout::for_window(Size::new(8000.0, 4000.0));
+        assert_eq!(huge.scale, 3.0);
+    }
+}
@@ -1,33 +1,227 @@
-use iced::widget::{button, column, container, progress_bar, row, text, text_input};
-use iced::{Alignment, Color, Element, Length, Subscription, Task};
+use iced::widget::{
+    button, column, container, image, mouse_area, progress_bar, row, stack, text, text_input,
+    Column, Row, Space,
+};
+use iced::{
+    event, keyboard, mouse, time, window, Alignment, Border, Color, Element, Event, Length, Point,
+    Size, Subscription, Task, Theme,
+};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, watch};
+
+use engine::{format_time, Engine, Snapshot, TimerState};
+use events::TimerEvent;
 
 mod audio;
 mod circular_progress;
+mod cli;
+mod config;
+#[cfg(unix)]
+mod control;
+#[cfg(target_os = "linux")]
+mod dbus;
+mod engine;
+mod events;
+mod flash;
+mod hooks;
+mod http;
+#[cfg(target_os = "linux")]
+mod inhibit;
+mod layout;
+mod meeting;
+#[cfg(target_os = "linux")]
+mod mpris;
+mod mqtt;
+#[cfg(target_os = "linux")]
+mod notifications;
+mod overlay;
+mod picture;
+mod presets;
+mod schedule;
+mod shortcuts;
+mod speech;
+mod state;
+mod sync;
+mod theme;
+mod timeline;
 mod timer;
+#[cfg(target_os = "linux")]
+mod tray;
+mod tui;
+mod webhooks;
 
 fn main() -> iced::Result {
+    let mut options = match cli::parse(std::env::args().skip(1)) {
+        Ok(cli::Command::Run(options)) => options,
+        Ok(cli::Command::Control(command)) => run_control(&command),
+        Ok(cli::Command::Help) => {
+            print!("{}", cli::help_text());
+            return Ok(());
+        }
+        Ok(cli::Command::Version) => {
+            println!("round-timer {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("round-timer: {}", err);
+            eprintln!("Try 'round-timer --help' for more information.");
+            std::process::exit(2);
+        }
+    };
+
+    let config = match config::load(options.config_path.as_deref()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("round-timer: {}", err);
+            std::process::exit(2);
+        }
+    };
+
+    // Speakers on the command line, or a schedule file, replace the config file's
+    let speakers = if options.speakers.is_empty() && options.schedule_path.is_none() {
+        config.meeting.speakers.clone()
+    } else {
+        options.speakers.clone()
+    };
+    let meeting = meeting::Meeting::new(speakers, options.shuffle || config.meeting.shuffle);
+
+    // Pictures in the config file are relative to it
+    let schedule = match (&meeting, &options.schedule_path) {
+        (Some(meeting), _) => {
+            options.num_rounds = meeting.rounds();
+            meeting.schedule()
+        }
+        (None, Some(path)) => match schedule::Schedule::load(path) {
+            Ok(schedule) => schedule,
+            Err(err) => {
+                eprintln!("round-timer: {}", err);
+                std::process::exit(2);
+            }
+        },
+        (None, None) => {
+            let config_path = options.config_path.clone().or_else(config::default_path);
+            schedule::Schedule::new(
+                config.rounds.clone(),
+                config_path.as_deref().and_then(Path::parent),
+            )
+        }
+    };
+
+    if options.tui {
+        if let Err(err) = tui::run(options, config, schedule, meeting) {
+            eprintln!("round-timer: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let close_to_tray =
+        cfg!(target_os = "linux") && config.tray.enabled && config.tray.close_to_tray;
+
     iced::application("Round Timer", RecurringTimer::update, RecurringTimer::view)
         .subscription(RecurringTimer::subscription)
-        .run_with(RecurringTimer::new)
-}
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum TimerState {
-    Stopped,
-    Running,
-    Paused,
+        .theme(RecurringTimer::theme)
+        .exit_on_close_request(!close_to_tray)
+        .run_with(move || RecurringTimer::new(options, config, schedule, meeting))
 }
 
 struct RecurringTimer {
     interval_input: String,
     rounds_input: String,
-    interval_secs: u32,
-    num_rounds: u32,
-    timer_state: TimerState,
-    elapsed_secs: u32,
-    total_duration_secs: u32,
-    round_number: u32,
+    engine: Engine,
     audio_player: audio::AudioPlayer,
+    event_format: Option<cli::EventFormat>,
+    hooks: hooks::Hooks,
+    webhooks: webhooks::Webhooks,
+    speech: speech::Speech,
+    status: watch::Sender<Snapshot>,
+    event_sender: broadcast::Sender<TimerEvent>,
+    #[cfg(target_os = "linux")]
+    notifications: config::NotificationsConfig,
+    #[cfg(target_os = "linux")]
+    tray: config::TrayConfig,
+    /// Set once the tray has accepted our icon, so hiding the window is safe.
+    tray_ready: bool,
+    power: config::PowerConfig,
+    http: config::HttpConfig,
+    mqtt: config::MqttConfig,
+    sync: config::SyncConfig,
+    overlay: config::OverlayConfig,
+    /// Shown on followers that lost their leader.
+    sync_notice: Option<String>,
+    window_size: Size,
+    /// How the window was opened, which showing it from the tray goes back to.
+    launch_mode: cli::WindowMode,
+    /// Full-screen presentation mode showing only the countdown.
+    big_clock: bool,
+    /// When the mouse last moved over the big clock, while its controls show.
+    controls_shown_at: Option<Instant>,
+    mini: config::MiniConfig,
+    ring: config::RingConfig,
+    palette: theme::Palette,
+    /// Built from `palette` once, rather than on every frame.
+    theme: Theme,
+    shortcuts: shortcuts::Shortcuts,
+    /// Seconds the time shortcuts add or take away.
+    step_secs: u32,
+    /// Whether the keyboard shortcuts are shown over the window.
+    help_shown: bool,
+    /// Pictures for the entries of the engine's schedule, in the same order.
+    pictures: Vec<Option<image::Handle>>,
+    /// The timeline's blocks, built again only when the session changes.
+    timeline_blocks: Rc<timeline::Blocks>,
+    /// Set in the meeting mode, where each round is a speaker's turn.
+    meeting: Option<meeting::Meeting>,
+    flash_config: config::FlashConfig,
+    /// The visual alert being shown, if any.
+    flash: Option<flash::Flash>,
+    /// Compact borderless window showing only the ring and countdown.
+    mini_window: bool,
+    /// Size to go back to when leaving the mini window.
+    normal_size: Size,
+    /// Where to go back to when leaving the mini window.
+    normal_position: Option<Point>,
+    /// The window's last known position on the screen.
+    window_position: Option<Point>,
+    /// When the mini window last moved, while its position is not saved yet.
+    mini_moved_at: Option<Instant>,
+    state: state::State,
+    /// Where [`state`](Self::state) is saved; `None` keeps it in memory only.
+    state_path: Option<PathBuf>,
+    /// Time the computer had spent asleep at the last tick while running, to
+    /// detect suspends.
+    last_tick: Option<Duration>,
+    suspend_notice: Option<String>,
+}
+
+#[cfg(unix)]
+fn run_control(command: &str) -> ! {
+    match control::send(command) {
+        Ok(reply) if reply.starts_with("error") => {
+            eprintln!("round-timer: {}", reply);
+            std::process::exit(1);
+        }
+        Ok(reply) => {
+            println!("{}", reply);
+            std::process::exit(0);
+        }
+        Err(err) => {
+            eprintln!(
+                "round-timer: could not reach a running timer at {}: {}",
+                control::socket_path().display(),
+                err
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn run_control(_command: &str) -> ! {
+    eprintln!("round-timer: ctl is only supported on Unix systems");
+    std::process::exit(1);
 }
 
 #[derive(Debug, Clone)]
@@ -38,25 +232,167 @@ enum Message {
     Pause,
     Resume,
     Stop,
+    Skip,
+    Toggle,
     Tick,
+    ApplyPreset(&'static str),
+    ShowWindow,
+    CloseRequested(window::Id),
+    TrayReady,
+    Follow(sync::Beacon),
+    LeaderLost,
+    WindowResized(Size),
+    ToggleBigClock,
+    MouseMoved,
+    HideControls,
+    ToggleMiniWindow,
+    DragWindow,
+    WindowMoved(Point),
+    SaveMiniPosition,
+    KeyPressed(keyboard::Key, keyboard::Modifiers),
+    Previous,
+    /// Go to a round picked on the timeline.
+    JumpTo(u32),
+    /// More or less time, in seconds.
+    AdjustTime(i32),
+    ToggleMute,
+    ToggleHelp,
+    /// Redraws the window while a flash is running.
+    FlashFrame,
+    Quit,
+}
+
+/// The big clock hides its controls after the mouse has been still this long.
+const CONTROLS_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The mini window's position is saved once it has been still this long.
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+impl Message {
+    /// Maps a remote-control command, as used by `ctl` and the HTTP API, to
+    /// its message.
+    fn from_command(command: &str) -> Option<Self> {
+        match command {
+            "start" => Some(Message::Start),
+            "pause" => Some(Message::Pause),
+            "resume" => Some(Message::Resume),
+            "toggle" => Some(Message::Toggle),
+            "stop" => Some(Message::Stop),
+            "skip" => Some(Message::Skip),
+            _ => None,
+        }
+    }
 }
 
 impl RecurringTimer {
-    fn new() -> (Self, Task<Message>) {
-        (
-            Self {
-                interval_input: String::from("60"),
-                rounds_input: String::from("20"),
-                interval_secs: 60,
-                num_rounds: 20,
-                timer_state: TimerState::Stopped,
-                elapsed_secs: 0,
-                total_duration_secs: 60 * 20,
-                round_number: 1,
-                audio_player: audio::AudioPlayer::new(),
+    fn new(
+        options: cli::Options,
+        config: config::Config,
+        schedule: schedule::Schedule,
+        meeting: Option<meeting::Meeting>,
+    ) -> (Self, Task<Message>) {
+        let mut audio_player = audio::AudioPlayer::new();
+        audio_player.set_muted(options.mute);
+
+        let mut engine = Engine::new(
+            options.interval_secs,
+            options.num_rounds,
+            options.lead_in_secs,
+        );
+        engine.set_warning(options.warning_secs);
+        engine.schedule = schedule;
+        // A missing picture should not stop the session
+        let pictures = engine
+            .schedule
+            .entries()
+            .iter()
+            .map(|entry| {
+                let path = entry.image.as_deref()?;
+                match picture::load(path) {
+                    Ok(picture) => Some(picture),
+                    Err(err) => {
+                        eprintln!("round-timer: cannot show {}: {}", path.display(), err);
+                        None
+                    }
+                }
+            })
+            .collect();
+        let engine_snapshot = engine.snapshot();
+        let timeline_blocks = Rc::new(timeline::Blocks::new(&engine_snapshot, &engine.schedule));
+        let state_path = state::default_path();
+        let palette = theme::Palette::from_config(&config.theme);
+
+        let mut timer = Self {
+            interval_input: options.interval_secs.to_string(),
+            rounds_input: options.num_rounds.to_string(),
+            engine,
+            audio_player,
+            event_format: options.events,
+            hooks: hooks::Hooks::new(config.hooks, false),
+            webhooks: webhooks::Webhooks::new(config.webhooks, false),
+            speech: speech::Speech::new(config.speech, false),
+            status: watch::Sender::new(engine_snapshot),
+            event_sender: broadcast::channel(64).0,
+            #[cfg(target_os = "linux")]
+            notifications: config.notifications,
+            #[cfg(target_os = "linux")]
+            tray: config.tray,
+            tray_ready: false,
+            power: config.power,
+            http: config.http,
+            mqtt: config.mqtt,
+            sync: config.sync,
+            overlay: config.overlay,
+            sync_notice: None,
+            window_size: window::Settings::default().size,
+            launch_mode: options.window_mode,
+            big_clock: false,
+            controls_shown_at: None,
+            mini: config.mini,
+            ring: config.ring,
+            palette,
+            theme: palette.theme(),
+            // The keys were checked with the rest of the config
+            shortcuts: shortcuts::Shortcuts::from_config(&config.keys).unwrap_or_default(),
+            step_secs: config.keys.step_secs,
+            help_shown: false,
+            pictures,
+            timeline_blocks,
+            meeting,
+            flash_config: config::FlashConfig {
+                enabled: config.flash.enabled || options.flash,
+                ..config.flash
             },
-            Task::none(),
-        )
+            flash: None,
+            mini_window: false,
+            normal_size: window::Settings::default().size,
+            normal_position: None,
+            window_position: None,
+            mini_moved_at: None,
+            state: state_path
+                .as_deref()
+                .map(state::State::load)
+                .unwrap_or_default(),
+            state_path,
+            last_tick: None,
+            suspend_notice: None,
+        };
+
+        let window_task = match options.window_mode {
+            cli::WindowMode::Windowed => Task::none(),
+            cli::WindowMode::Maximized => {
+                window::get_oldest().and_then(|id| window::maximize(id, true))
+            }
+            cli::WindowMode::Fullscreen => window::get_oldest()
+                .and_then(|id| window::change_mode(id, window::Mode::Fullscreen)),
+        };
+        let start_task = if options.auto_start {
+            timer.update(Message::Start)
+        } else {
+            Task::none()
+        };
+
+        (timer, Task::batch([window_task, start_task]))
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
@@ -64,99 +400,367 @@ impl RecurringTimer {
             Message::IntervalChanged(value) => {
                 self.interval_input = value.clone();
                 if let Ok(secs) = value.parse::<u32>() {
-                    if secs > 0 {
-                        self.interval_secs = secs;
-                        self.total_duration_secs = self.interval_secs * self.num_rounds;
+                    if secs > 0 && secs.checked_mul(self.engine.num_rounds).is_some() {
+                        self.engine.set_interval(secs);
                     }
                 }
             }
+            // A meeting has one round per speaker
+            Message::RoundsChanged(_) if self.meeting.is_some() => {}
             Message::RoundsChanged(value) => {
                 self.rounds_input = value.clone();
                 if let Ok(rounds) = value.parse::<u32>() {
-                    if rounds > 0 {
-                        self.num_rounds = rounds;
-                        self.total_duration_secs = self.interval_secs * self.num_rounds;
+                    if rounds > 0 && rounds.checked_mul(self.engine.interval_secs).is_some() {
+                        self.engine.set_rounds(rounds);
                     }
                 }
             }
             Message::Start => {
-                self.timer_state = TimerState::Running;
-                self.elapsed_secs = 0;
-                self.round_number = 1;
-                self.total_duration_secs = self.interval_secs * self.num_rounds;
+                let events = self.start();
+                self.dispatch(events);
             }
             Message::Pause => {
-                self.timer_state = TimerState::Paused;
+                let events = self.engine.pause();
+                self.dispatch(events);
             }
             Message::Resume => {
-                self.timer_state = TimerState::Running;
+                let events = self.engine.resume();
+                self.dispatch(events);
             }
             Message::Stop => {
-                self.timer_state = TimerState::Stopped;
-                self.elapsed_secs = 0;
-                self.round_number = 1;
-            }
-            Message::Tick => {
-                if self.timer_state == TimerState::Running {
-                    self.elapsed_secs += 1;
-
-                    // Check if it's time to play a chime
-                    if self.elapsed_secs % self.interval_secs == 0 {
-                        self.audio_player.play_chime();
-                        // Only increment round number if we're not at the final chime
-                        if self.elapsed_secs < self.total_duration_secs {
-                            self.round_number += 1;
-                        }
-                    }
-
-                    // Check if we've reached the total duration
-                    if self.elapsed_secs >= self.total_duration_secs {
-                        self.timer_state = TimerState::Stopped;
-                    }
+                let events = self.engine.stop();
+                self.dispatch(events);
+            }
+            Message::Skip => {
+                let events = self.engine.skip();
+                self.dispatch(events);
+            }
+            Message::Toggle => {
+                let events = match self.engine.timer_state {
+                    TimerState::Stopped => self.start(),
+                    TimerState::Running => self.engine.pause(),
+                    TimerState::Paused => self.engine.resume(),
+                };
+                self.dispatch(events);
+            }
+            Message::Tick => self.tick(timer::time_asleep()),
+            Message::ApplyPreset(_) if self.meeting.is_some() => {}
+            Message::ApplyPreset(name) => {
+                if let (TimerState::Stopped, Some(preset)) =
+                    (self.engine.timer_state, presets::find(name))
+                {
+                    self.interval_input = preset.interval_secs.to_string();
+                    self.rounds_input = preset.num_rounds.to_string();
+                    self.engine.set_interval(preset.interval_secs);
+                    self.engine.set_rounds(preset.num_rounds);
+                }
+            }
+            Message::ShowWindow => {
+                let mode = self.window_mode();
+                let maximized = self.launch_mode == cli::WindowMode::Maximized;
+                return window::get_oldest().and_then(move |id| {
+                    let shown = window::change_mode(id, mode);
+                    let shown = if maximized {
+                        shown.chain(window::maximize(id, true))
+                    } else {
+                        shown
+                    };
+                    shown.chain(window::gain_focus(id))
+                });
+            }
+            Message::CloseRequested(id) => {
+                // Without a tray icon there would be no way to bring the window back
+                return if self.tray_ready {
+                    window::change_mode(id, window::Mode::Hidden)
+                } else {
+                    window::close(id)
+                };
+            }
+            Message::TrayReady => self.tray_ready = true,
+            Message::Follow(beacon) => {
+                let events = sync::follow(&mut self.engine, &beacon.engine());
+                self.interval_input = self.engine.interval_secs.to_string();
+                self.rounds_input = self.engine.num_rounds.to_string();
+                self.sync_notice = None;
+                self.dispatch(events);
+            }
+            Message::WindowResized(size) => self.window_size = size,
+            Message::ToggleBigClock if self.mini_window => {}
+            Message::ToggleBigClock => {
+                self.big_clock = !self.big_clock;
+                self.controls_shown_at = None;
+                let mode = self.window_mode();
+                return window::get_oldest().and_then(move |id| window::change_mode(id, mode));
+            }
+            Message::MouseMoved => {
+                if self.big_clock {
+                    self.controls_shown_at = Some(Instant::now());
+                }
+            }
+            Message::HideControls => {
+                if self
+                    .controls_shown_at
+                    .is_some_and(|shown_at| shown_at.elapsed() >= CONTROLS_TIMEOUT)
+                {
+                    self.controls_shown_at = None;
                 }
             }
+            Message::ToggleMiniWindow => return self.toggle_mini_window(),
+            Message::DragWindow => return window::get_oldest().and_then(window::drag),
+            Message::WindowMoved(position) => {
+                self.window_position = Some(position);
+                let position = Some((position.x, position.y));
+                // Dragging moves the window many times a second, so wait
+                // for it to settle before writing the position out
+                if self.mini_window && self.state.mini_position != position {
+                    self.state.mini_position = position;
+                    self.mini_moved_at = Some(Instant::now());
+                }
+            }
+            Message::SaveMiniPosition => {
+                if self
+                    .mini_moved_at
+                    .is_some_and(|moved_at| moved_at.elapsed() >= SAVE_DELAY)
+                {
+                    self.mini_moved_at = None;
+                    self.save_state();
+                }
+            }
+            Message::KeyPressed(key, modifiers) => {
+                // Esc leaves the help before it stops the timer
+                if self.help_shown && key == keyboard::Key::Named(keyboard::key::Named::Escape) {
+                    self.help_shown = false;
+                } else if let Some(action) = self.shortcuts.action(&key, modifiers) {
+                    let step = self.step_secs as i32;
+                    return self.update(match action {
+                        shortcuts::Action::Toggle => Message::Toggle,
+                        shortcuts::Action::Stop => Message::Stop,
+                        shortcuts::Action::Next => Message::Skip,
+                        shortcuts::Action::Previous => Message::Previous,
+                        shortcuts::Action::MoreTime => Message::AdjustTime(step),
+                        shortcuts::Action::LessTime => Message::AdjustTime(-step),
+                        shortcuts::Action::BigClock => Message::ToggleBigClock,
+                        shortcuts::Action::Mute => Message::ToggleMute,
+                        shortcuts::Action::Help => Message::ToggleHelp,
+                    });
+                }
+            }
+            Message::Previous => {
+                let events = self.engine.previous();
+                self.dispatch(events);
+            }
+            Message::JumpTo(round) => {
+                let events = self.engine.jump_to(round);
+                self.dispatch(events);
+            }
+            // Every speaker gets the same time
+            Message::AdjustTime(_) if self.meeting.is_some() => {}
+            Message::AdjustTime(secs) => {
+                if self.engine.timer_state == TimerState::Stopped {
+                    // Before a session, lengthen or shorten the rounds instead
+                    let interval = (self.engine.interval_secs as i64 + secs as i64).max(1) as u32;
+                    self.engine.set_interval(interval);
+                    self.interval_input = interval.to_string();
+                } else {
+                    self.engine.adjust(secs);
+                }
+            }
+            Message::ToggleMute => {
+                let muted = !self.audio_player.is_muted();
+                self.audio_player.set_muted(muted);
+            }
+            Message::ToggleHelp => self.help_shown = !self.help_shown,
+            Message::FlashFrame => {
+                if self
+                    .flash
+                    .as_ref()
+                    .is_some_and(|flash| flash.is_over(Instant::now()))
+                {
+                    self.flash = None;
+                }
+            }
+            Message::LeaderLost => {
+                self.sync_notice = Some(String::from(
+                    "Lost contact with the leader, keeping time on this computer",
+                ));
+            }
+            Message::Quit => {
+                if self.mini_moved_at.take().is_some() {
+                    self.save_state();
+                }
+                return iced::exit();
+            }
         }
+        match self.engine.timer_state {
+            TimerState::Running => self.suspend_notice = None,
+            TimerState::Paused => self.last_tick = None,
+            TimerState::Stopped => {
+                self.last_tick = None;
+                self.suspend_notice = None;
+            }
+        }
+        let status = self.engine.snapshot();
+        if !self.timeline_blocks.fit(&status, &self.engine.schedule) {
+            self.timeline_blocks = Rc::new(timeline::Blocks::new(&status, &self.engine.schedule));
+        }
+        self.status.send_replace(status);
         Task::none()
     }
 
-    fn view(&self) -> Element<'_, Message> {
-        let is_configurable = self.timer_state == TimerState::Stopped;
+    fn start(&mut self) -> Vec<TimerEvent> {
+        // Shuffled meetings get a new order each time
+        if let Some(meeting) = &self.meeting {
+            if self.engine.timer_state == TimerState::Stopped {
+                self.engine.schedule = meeting.schedule();
+            }
+        }
+        self.engine.start()
+    }
 
-        let interval_input = text_input("Interval (seconds)", &self.interval_input)
-            .on_input(Message::IntervalChanged)
-            .padding(10);
+    fn tick(&mut self, asleep: Duration) {
+        let missed = self
+            .last_tick
+            .map_or(0, |last_tick| timer::missed_ticks(last_tick, asleep));
+        self.last_tick = Some(asleep);
+
+        if missed > 0 {
+            match self.power.after_suspend {
+                config::AfterSuspend::Pause => {
+                    let events = self.engine.pause();
+                    self.dispatch(events);
+                    self.suspend_notice = Some(format!(
+                        "Paused after the computer was asleep for {}",
+                        format_time(missed)
+                    ));
+                    return;
+                }
+                config::AfterSuspend::CatchUp => {
+                    let events = self.engine.advance(missed);
+                    self.dispatch(events);
+                }
+            }
+        }
 
-        let rounds_input = text_input("Number of Rounds", &self.rounds_input)
-            .on_input(Message::RoundsChanged)
-            .padding(10);
+        let events = self.engine.tick();
+        self.dispatch(events);
+    }
 
-        let inputs = if is_configurable {
-            row![
-                column![text("Interval (seconds)"), interval_input].spacing(5),
-                column![text("Number of Rounds"), rounds_input].spacing(5),
-            ]
-            .spacing(20)
+    fn dispatch(&mut self, events: Vec<TimerEvent>) {
+        // Catching up with a sync leader can produce several chimes at once
+        let mut chimed = false;
+        for event in events {
+            if let TimerEvent::Chime { .. } = event {
+                if !chimed {
+                    self.audio_player.play_chime();
+                    chimed = true;
+                }
+            }
+            // Flashes go with the chime, for when it cannot be heard
+            if self.flash_config.enabled {
+                if let Some(flash) =
+                    flash::Flash::for_event(&event, &self.flash_config, Instant::now())
+                {
+                    self.flash = Some(flash);
+                }
+            }
+            if let Some(cli::EventFormat::Json) = self.event_format {
+                events::emit_json(&event);
+            }
+            if !self.audio_player.is_muted() {
+                self.speech.say(&event);
+            }
+            self.hooks.run(&event);
+            self.webhooks
+                .send(&event, &webhooks::Configuration::from(&self.engine));
+            // Fails only when no integration is listening
+            let _ = self.event_sender.send(event);
+        }
+    }
+
+    fn toggle_mini_window(&mut self) -> Task<Message> {
+        self.mini_window = !self.mini_window;
+
+        if !self.mini_window {
+            if self.mini_moved_at.take().is_some() {
+                self.save_state();
+            }
+            let size = self.normal_size;
+            let position = self.normal_position;
+            return window::get_oldest().and_then(move |id| {
+                let mut tasks = vec![
+                    window::toggle_decorations(id),
+                    window::change_level(id, window::Level::Normal),
+                    window::resize(id, size),
+                ];
+                if let Some(position) = position {
+                    tasks.push(window::move_to(id, position));
+                }
+                Task::batch(tasks)
+            });
+        }
+
+        self.normal_size = self.window_size;
+        self.normal_position = self.window_position;
+        self.big_clock = false;
+        let size = Size::new(self.mini.size as f32, self.mini.size as f32);
+        let level = if self.mini.always_on_top {
+            window::Level::AlwaysOnTop
         } else {
-            row![
-                column![
-                    text("Interval (seconds)"),
-                    text(&self.interval_input).size(16)
-                ]
-                .spacing(5),
-                column![
-                    text("Number of Rounds"),
-                    text(&self.rounds_input).size(16)
-                ]
-                .spacing(5),
-            ]
-            .spacing(20)
+            window::Level::Normal
         };
+        let position = self.state.mini_position.map(|(x, y)| Point::new(x, y));
+        window::get_oldest().and_then(move |id| {
+            let mut tasks = vec![
+                window::change_mode(id, window::Mode::Windowed),
+                window::toggle_decorations(id),
+                window::change_level(id, level),
+                window::resize(id, size),
+            ];
+            if let Some(position) = position {
+                tasks.push(window::move_to(id, position));
+            }
+            Task::batch(tasks)
+        })
+    }
+
+    fn save_state(&self) {
+        if let Some(path) = &self.state_path {
+            if let Err(err) = self.state.save(path) {
+                eprintln!("round-timer: cannot save {}: {}", path.display(), err);
+            }
+        }
+    }
 
-        let control_buttons = match self.timer_state {
+    /// The time left in the round, or in the lead-in.
+    fn countdown(&self) -> String {
+        format_time(if self.engine.in_lead_in() {
+            self.engine.lead_in_remaining
+        } else {
+            self.engine.round_remaining_secs()
+        })
+    }
+
+    fn window_mode(&self) -> window::Mode {
+        if self.big_clock || self.launch_mode == cli::WindowMode::Fullscreen {
+            window::Mode::Fullscreen
+        } else {
+            window::Mode::Windowed
+        }
+    }
+
+    fn control_buttons(&self) -> Row<'_, Message> {
+        // A speaker who finishes early hands over to the next
+        let skip = if self.meeting.is_some() {
+            "Done"
+        } else {
+            "Skip"
+        };
+        match self.engine.timer_state {
             TimerState::Stopped => row![button("Start").on_press(Message::Start)].spacing(10),
             TimerState::Running => {
                 row![
                     button("Pause").on_press(Message::Pause),
+                    button(skip).on_press(Message::Skip),
                     button("Stop").on_press(Message::Stop)
                 ]
                 .spacing(10)
@@ -164,414 +768,1369 @@ impl RecurringTimer {
             TimerState::Paused => {
                 row![
                     button("Resume").on_press(Message::Resume),
+                    button(skip).on_press(Message::Skip),
                     button("Stop").on_press(Message::Stop)
                 ]
                 .spacing(10)
             }
+        }
+    }
+
+    /// The current round's label, naming the speaker in the meeting mode.
+    fn current_label(&self) -> Option<String> {
+        let label = self.engine.current_label()?;
+        if self.meeting.is_some() && self.engine.timer_state != TimerState::Stopped {
+            Some(format!("Speaking: {}", label))
+        } else {
+            Some(label)
+        }
+    }
+
+    /// How long each speaker took, once the meeting is over.
+    fn meeting_summary(&self) -> Option<Vec<String>> {
+        self.meeting.as_ref()?;
+        let turns = meeting::turns(&self.engine);
+        (self.engine.timer_state == TimerState::Stopped && !turns.is_empty())
+            .then(|| meeting::summary(&turns))
+    }
+
+    /// The progress ring as configured. Views that show the countdown in
+    /// large digits already leave out its `label`.
+    fn ring(&self, label: bool) -> circular_progress::CircularProgress {
+        let config = config::RingConfig {
+            show_time: label,
+            ..self.ring.clone()
         };
+        circular_progress::CircularProgress::for_status(
+            &self.engine.snapshot(),
+            &config,
+            &self.palette,
+        )
+    }
 
-        let remaining_secs = self.total_duration_secs.saturating_sub(self.elapsed_secs);
-        let remaining_mins = remaining_secs / 60;
-        let remaining_secs_part = remaining_secs % 60;
+    /// The picture for the current round, or the first during the lead-in.
+    fn round_picture(&self) -> Option<image::Handle> {
+        let (index, _) = self.engine.schedule.entry(self.engine.round_number)?;
+        self.pictures.get(index).cloned().flatten()
+    }
 
-        let time_display = text(format!(
-            "Total Time Remaining: {:02}:{:02}",
-            remaining_mins, remaining_secs_part
-        ))
-        .size(18);
+    /// Background of the big clock, so the phase can be seen from across the room.
+    fn phase_color(&self) -> Color {
+        let warning = self.engine.warning_secs > 0
+            && self.engine.round_remaining_secs() <= self.engine.warning_secs;
+        match self.engine.timer_state {
+            TimerState::Stopped => self.palette.stopped,
+            TimerState::Paused => self.palette.paused,
+            TimerState::Running if self.engine.in_lead_in() => self.palette.lead_in,
+            TimerState::Running if warning => self.palette.warning,
+            TimerState::Running => self.palette.running,
+        }
+    }
 
-        let round_remaining_secs = if self.interval_secs > 0 {
-            let time_in_round = self.elapsed_secs % self.interval_secs;
-            if time_in_round == 0 {
-                self.interval_secs
-            } else {
-                self.interval_secs - time_in_round
-            }
+    fn big_clock_view(&self) -> Element<'_, Message> {
+        let countdown = self.countdown();
+        let round_label = if self.engine.in_lead_in() {
+            String::from("Get Ready")
         } else {
-            0
+            format!(
+                "Round {} / {}",
+                self.engine.round_number, self.engine.num_rounds
+            )
         };
-        let round_remaining_mins = round_remaining_secs / 60;
-        let round_remaining_secs_part = round_remaining_secs % 60;
 
-        let round_time_display = text(format!(
-            "Round Time Remaining: {:02}:{:02}",
-            round_remaining_mins, round_remaining_secs_part
-        ))
-        .size(28);
+        // Digits scale with the screen rather than using fixed sizes
+        let digits = self.window_size.width.min(self.window_size.height) * 0.22;
+        let clock = container(
+            column![
+                text(countdown).size(digits),
+                text(round_label).size(digits * 0.3),
+            ]
+            .push_maybe(
+                self.current_label()
+                    .map(|label| text(label).size(digits * 0.3)),
+            )
+            .push_maybe(
+                self.engine
+                    .next_label()
+                    .map(|next| text(format!("Next: {}", next)).size(digits * 0.15)),
+            )
+            .align_x(Alignment::Center),
+        )
+        .center(Length::Fill);
 
-        let round_display = text(format!("Round: {}", self.round_number)).size(20);
+        let ring = container(
+            circular_progress::circular_progress(self.ring(false), Length::Fill)
+                .map(|_| Message::Tick),
+        )
+        .padding(20);
+
+        let controls = self.controls_shown_at.map(|_| {
+            container(
+                self.control_buttons()
+                    .push(button("Exit Full Screen").on_press(Message::ToggleBigClock)),
+            )
+            .center_x(Length::Fill)
+            .align_bottom(Length::Fill)
+            .padding(30)
+        });
+
+        let (background, text_color) = match self.flash_background() {
+            Some(color) => (color, flash::text_color(color)),
+            None => (self.phase_color(), self.palette.phase_text),
+        };
+        container(stack![ring, clock].push_maybe(controls))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(move |_theme| container::Style {
+                background: Some(background.into()),
+                text_color: Some(text_color),
+                ..container::Style::default()
+            })
+            .into()
+    }
 
-        let progress = if self.total_duration_secs > 0 {
-            self.elapsed_secs as f32 / self.total_duration_secs as f32
-        } else {
-            0.0
+    fn mini_view(&self) -> Element<'_, Message> {
+        let ring = circular_progress::circular_progress(self.ring(false), Length::Fill)
+            .map(|_| Message::Tick);
+        let countdown = container(text(self.countdown()).size(self.mini.size as f32 * 0.16))
+            .center(Length::Fill);
+
+        let flash = self.flash_background();
+        // Without decorations, the whole window is the handle for moving it
+        mouse_area(
+            container(stack![ring, countdown])
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .style(move |theme: &Theme| container::Style {
+                    background: Some(flash.unwrap_or(theme.palette().background).into()),
+                    text_color: Some(flash.map_or(theme.palette().text, flash::text_color)),
+                    ..container::Style::default()
+                }),
+        )
+        .on_press(Message::DragWindow)
+        .on_middle_press(Message::Toggle)
+        .on_right_press(Message::ToggleMiniWindow)
+        .into()
+    }
+
+    fn theme(&self) -> Theme {
+        self.theme.clone()
+    }
+
+    /// The keyboard shortcuts, over the rest of the window.
+    fn help_view(&self) -> Element<'_, Message> {
+        let rows = self
+            .shortcuts
+            .bindings()
+            .iter()
+            .filter(|(_, keys)| !keys.is_empty())
+            .map(|(action, keys)| {
+                let labels: Vec<String> = keys.iter().map(|binding| binding.label()).collect();
+                let description = match action {
+                    shortcuts::Action::MoreTime | shortcuts::Action::LessTime => {
+                        format!("{} ({} seconds)", action.description(), self.step_secs)
+                    }
+                    _ => action.description().to_string(),
+                };
+                row![
+                    text(labels.join(" / ")).width(Length::Fixed(80.0)),
+                    text(description)
+                ]
+                .spacing(20)
+                .into()
+            });
+
+        let close_hint = match self.shortcuts.binding(shortcuts::Action::Help) {
+            Some(binding) => format!("Press {} or click anywhere to close.", binding.label()),
+            None => String::from("Click anywhere to close."),
         };
+        let panel = container(
+            column![
+                text("Keyboard Shortcuts").size(24),
+                Column::with_children(rows).spacing(8),
+                text(close_hint).size(13),
+            ]
+            .spacing(20),
+        )
+        .padding(30)
+        .style(container::rounded_box);
 
-        let progress_bar = progress_bar(0.0..=1.0, progress);
-
-        // Calculate round progress for circular indicator (shows remaining time)
-        let round_progress = if self.interval_secs > 0 {
-            let time_in_round = self.elapsed_secs % self.interval_secs;
-            let remaining = if time_in_round == 0 {
-                self.interval_secs
-            } else {
-                self.interval_secs - time_in_round
-            };
-            remaining as f32 / self.interval_secs as f32
+        let backdrop = Color {
+            a: 0.85,
+            ..self.palette.background
+        };
+        mouse_area(
+            container(panel)
+                .center(Length::Fill)
+                .style(move |_theme| container::Style {
+                    background: Some(backdrop.into()),
+                    ..container::Style::default()
+                }),
+        )
+        .on_press(Message::ToggleHelp)
+        .into()
+    }
+
+    /// The colour of the flash, while it is lit.
+    fn flash_color(&self) -> Option<Color> {
+        self.flash.as_ref()?.color_at(Instant::now())
+    }
+
+    /// The colour of the flash, while it is lit and lights the background.
+    fn flash_background(&self) -> Option<Color> {
+        self.flash_color()
+            .filter(|_| self.flash_config.style == config::FlashStyle::Background)
+    }
+
+    /// A border in the flash's colour around the edges of the window.
+    fn flash_border(&self, color: Color) -> Element<'_, Message> {
+        let width = (self.window_size.width.min(self.window_size.height) * 0.04).max(8.0);
+        container(Space::new(Length::Fill, Length::Fill))
+            .style(move |_theme| container::Style {
+                border: Border {
+                    color,
+                    width,
+                    radius: 0.0.into(),
+                },
+                ..container::Style::default()
+            })
+            .into()
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let content = if self.mini_window {
+            self.mini_view()
+        } else if self.big_clock {
+            self.big_clock_view()
         } else {
-            0.0
+            self.main_view()
         };
+        // The mini window is too small for the help
+        let content = if self.help_shown && !self.mini_window {
+            stack![content, self.help_view()].into()
+        } else {
+            content
+        };
+        match self.flash_color() {
+            Some(color) if self.flash_config.style == config::FlashStyle::Border => {
+                stack![content, self.flash_border(color)].into()
+            }
+            _ => content,
+        }
+    }
+
+    fn main_view<'a>(&'a self) -> Element<'a, Message> {
+        let is_configurable = self.engine.timer_state == TimerState::Stopped;
+        let layout = layout::Layout::for_window(self.window_size);
+        let label_size = layout.size(16.0);
+
+        let interval_input = text_input("Interval (seconds)", &self.interval_input)
+            .on_input(Message::IntervalChanged)
+            .size(label_size)
+            .padding(10);
 
-        let status_text = match self.timer_state {
-            TimerState::Stopped => "Stopped",
-            TimerState::Running => "Running",
-            TimerState::Paused => "Paused",
+        let rounds_input = text_input("Number of Rounds", &self.rounds_input)
+            .on_input_maybe(self.meeting.is_none().then_some(Message::RoundsChanged))
+            .size(label_size)
+            .padding(10);
+
+        let inputs = if is_configurable {
+            row![
+                column![text("Interval (seconds)").size(label_size), interval_input].spacing(5),
+                column![text("Number of Rounds").size(label_size), rounds_input].spacing(5),
+            ]
+            .spacing(layout.size(20.0))
+        } else {
+            row![
+                column![
+                    text("Interval (seconds)").size(label_size),
+                    text(&self.interval_input).size(label_size)
+                ]
+                .spacing(5),
+                column![
+                    text("Number of Rounds").size(label_size),
+                    text(&self.rounds_input).size(label_size)
+                ]
+                .spacing(5),
+            ]
+            .spacing(layout.size(20.0))
         };
-        let status_display = text(format!("Status: {}", status_text)).size(16);
 
-        let content = column![
-            text("Round Timer").size(32),
+        let control_buttons = self
+            .control_buttons()
+            .push(button("Big Clock").on_press(Message::ToggleBigClock))
+            .push(button("Mini").on_press(Message::ToggleMiniWindow));
+
+        let time_display = text(format!(
+            "Total Time Remaining: {}",
+            format_time(self.engine.remaining_secs())
+        ))
+        .size(layout.size(18.0));
+
+        let round_time_display = if self.engine.in_lead_in() {
+            text(format!(
+                "Starting In: {}",
+                format_time(self.engine.lead_in_remaining)
+            ))
+        } else {
+            text(format!(
+                "Round Time Remaining: {}",
+                format_time(self.engine.round_remaining_secs())
+            ))
+        }
+        .size(layout.size(28.0));
+
+        let round_display =
+            text(format!("Round: {}", self.engine.round_number)).size(layout.size(20.0));
+
+        // What the schedule says about this round, and the next
+        let entry = self.engine.schedule.round(self.engine.round_number);
+        let schedule_display = column![]
+            .push_maybe(
+                self.current_label()
+                    .map(|label| text(label).size(layout.size(36.0))),
+            )
+            .push_maybe(
+                entry
+                    .and_then(|entry| entry.notes.as_deref())
+                    .map(|notes| text(notes).size(label_size)),
+            )
+            .push_maybe(self.round_picture().map(|round_picture| {
+                picture::picture(round_picture, Length::Fixed(layout.size(120.0)))
+            }))
+            .push_maybe(
+                self.engine
+                    .next_label()
+                    .map(|next| text(format!("Next: {}", next)).size(label_size)),
+            )
+            .spacing(layout.size(8.0))
+            .align_x(Alignment::Center);
+
+        let summary_display = self.meeting_summary().map(|lines| {
+            column![text("Time Taken").size(layout.size(20.0))]
+                .extend(
+                    lines
+                        .into_iter()
+                        .map(|line| text(line).size(label_size).into()),
+                )
+                .spacing(5)
+                .align_x(Alignment::Center)
+        });
+
+        let progress_bar = progress_bar(0.0..=1.0, self.engine.progress());
+
+        let timeline = timeline::timeline(
+            timeline::Timeline::for_status(
+                self.timeline_blocks.clone(),
+                &self.engine.snapshot(),
+                &self.palette,
+            ),
+            Length::Fixed(layout.size(36.0)),
+        )
+        .map(Message::JumpTo);
+
+        let notice_size = layout.size(14.0);
+        let status_display =
+            column![text(format!("Status: {}", self.engine.status_text())).size(label_size)]
+                .push_maybe(
+                    self.audio_player
+                        .is_muted()
+                        .then(|| text("Chime muted").size(notice_size)),
+                )
+                .push_maybe(
+                    self.suspend_notice
+                        .as_deref()
+                        .map(|notice| text(notice).size(notice_size)),
+                )
+                .push_maybe(
+                    self.sync_notice
+                        .as_deref()
+                        .map(|notice| text(notice).size(notice_size)),
+                )
+                .spacing(5)
+                .align_x(Alignment::Center);
+
+        let settings = column![
+            text("Round Timer").size(layout.size(32.0)),
             inputs,
             control_buttons,
             status_display,
+        ];
+        let clock = column![
             round_time_display,
-            circular_progress::circular_progress(round_progress, Color::from_rgb(0.2, 0.7, 0.9))
-                .map(|_| Message::Tick),
+            circular_progress::circular_progress(
+                self.ring(self.ring.show_time),
+                Length::Fixed(layout.ring_size),
+            )
+            .map(|_| Message::Tick),
             round_display,
+            schedule_display,
             progress_bar,
+            timeline,
             time_display,
         ]
-        .spacing(20)
-        .padding(20)
-        .align_x(Alignment::Center);
+        .push_maybe(summary_display);
 
+        let spacing = layout.size(20.0);
+        let arrange = |part: Column<'a, Message>| {
+            part.spacing(spacing)
+                .align_x(Alignment::Center)
+                .width(Length::Fill)
+        };
+        // Wide windows put the clock beside the settings instead of below them
+        let content: Element<'a, Message> = if layout.wide {
+            row![arrange(settings), arrange(clock)]
+                .spacing(spacing)
+                .align_y(Alignment::Center)
+                .into()
+        } else {
+            arrange(settings.extend([clock.into()])).into()
+        };
+
+        let flash = self.flash_background();
         container(content)
             .width(Length::Fill)
             .height(Length::Fill)
+            .padding(spacing)
             .center(Length::Fill)
+            .style(move |_theme| container::Style {
+                background: flash.map(Into::into),
+                text_color: flash.map(flash::text_color),
+                ..container::Style::default()
+            })
             .into()
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        if self.timer_state == TimerState::Running {
-            timer::timer_subscription()
-        } else {
-            Subscription::none()
+        let mut subscriptions = Vec::new();
+
+        if self.engine.timer_state == TimerState::Running {
+            // Followers tick in step with their leader instead
+            if self.sync.role != config::SyncRole::Follower {
+                subscriptions.push(timer::timer_subscription());
+            }
+            #[cfg(target_os = "linux")]
+            subscriptions.push(inhibit::subscription(self.power.clone()));
+        }
+
+        subscriptions.push(window::resize_events().map(|(_id, size)| Message::WindowResized(size)));
+        subscriptions.push(keyboard::on_key_press(|key, modifiers| {
+            Some(Message::KeyPressed(key, modifiers))
+        }));
+
+        if self.flash.is_some() {
+            subscriptions.push(time::every(Duration::from_millis(40)).map(|_| Message::FlashFrame));
+        }
+
+        if self.big_clock {
+            subscriptions.push(event::listen_with(|event, _status, _window| match event {
+                Event::Mouse(mouse::Event::CursorMoved { .. }) => Some(Message::MouseMoved),
+                _ => None,
+            }));
+            if self.controls_shown_at.is_some() {
+                subscriptions
+                    .push(time::every(Duration::from_millis(500)).map(|_| Message::HideControls));
+            }
+        }
+
+        // The main window's position is needed to go back to it from the mini window
+        subscriptions.push(event::listen_with(|event, _status, _window| match event {
+            Event::Window(window::Event::Moved(position)) => Some(Message::WindowMoved(position)),
+            _ => None,
+        }));
+        if self.mini_moved_at.is_some() {
+            subscriptions
+                .push(time::every(Duration::from_millis(500)).map(|_| Message::SaveMiniPosition));
         }
+
+        #[cfg(unix)]
+        subscriptions.push(control::subscription(self.status.subscribe()));
+
+        if self.http.enabled {
+            subscriptions.push(http::subscription(
+                self.http.clone(),
+                self.status.subscribe(),
+                self.event_sender.subscribe(),
+            ));
+        }
+
+        if self.sync.role != config::SyncRole::Off {
+            subscriptions.push(sync::subscription(
+                self.sync.clone(),
+                self.status.subscribe(),
+            ));
+        }
+
+        if self.overlay.enabled {
+            subscriptions.push(overlay::subscription(
+                self.overlay.clone(),
+                self.ring.clone(),
+                self.palette,
+                self.status.subscribe(),
+            ));
+        }
+
+        if self.mqtt.enabled {
+            subscriptions.push(mqtt::subscription(
+                self.mqtt.clone(),
+                self.status.subscribe(),
+                self.event_sender.subscribe(),
+            ));
+        }
+
+        #[cfg(target_os = "linux")]
+        subscriptions.push(dbus::subscription(
+            self.status.subscribe(),
+            self.event_sender.subscribe(),
+        ));
+        #[cfg(target_os = "linux")]
+        subscriptions.push(mpris::subscription(self.status.subscribe()));
+        #[cfg(target_os = "linux")]
+        if self.tray.enabled {
+            subscriptions.push(tray::subscription(self.status.subscribe()));
+            if self.tray.close_to_tray {
+                subscriptions.push(window::close_requests().map(Message::CloseRequested));
+            }
+        }
+        #[cfg(target_os = "linux")]
+        if self.notifications.enabled {
+            subscriptions.push(notifications::subscription(
+                self.notifications.clone(),
+                self.status.subscribe(),
+                self.event_sender.subscribe(),
+            ));
+        }
+
+        Subscription::batch(subscriptions)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
 
     fn create_test_timer() -> RecurringTimer {
         RecurringTimer {
             interval_input: String::from("60"),
             rounds_input: String::from("20"),
-            interval_secs: 60,
-            num_rounds: 20,
-            timer_state: TimerState::Stopped,
-            elapsed_secs: 0,
-            total_duration_secs: 1200,
-            round_number: 1,
+            engine: Engine::new(60, 20, 0),
             audio_player: audio::AudioPlayer::new(),
+            event_format: None,
+            hooks: hooks::Hooks::new(config::HooksConfig::default(), true),
+            webhooks: webhooks::Webhooks::new(config::WebhooksConfig::default(), true),
+            speech: speech::Speech::new(config::SpeechConfig::default(), true),
+            status: watch::Sender::new(Engine::new(60, 20, 0).snapshot()),
+            event_sender: broadcast::channel(64).0,
+            #[cfg(target_os = "linux")]
+            notifications: config::NotificationsConfig::default(),
+            #[cfg(target_os = "linux")]
+            tray: config::TrayConfig::default(),
+            tray_ready: false,
+            power: config::PowerConfig::default(),
+            http: config::HttpConfig::default(),
+            mqtt: config::MqttConfig::default(),
+            sync: config::SyncConfig::default(),
+            overlay: config::OverlayConfig::default(),
+            sync_notice: None,
+            window_size: window::Settings::default().size,
+            launch_mode: cli::WindowMode::Windowed,
+            big_clock: false,
+            controls_shown_at: None,
+            mini: config::MiniConfig::default(),
+            ring: config::RingConfig::default(),
+            palette: theme::Palette::dark(),
+            theme: theme::Palette::dark().theme(),
+            shortcuts: shortcuts::Shortcuts::default(),
+            step_secs: 10,
+            help_shown: false,
+            pictures: Vec::new(),
+            timeline_blocks: Rc::new(timeline::Blocks::new(
+                &Engine::new(60, 20, 0).snapshot(),
+                &schedule::Schedule::default(),
+            )),
+            meeting: None,
+            flash_config: config::FlashConfig::default(),
+            flash: None,
+            mini_window: false,
+            normal_size: window::Settings::default().size,
+            normal_position: None,
+            window_position: None,
+            mini_moved_at: None,
+            state: state::State::default(),
+            state_path: None,
+            last_tick: None,
+            suspend_notice: None,
         }
     }
 
     #[test]
     fn test_initial_state() {
-        let (timer, _) = RecurringTimer::new();
-        assert_eq!(timer.interval_secs, 60);
-        assert_eq!(timer.num_rounds, 20);
-        assert_eq!(timer.timer_state, TimerState::Stopped);
-        assert_eq!(timer.elapsed_secs, 0);
-        assert_eq!(timer.round_number, 1);
-        assert_eq!(timer.total_duration_secs, 1200);
+        let (timer, _) = RecurringTimer::new(
+            cli::Options::default(),
+            config::Config::default(),
+            schedule::Schedule::default(),
+            None,
+        );
+        assert_eq!(timer.engine.interval_secs, 60);
+        assert_eq!(timer.engine.num_rounds, 20);
+        assert_eq!(timer.engine.timer_state, TimerState::Stopped);
+        assert_eq!(timer.engine.elapsed_secs, 0);
+        assert_eq!(timer.engine.round_number, 1);
+        assert_eq!(timer.engine.total_duration_secs, 1200);
     }
 
     #[test]
     fn test_start_message() {
         let mut timer = create_test_timer();
-        timer.elapsed_secs = 100;
-        timer.round_number = 5;
+        timer.engine.elapsed_secs = 100;
+        timer.engine.round_number = 5;
 
         let _ = timer.update(Message::Start);
 
-        assert_eq!(timer.timer_state, TimerState::Running);
-        assert_eq!(timer.elapsed_secs, 0);
-        assert_eq!(timer.round_number, 1);
-        assert_eq!(timer.total_duration_secs, 1200);
+        assert_eq!(timer.engine.timer_state, TimerState::Running);
+        assert_eq!(timer.engine.elapsed_secs, 0);
+        assert_eq!(timer.engine.round_number, 1);
+        assert_eq!(timer.engine.total_duration_secs, 1200);
     }
 
     #[test]
     fn test_pause_message() {
         let mut timer = create_test_timer();
-        timer.timer_state = TimerState::Running;
-        timer.elapsed_secs = 30;
+        timer.engine.timer_state = TimerState::Running;
+        timer.engine.elapsed_secs = 30;
 
         let _ = timer.update(Message::Pause);
 
-        assert_eq!(timer.timer_state, TimerState::Paused);
-        assert_eq!(timer.elapsed_secs, 30); // Elapsed time should be preserved
+        assert_eq!(timer.engine.timer_state, TimerState::Paused);
+        assert_eq!(timer.engine.elapsed_secs, 30); // Elapsed time should be preserved
     }
 
     #[test]
     fn test_resume_message() {
         let mut timer = create_test_timer();
-        timer.timer_state = TimerState::Paused;
-        timer.elapsed_secs = 30;
+        timer.engine.timer_state = TimerState::Paused;
+        timer.engine.elapsed_secs = 30;
 
         let _ = timer.update(Message::Resume);
 
-        assert_eq!(timer.timer_state, TimerState::Running);
-        assert_eq!(timer.elapsed_secs, 30); // Elapsed time should be preserved
+        assert_eq!(timer.engine.timer_state, TimerState::Running);
+        assert_eq!(timer.engine.elapsed_secs, 30); // Elapsed time should be preserved
     }
 
     #[test]
     fn test_stop_message() {
         let mut timer = create_test_timer();
-        timer.timer_state = TimerState::Running;
-        timer.elapsed_secs = 100;
-        timer.round_number = 5;
+        timer.engine.timer_state = TimerState::Running;
+        timer.engine.elapsed_secs = 100;
+        timer.engine.round_number = 5;
 
         let _ = timer.update(Message::Stop);
 
-        assert_eq!(timer.timer_state, TimerState::Stopped);
-        assert_eq!(timer.elapsed_secs, 0);
-        assert_eq!(timer.round_number, 1);
+        assert_eq!(timer.engine.timer_state, TimerState::Stopped);
+        assert_eq!(timer.engine.elapsed_secs, 0);
+        assert_eq!(timer.engine.round_number, 1);
     }
 
     #[test]
     fn test_tick_increments_elapsed_time() {
         let mut timer = create_test_timer();
-        timer.timer_state = TimerState::Running;
+        timer.engine.timer_state = TimerState::Running;
 
         let _ = timer.update(Message::Tick);
 
-        assert_eq!(timer.elapsed_secs, 1);
+        assert_eq!(timer.engine.elapsed_secs, 1);
     }
 
     #[test]
     fn test_tick_does_not_increment_when_stopped() {
         let mut timer = create_test_timer();
-        timer.timer_state = TimerState::Stopped;
+        timer.engine.timer_state = TimerState::Stopped;
 
         let _ = timer.update(Message::Tick);
 
-        assert_eq!(timer.elapsed_secs, 0);
+        assert_eq!(timer.engine.elapsed_secs, 0);
     }
 
     #[test]
     fn test_tick_does_not_increment_when_paused() {
         let mut timer = create_test_timer();
-        timer.timer_state = TimerState::Paused;
-        timer.elapsed_secs = 30;
+        timer.engine.timer_state = TimerState::Paused;
+        timer.engine.elapsed_secs = 30;
 
         let _ = timer.update(Message::Tick);
 
-        assert_eq!(timer.elapsed_secs, 30);
+        assert_eq!(timer.engine.elapsed_secs, 30);
     }
 
     #[test]
     fn test_round_number_increments_at_interval() {
         let mut timer = create_test_timer();
-        timer.interval_secs = 10;
-        timer.num_rounds = 5;
-        timer.total_duration_secs = 50;
-        timer.timer_state = TimerState::Running;
+        timer.engine.interval_secs = 10;
+        timer.engine.num_rounds = 5;
+        timer.engine.total_duration_secs = 50;
+        timer.engine.timer_state = TimerState::Running;
 
         // Advance to 10 seconds (first chime)
         for _ in 0..10 {
             let _ = timer.update(Message::Tick);
         }
 
-        assert_eq!(timer.elapsed_secs, 10);
-        assert_eq!(timer.round_number, 2);
+        assert_eq!(timer.engine.elapsed_secs, 10);
+        assert_eq!(timer.engine.round_number, 2);
     }
 
     #[test]
     fn test_round_number_does_not_increment_at_final_chime() {
         let mut timer = create_test_timer();
-        timer.interval_secs = 10;
-        timer.num_rounds = 2;
-        timer.total_duration_secs = 20;
-        timer.timer_state = TimerState::Running;
+        timer.engine.interval_secs = 10;
+        timer.engine.num_rounds = 2;
+        timer.engine.total_duration_secs = 20;
+        timer.engine.timer_state = TimerState::Running;
 
         // Advance to 20 seconds (final chime)
         for _ in 0..20 {
             let _ = timer.update(Message::Tick);
         }
 
-        assert_eq!(timer.elapsed_secs, 20);
-        assert_eq!(timer.round_number, 2);
-        assert_eq!(timer.timer_state, TimerState::Stopped);
+        assert_eq!(timer.engine.elapsed_secs, 20);
+        assert_eq!(timer.engine.round_number, 2);
+        assert_eq!(timer.engine.timer_state, TimerState::Stopped);
     }
 
     #[test]
     fn test_timer_stops_at_total_duration() {
         let mut timer = create_test_timer();
-        timer.interval_secs = 10;
-        timer.num_rounds = 3;
-        timer.total_duration_secs = 30;
-        timer.timer_state = TimerState::Running;
+        timer.engine.interval_secs = 10;
+        timer.engine.num_rounds = 3;
+        timer.engine.total_duration_secs = 30;
+        timer.engine.timer_state = TimerState::Running;
 
         // Advance past total duration
         for _ in 0..31 {
             let _ = timer.update(Message::Tick);
         }
 
-        assert_eq!(timer.timer_state, TimerState::Stopped);
-        assert_eq!(timer.elapsed_secs, 30);
+        assert_eq!(timer.engine.timer_state, TimerState::Stopped);
+        assert_eq!(timer.engine.elapsed_secs, 30);
     }
 
     #[test]
     fn test_interval_changed_valid_input() {
         let mut timer = create_test_timer();
-        timer.interval_secs = 60;
-        timer.num_rounds = 20;
+        timer.engine.interval_secs = 60;
+        timer.engine.num_rounds = 20;
 
         let _ = timer.update(Message::IntervalChanged(String::from("90")));
 
         assert_eq!(timer.interval_input, "90");
-        assert_eq!(timer.interval_secs, 90);
-        assert_eq!(timer.total_duration_secs, 90 * 20);
+        assert_eq!(timer.engine.interval_secs, 90);
+        assert_eq!(timer.engine.total_duration_secs, 90 * 20);
     }
 
     #[test]
     fn test_interval_changed_invalid_input() {
         let mut timer = create_test_timer();
-        let original_interval = timer.interval_secs;
-        let original_duration = timer.total_duration_secs;
+        let original_interval = timer.engine.interval_secs;
+        let original_duration = timer.engine.total_duration_secs;
 
         let _ = timer.update(Message::IntervalChanged(String::from("abc")));
 
         assert_eq!(timer.interval_input, "abc");
-        assert_eq!(timer.interval_secs, original_interval);
-        assert_eq!(timer.total_duration_secs, original_duration);
+        assert_eq!(timer.engine.interval_secs, original_interval);
+        assert_eq!(timer.engine.total_duration_secs, original_duration);
+    }
+
+    #[test]
+    fn test_interval_too_long_for_the_rounds_is_rejected() {
+        let mut timer = create_test_timer();
+
+        let _ = timer.update(Message::IntervalChanged(String::from("4294967295")));
+        assert_eq!(timer.engine.interval_secs, 60);
+
+        let _ = timer.update(Message::RoundsChanged(String::from("4294967295")));
+        assert_eq!(timer.engine.num_rounds, 20);
     }
 
     #[test]
     fn test_interval_changed_zero_rejected() {
         let mut timer = create_test_timer();
-        let original_interval = timer.interval_secs;
-        let original_duration = timer.total_duration_secs;
+        let original_interval = timer.engine.interval_secs;
+        let original_duration = timer.engine.total_duration_secs;
 
         let _ = timer.update(Message::IntervalChanged(String::from("0")));
 
         assert_eq!(timer.interval_input, "0");
-        assert_eq!(timer.interval_secs, original_interval);
-        assert_eq!(timer.total_duration_secs, original_duration);
+        assert_eq!(timer.engine.interval_secs, original_interval);
+        assert_eq!(timer.engine.total_duration_secs, original_duration);
     }
 
     #[test]
     fn test_rounds_changed_valid_input() {
         let mut timer = create_test_timer();
-        timer.interval_secs = 60;
-        timer.num_rounds = 20;
+        timer.engine.interval_secs = 60;
+        timer.engine.num_rounds = 20;
 
         let _ = timer.update(Message::RoundsChanged(String::from("30")));
 
         assert_eq!(timer.rounds_input, "30");
-        assert_eq!(timer.num_rounds, 30);
-        assert_eq!(timer.total_duration_secs, 60 * 30);
+        assert_eq!(timer.engine.num_rounds, 30);
+        assert_eq!(timer.engine.total_duration_secs, 60 * 30);
     }
 
     #[test]
     fn test_rounds_changed_invalid_input() {
         let mut timer = create_test_timer();
-        let original_rounds = timer.num_rounds;
-        let original_duration = timer.total_duration_secs;
+        let original_rounds = timer.engine.num_rounds;
+        let original_duration = timer.engine.total_duration_secs;
 
         let _ = timer.update(Message::RoundsChanged(String::from("xyz")));
 
         assert_eq!(timer.rounds_input, "xyz");
-        assert_eq!(timer.num_rounds, original_rounds);
-        assert_eq!(timer.total_duration_secs, original_duration);
+        assert_eq!(timer.engine.num_rounds, original_rounds);
+        assert_eq!(timer.engine.total_duration_secs, original_duration);
     }
 
     #[test]
     fn test_rounds_changed_zero_rejected() {
         let mut timer = create_test_timer();
-        let original_rounds = timer.num_rounds;
-        let original_duration = timer.total_duration_secs;
+        let original_rounds = timer.engine.num_rounds;
+        let original_duration = timer.engine.total_duration_secs;
 
         let _ = timer.update(Message::RoundsChanged(String::from("0")));
 
         assert_eq!(timer.rounds_input, "0");
-        assert_eq!(timer.num_rounds, original_rounds);
-        assert_eq!(timer.total_duration_secs, original_duration);
+        assert_eq!(timer.engine.num_rounds, original_rounds);
+        assert_eq!(timer.engine.total_duration_secs, original_duration);
     }
 
     #[test]
     fn test_multiple_rounds() {
         let mut timer = create_test_timer();
-        timer.interval_secs = 5;
-        timer.num_rounds = 4;
-        timer.total_duration_secs = 20;
-        timer.timer_state = TimerState::Running;
+        timer.engine.interval_secs = 5;
+        timer.engine.num_rounds = 4;
+        timer.engine.total_duration_secs = 20;
+        timer.engine.timer_state = TimerState::Running;
 
         // Round 1: 0-5 seconds
         for _ in 0..5 {
             let _ = timer.update(Message::Tick);
         }
-        assert_eq!(timer.round_number, 2);
-        assert_eq!(timer.timer_state, TimerState::Running);
+        assert_eq!(timer.engine.round_number, 2);
+        assert_eq!(timer.engine.timer_state, TimerState::Running);
 
         // Round 2: 5-10 seconds
         for _ in 0..5 {
             let _ = timer.update(Message::Tick);
         }
-        assert_eq!(timer.round_number, 3);
-        assert_eq!(timer.timer_state, TimerState::Running);
+        assert_eq!(timer.engine.round_number, 3);
+        assert_eq!(timer.engine.timer_state, TimerState::Running);
 
         // Round 3: 10-15 seconds
         for _ in 0..5 {
             let _ = timer.update(Message::Tick);
         }
-        assert_eq!(timer.round_number, 4);
-        assert_eq!(timer.timer_state, TimerState::Running);
+        assert_eq!(timer.engine.round_number, 4);
+        assert_eq!(timer.engine.timer_state, TimerState::Running);
 
         // Round 4: 15-20 seconds (final)
         for _ in 0..5 {
             let _ = timer.update(Message::Tick);
         }
-        assert_eq!(timer.round_number, 4);
-        assert_eq!(timer.timer_state, TimerState::Stopped);
+        assert_eq!(timer.engine.round_number, 4);
+        assert_eq!(timer.engine.timer_state, TimerState::Stopped);
     }
 
     #[test]
     fn test_pause_resume_preserves_state() {
         let mut timer = create_test_timer();
-        timer.interval_secs = 10;
-        timer.num_rounds = 3;
-        timer.total_duration_secs = 30;
-        timer.timer_state = TimerState::Running;
+        timer.engine.interval_secs = 10;
+        timer.engine.num_rounds = 3;
+        timer.engine.total_duration_secs = 30;
+        timer.engine.timer_state = TimerState::Running;
 
         // Run for 7 seconds
         for _ in 0..7 {
             let _ = timer.update(Message::Tick);
         }
-        assert_eq!(timer.elapsed_secs, 7);
-        assert_eq!(timer.round_number, 1);
+        assert_eq!(timer.engine.elapsed_secs, 7);
+        assert_eq!(timer.engine.round_number, 1);
 
         // Pause
         let _ = timer.update(Message::Pause);
-        assert_eq!(timer.timer_state, TimerState::Paused);
+        assert_eq!(timer.engine.timer_state, TimerState::Paused);
 
         // Tick while paused - should not change
         let _ = timer.update(Message::Tick);
-        assert_eq!(timer.elapsed_secs, 7);
+        assert_eq!(timer.engine.elapsed_secs, 7);
 
         // Resume
         let _ = timer.update(Message::Resume);
-        assert_eq!(timer.timer_state, TimerState::Running);
+        assert_eq!(timer.engine.timer_state, TimerState::Running);
 
         // Continue for 3 more seconds to reach first chime
         for _ in 0..3 {
             let _ = timer.update(Message::Tick);
         }
-        assert_eq!(timer.elapsed_secs, 10);
-        assert_eq!(timer.round_number, 2);
+        assert_eq!(timer.engine.elapsed_secs, 10);
+        assert_eq!(timer.engine.round_number, 2);
+    }
+
+    #[test]
+    fn test_new_applies_options() {
+        let options = cli::Options {
+            interval_secs: 90,
+            num_rounds: 10,
+            auto_start: true,
+            ..cli::Options::default()
+        };
+
+        let (timer, _) = RecurringTimer::new(
+            options,
+            config::Config::default(),
+            schedule::Schedule::default(),
+            None,
+        );
+
+        assert_eq!(timer.interval_input, "90");
+        assert_eq!(timer.rounds_input, "10");
+        assert_eq!(timer.engine.total_duration_secs, 900);
+        assert_eq!(timer.engine.timer_state, TimerState::Running);
+    }
+
+    #[test]
+    fn test_lead_in_counts_down_before_first_round() {
+        let mut timer = create_test_timer();
+        timer.engine.interval_secs = 10;
+        timer.engine.num_rounds = 2;
+        timer.engine.lead_in_secs = 3;
+
+        let _ = timer.update(Message::Start);
+        assert_eq!(timer.engine.lead_in_remaining, 3);
+
+        for _ in 0..3 {
+            let _ = timer.update(Message::Tick);
+        }
+        assert_eq!(timer.engine.lead_in_remaining, 0);
+        assert_eq!(timer.engine.elapsed_secs, 0);
+
+        let _ = timer.update(Message::Tick);
+        assert_eq!(timer.engine.elapsed_secs, 1);
+    }
+
+    #[test]
+    fn test_stop_clears_lead_in() {
+        let mut timer = create_test_timer();
+        timer.engine.lead_in_secs = 5;
+
+        let _ = timer.update(Message::Start);
+        let _ = timer.update(Message::Stop);
+
+        assert_eq!(timer.engine.lead_in_remaining, 0);
+    }
+
+    #[test]
+    fn test_apply_preset() {
+        let mut timer = create_test_timer();
+
+        let _ = timer.update(Message::ApplyPreset("boxing"));
+
+        assert_eq!(timer.engine.interval_secs, 180);
+        assert_eq!(timer.engine.num_rounds, 12);
+        assert_eq!(timer.interval_input, "180");
+        assert_eq!(timer.rounds_input, "12");
+    }
+
+    #[test]
+    fn test_apply_preset_ignored_while_running() {
+        let mut timer = create_test_timer();
+        let _ = timer.update(Message::Start);
+
+        let _ = timer.update(Message::ApplyPreset("boxing"));
+
+        assert_eq!(timer.engine.interval_secs, 60);
+        assert_eq!(timer.engine.num_rounds, 20);
+    }
+
+    #[test]
+    fn test_suspend_pauses_with_notice() {
+        let mut timer = create_test_timer();
+        let _ = timer.update(Message::Start);
+        timer.tick(Duration::ZERO);
+        timer.tick(Duration::from_secs(600));
+
+        assert_eq!(timer.engine.timer_state, TimerState::Paused);
+        assert_eq!(timer.engine.elapsed_secs, 1);
+        assert!(timer.suspend_notice.is_some());
+
+        let _ = timer.update(Message::Resume);
+        assert!(timer.suspend_notice.is_none());
+    }
+
+    #[test]
+    fn test_suspend_catches_up() {
+        let mut timer = create_test_timer();
+        timer.power.after_suspend = config::AfterSuspend::CatchUp;
+        let _ = timer.update(Message::Start);
+        let (event_sender, mut events) = broadcast::channel(16);
+        timer.event_sender = event_sender;
+
+        timer.tick(Duration::ZERO);
+        timer.tick(Duration::from_secs(150));
+
+        assert_eq!(timer.engine.timer_state, TimerState::Running);
+        assert_eq!(timer.engine.elapsed_secs, 152);
+        assert_eq!(timer.engine.round_number, 3);
+        assert!(timer.suspend_notice.is_none());
+        // One event for where the session is now, not one per missed chime
+        assert_eq!(
+            events.try_recv().unwrap(),
+            TimerEvent::RoundStarted {
+                round: 3,
+                label: None
+            }
+        );
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_follow_leader() {
+        let mut timer = create_test_timer();
+        let beacon = serde_json::from_value(serde_json::json!({
+            "session": "default",
+            "state": "running",
+            "interval_secs": 45,
+            "rounds": 8,
+            "lead_in_secs": 0,
+            "warning_secs": 0,
+            "elapsed_secs": 1,
+            "round": 1,
+            "lead_in_remaining_secs": 0,
+            "tick_age_ms": 0,
+            "sent_ms": 0,
+        }))
+        .unwrap();
+
+        let _ = timer.update(Message::LeaderLost);
+        assert!(timer.sync_notice.is_some());
+
+        let _ = timer.update(Message::Follow(beacon));
+        assert_eq!(timer.engine.timer_state, TimerState::Running);
+        assert_eq!(timer.engine.elapsed_secs, 1);
+        assert_eq!(timer.interval_input, "45");
+        assert_eq!(timer.rounds_input, "8");
+        assert!(timer.sync_notice.is_none());
+    }
+
+    #[test]
+    fn test_toggle_big_clock() {
+        let mut timer = create_test_timer();
+
+        let _ = timer.update(Message::ToggleBigClock);
+        assert!(timer.big_clock);
+        assert_eq!(timer.window_mode(), window::Mode::Fullscreen);
+
+        let _ = timer.update(Message::ToggleBigClock);
+        assert!(!timer.big_clock);
+        assert_eq!(timer.window_mode(), window::Mode::Windowed);
+    }
+
+    #[test]
+    fn test_window_returns_to_its_launch_mode() {
+        let mut timer = create_test_timer();
+        timer.launch_mode = cli::WindowMode::Fullscreen;
+        assert_eq!(timer.window_mode(), window::Mode::Fullscreen);
+
+        // Leaving the big clock keeps a full-screen launch full screen
+        let _ = timer.update(Message::ToggleBigClock);
+        let _ = timer.update(Message::ToggleBigClock);
+        assert_eq!(timer.window_mode(), window::Mode::Fullscreen);
+    }
+
+    #[test]
+    fn test_big_clock_controls_show_on_mouse_move() {
+        let mut timer = create_test_timer();
+        let _ = timer.update(Message::MouseMoved);
+        assert!(timer.controls_shown_at.is_none());
+
+        let _ = timer.update(Message::ToggleBigClock);
+        let _ = timer.update(Message::MouseMoved);
+        assert!(timer.controls_shown_at.is_some());
+
+        // Still shown right after moving, hidden once the mouse rests
+        let _ = timer.update(Message::HideControls);
+        assert!(timer.controls_shown_at.is_some());
+        timer.controls_shown_at = Some(Instant::now() - CONTROLS_TIMEOUT);
+        let _ = timer.update(Message::HideControls);
+        assert!(timer.controls_shown_at.is_none());
+    }
+
+    #[test]
+    fn test_phase_color() {
+        let mut timer = create_test_timer();
+        let stopped = timer.phase_color();
+
+        timer.engine.set_warning(10);
+        let _ = timer.update(Message::Start);
+        let running = timer.phase_color();
+        assert_ne!(running, stopped);
+
+        for _ in 0..50 {
+            timer.engine.tick();
+        }
+        let warning = timer.phase_color();
+        assert_ne!(warning, running);
+
+        let _ = timer.update(Message::Pause);
+        assert_ne!(timer.phase_color(), warning);
+    }
+
+    #[test]
+    fn test_short_interval_has_no_warning_colour() {
+        let mut timer = create_test_timer();
+        timer.engine.set_warning(10);
+        let _ = timer.update(Message::IntervalChanged(String::from("5")));
+        let _ = timer.update(Message::Start);
+        let running = timer.phase_color();
+        timer.engine.tick();
+        assert_eq!(timer.phase_color(), running);
+        assert_eq!(timer.engine.warning_secs, 0);
+    }
+
+    fn press(timer: &mut RecurringTimer, key: keyboard::Key) {
+        let _ = timer.update(Message::KeyPressed(key, keyboard::Modifiers::empty()));
+    }
+
+    fn character(c: &str) -> keyboard::Key {
+        keyboard::Key::Character(c.into())
+    }
+
+    #[test]
+    fn test_keyboard_controls_the_timer() {
+        let mut timer = create_test_timer();
+        let space = keyboard::Key::Named(keyboard::key::Named::Space);
+
+        press(&mut timer, space.clone());
+        assert_eq!(timer.engine.timer_state, TimerState::Running);
+        press(&mut timer, space);
+        assert_eq!(timer.engine.timer_state, TimerState::Paused);
+
+        press(&mut timer, character("n"));
+        assert_eq!(timer.engine.round_number, 2);
+        press(&mut timer, character("p"));
+        assert_eq!(timer.engine.round_number, 1);
+
+        press(&mut timer, character("-"));
+        assert_eq!(timer.engine.round_remaining_secs(), 50);
+        press(&mut timer, character("+"));
+        assert_eq!(timer.engine.round_remaining_secs(), 60);
+
+        press(
+            &mut timer,
+            keyboard::Key::Named(keyboard::key::Named::Escape),
+        );
+        assert_eq!(timer.engine.timer_state, TimerState::Stopped);
+    }
+
+    #[test]
+    fn test_time_keys_change_the_interval_when_stopped() {
+        let mut timer = create_test_timer();
+        press(&mut timer, character("+"));
+        assert_eq!(timer.engine.interval_secs, 70);
+        assert_eq!(timer.interval_input, "70");
+
+        timer.engine.set_interval(5);
+        press(&mut timer, character("-"));
+        assert_eq!(timer.engine.interval_secs, 1);
+    }
+
+    #[test]
+    fn test_mute_and_help_keys() {
+        let mut timer = create_test_timer();
+        press(&mut timer, character("m"));
+        assert!(timer.audio_player.is_muted());
+        press(&mut timer, character("m"));
+        assert!(!timer.audio_player.is_muted());
+
+        press(&mut timer, character("?"));
+        assert!(timer.help_shown);
+        press(&mut timer, character("f"));
+        assert!(timer.big_clock);
+        press(&mut timer, keyboard::Key::Named(keyboard::key::Named::F11));
+        assert!(!timer.big_clock);
+        press(&mut timer, character("f"));
+
+        // Esc closes the help without stopping the timer
+        let _ = timer.update(Message::Start);
+        press(
+            &mut timer,
+            keyboard::Key::Named(keyboard::key::Named::Escape),
+        );
+        assert!(!timer.help_shown);
+        assert_eq!(timer.engine.timer_state, TimerState::Running);
+    }
+
+    #[test]
+    fn test_toggle_mini_window() {
+        let mut timer = create_test_timer();
+        timer.window_size = Size::new(800.0, 600.0);
+        let _ = timer.update(Message::ToggleBigClock);
+
+        let _ = timer.update(Message::ToggleMiniWindow);
+        assert!(timer.mini_window);
+        assert!(!timer.big_clock);
+        assert_eq!(timer.normal_size, Size::new(800.0, 600.0));
+
+        // The big clock stays out of the way while the mini window is open
+        let _ = timer.update(Message::ToggleBigClock);
+        assert!(!timer.big_clock);
+
+        let _ = timer.update(Message::ToggleMiniWindow);
+        assert!(!timer.mini_window);
+    }
+
+    #[test]
+    fn test_leaving_mini_window_returns_to_main_position() {
+        let mut timer = create_test_timer();
+        let _ = timer.update(Message::WindowMoved(Point::new(300.0, 200.0)));
+
+        let _ = timer.update(Message::ToggleMiniWindow);
+        assert_eq!(timer.normal_position, Some(Point::new(300.0, 200.0)));
+        let _ = timer.update(Message::WindowMoved(Point::new(1500.0, 30.0)));
+
+        // The main window's own position is not overwritten by the mini one
+        assert_eq!(timer.normal_position, Some(Point::new(300.0, 200.0)));
+        assert_eq!(timer.state.mini_position, Some((1500.0, 30.0)));
+    }
+
+    #[test]
+    fn test_mini_window_position_is_saved() {
+        let path = std::env::temp_dir()
+            .join(format!("round-timer-mini-{}", std::process::id()))
+            .join("state.json");
+        let mut timer = create_test_timer();
+        timer.state_path = Some(path.clone());
+
+        // Only the mini window's position is remembered
+        let _ = timer.update(Message::WindowMoved(Point::new(10.0, 20.0)));
+        assert_eq!(timer.state.mini_position, None);
+
+        let _ = timer.update(Message::ToggleMiniWindow);
+        let _ = timer.update(Message::WindowMoved(Point::new(1400.0, 30.0)));
+        let _ = timer.update(Message::WindowMoved(Point::new(1500.0, 30.0)));
+        assert_eq!(timer.state.mini_position, Some((1500.0, 30.0)));
+
+        // Nothing is written while the window is still being dragged
+        let _ = timer.update(Message::SaveMiniPosition);
+        assert!(!path.exists());
+
+        timer.mini_moved_at = Some(Instant::now() - SAVE_DELAY);
+        let _ = timer.update(Message::SaveMiniPosition);
+        assert_eq!(
+            state::State::load(&path).mini_position,
+            Some((1500.0, 30.0))
+        );
+        assert!(timer.mini_moved_at.is_none());
+
+        // Leaving the mini window saves a position that has not settled yet
+        let _ = timer.update(Message::WindowMoved(Point::new(1600.0, 40.0)));
+        let _ = timer.update(Message::ToggleMiniWindow);
+        assert_eq!(
+            state::State::load(&path).mini_position,
+            Some((1600.0, 40.0))
+        );
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_meeting_names_the_speaker_and_sums_up_at_the_end() {
+        let mut timer = create_test_timer();
+        let speakers = vec![String::from("Ana"), String::from("Bo")];
+        timer.meeting = meeting::Meeting::new(speakers, false);
+        timer.engine.set_rounds(2);
+
+        // The rounds and their length stay as the meeting set them
+        let _ = timer.update(Message::RoundsChanged(String::from("5")));
+        let _ = timer.update(Message::ApplyPreset("boxing"));
+        let _ = timer.update(Message::AdjustTime(30));
+        assert_eq!(timer.engine.num_rounds, 2);
+        assert_eq!(timer.engine.interval_secs, 60);
+
+        let _ = timer.update(Message::Start);
+        assert_eq!(timer.current_label().as_deref(), Some("Speaking: Ana"));
+        assert_eq!(timer.engine.next_label().as_deref(), Some("Bo"));
+        assert_eq!(timer.meeting_summary(), None);
+
+        for _ in 0..20 {
+            let _ = timer.update(Message::Tick);
+        }
+        // Ana is done early
+        let _ = timer.update(Message::Skip);
+        assert_eq!(timer.current_label().as_deref(), Some("Speaking: Bo"));
+        for _ in 0..60 {
+            let _ = timer.update(Message::Tick);
+        }
+
+        assert_eq!(timer.engine.timer_state, TimerState::Stopped);
+        assert_eq!(
+            timer.meeting_summary(),
+            Some(vec![
+                String::from("Ana: 00:20"),
+                String::from("Bo: 01:00"),
+                String::from("Total: 01:20"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_chimes_flash_when_enabled() {
+        let mut timer = create_test_timer();
+        let _ = timer.update(Message::Start);
+        for _ in 0..60 {
+            let _ = timer.update(Message::Tick);
+        }
+        assert_eq!(timer.flash, None);
+
+        timer.flash_config.enabled = true;
+        for _ in 0..60 {
+            let _ = timer.update(Message::Tick);
+        }
+        assert!(timer.flash.is_some());
+        assert!(timer.flash_background().is_some());
+
+        // Frames keep the flash until its pattern is over
+        let _ = timer.update(Message::FlashFrame);
+        assert!(timer.flash.is_some());
+    }
+
+    #[test]
+    fn test_timeline_jumps_to_a_round_when_paused() {
+        let mut timer = create_test_timer();
+        let _ = timer.update(Message::Start);
+        let _ = timer.update(Message::JumpTo(5));
+        assert_eq!(timer.engine.round_number, 1);
+
+        let _ = timer.update(Message::Pause);
+        let _ = timer.update(Message::JumpTo(5));
+        assert_eq!(timer.engine.round_number, 5);
+        assert_eq!(timer.engine.remaining_secs(), 16 * 60);
+        assert_eq!(timer.engine.timer_state, TimerState::Paused);
+    }
+
+    #[test]
+    fn test_timeline_is_rebuilt_only_when_the_session_changes() {
+        let mut timer = create_test_timer();
+        let blocks = timer.timeline_blocks.clone();
+        let _ = timer.update(Message::Start);
+        let _ = timer.update(Message::Tick);
+        assert!(Rc::ptr_eq(&blocks, &timer.timeline_blocks));
+
+        let _ = timer.update(Message::Stop);
+        let _ = timer.update(Message::RoundsChanged(String::from("4")));
+        assert!(!Rc::ptr_eq(&blocks, &timer.timeline_blocks));
+        assert!(timer
+            .timeline_blocks
+            .fit(&timer.engine.snapshot(), &timer.engine.schedule));
     }
 }
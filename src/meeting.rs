@@ -0,0 +1,161 @@
+use crate::config::RoundConfig;
+use crate::engine::{format_time, Engine};
+use crate::schedule::Schedule;
+
+/// Stand-ups and other meetings where each round is one speaker's turn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Meeting {
+    speakers: Vec<String>,
+    shuffle: bool,
+}
+
+/// How long one speaker actually took.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Turn {
+    pub speaker: String,
+    pub secs: u32,
+}
+
+impl Meeting {
+    /// `None` without speakers, which leaves the meeting mode off.
+    pub fn new(speakers: Vec<String>, shuffle: bool) -> Option<Self> {
+        (!speakers.is_empty()).then_some(Self { speakers, shuffle })
+    }
+
+    /// One round per speaker.
+    pub fn rounds(&self) -> u32 {
+        self.speakers.len() as u32
+    }
+
+    /// A round for each speaker, labelled with their name. Shuffled meetings
+    /// get a new order every time.
+    pub fn schedule(&self) -> Schedule {
+        let mut speakers = self.speakers.clone();
+        if self.shuffle {
+            fastrand::shuffle(&mut speakers);
+        }
+        let rounds = speakers
+            .into_iter()
+            .map(|speaker| RoundConfig {
+                label: speaker,
+                ..RoundConfig::default()
+            })
+            .collect();
+        Schedule::new(rounds, None)
+    }
+}
+
+/// The turns taken so far in the engine's session, with the speaker of each.
+pub fn turns(engine: &Engine) -> Vec<Turn> {
+    (1..)
+        .zip(&engine.round_times)
+        .map(|(round, &secs)| Turn {
+            speaker: engine
+                .schedule
+                .label(round)
+                .unwrap_or_else(|| format!("Round {}", round)),
+            secs,
+        })
+        .collect()
+}
+
+/// The turns as lines of text, ending with the whole meeting's time.
+pub fn summary(turns: &[Turn]) -> Vec<String> {
+    let mut lines: Vec<String> = turns
+        .iter()
+        .map(|turn| format!("{}: {}", turn.speaker, format_time(turn.secs)))
+        .collect();
+    let total = turns.iter().map(|turn| turn.secs).sum();
+    lines.push(format!("Total: {}", format_time(total)));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn labels(schedule: &Schedule) -> Vec<String> {
+        schedule
+            .entries()
+            .iter()
+            .map(|entry| entry.label.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_no_speakers_is_no_meeting() {
+        assert_eq!(Meeting::new(Vec::new(), true), None);
+    }
+
+    #[test]
+    fn test_one_round_per_speaker_in_order() {
+        let meeting = Meeting::new(names(&["Ana", "Bo", "Cy"]), false).unwrap();
+        assert_eq!(meeting.rounds(), 3);
+        assert_eq!(labels(&meeting.schedule()), ["Ana", "Bo", "Cy"]);
+    }
+
+    #[test]
+    fn test_shuffle_keeps_every_speaker() {
+        let speakers = names(&["Ana", "Bo", "Cy", "Di", "Ed", "Flo"]);
+        let meeting = Meeting::new(speakers.clone(), true).unwrap();
+        let mut shuffled = labels(&meeting.schedule());
+        shuffled.sort();
+        assert_eq!(shuffled, speakers);
+    }
+
+    #[test]
+    fn test_turns_and_summary() {
+        let meeting = Meeting::new(names(&["Ana", "Bo", "Cy"]), false).unwrap();
+        let mut engine = Engine::new(60, meeting.rounds(), 0);
+        engine.schedule = meeting.schedule();
+        engine.start();
+        for _ in 0..45 {
+            engine.tick();
+        }
+        // Done early
+        engine.skip();
+        for _ in 0..60 {
+            engine.tick();
+        }
+        for _ in 0..5 {
+            engine.tick();
+        }
+        engine.stop();
+
+        let turns = turns(&engine);
+        assert_eq!(
+            turns,
+            [
+                Turn {
+                    speaker: String::from("Ana"),
+                    secs: 45
+                },
+                Turn {
+                    speaker: String::from("Bo"),
+                    secs: 60
+                },
+                Turn {
+                    speaker: String::from("Cy"),
+                    secs: 5
+                },
+            ]
+        );
+        assert_eq!(
+            summary(&turns),
+            ["Ana: 00:45", "Bo: 01:00", "Cy: 00:05", "Total: 01:50"]
+        );
+    }
+
+    #[test]
+    fn test_turns_without_labels_are_numbered() {
+        let mut engine = Engine::new(10, 2, 0);
+        engine.start();
+        engine.tick();
+        engine.skip();
+        assert_eq!(turns(&engine)[0].speaker, "Round 1");
+    }
+}
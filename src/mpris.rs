@@ -0,0 +1,387 @@
+use iced::futures::channel::mpsc;
+use iced::Subscription;
+use std::collections::HashMap;
+use tokio::sync::watch;
+use zbus::connection::Builder;
+use zbus::object_server::InterfaceRef;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::{fdo, interface, Connection};
+
+use crate::dbus::send;
+use crate::engine::{format_time, Snapshot, TimerState};
+use crate::Message;
+
+pub const BUS_NAME: &str = "org.mpris.MediaPlayer2.roundtimer";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+
+/// The `org.mpris.MediaPlayer2` root interface.
+struct Root;
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "Round Timer"
+    }
+
+    #[zbus(property)]
+    fn desktop_entry(&self) -> &str {
+        "round-timer"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// The `org.mpris.MediaPlayer2.Player` interface, where each round is a track.
+struct Player {
+    status: watch::Receiver<Snapshot>,
+    output: mpsc::Sender<Message>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    async fn play(&self) -> fdo::Result<()> {
+        // Resuming a stopped timer does nothing, so start a new session instead
+        let message = match self.status.borrow().state {
+            TimerState::Stopped => Message::Start,
+            _ => Message::Resume,
+        };
+        send(&self.output, message).await
+    }
+
+    async fn pause(&self) -> fdo::Result<()> {
+        send(&self.output, Message::Pause).await
+    }
+
+    async fn play_pause(&self) -> fdo::Result<()> {
+        send(&self.output, Message::Toggle).await
+    }
+
+    async fn stop(&self) -> fdo::Result<()> {
+        send(&self.output, Message::Stop).await
+    }
+
+    async fn next(&self) -> fdo::Result<()> {
+        send(&self.output, Message::Skip).await
+    }
+
+    async fn previous(&self) -> fdo::Result<()> {
+        send(&self.output, Message::Previous).await
+    }
+
+    fn seek(&self, _offset: i64) {}
+
+    fn set_position(&self, _track_id: ObjectPath<'_>, _position: i64) {}
+
+    fn open_uri(&self, _uri: &str) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported(String::from(
+            "Round Timer cannot open URIs",
+        )))
+    }
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        playback_status(self.status.borrow().state)
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        metadata(&self.status.borrow())
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        let status = self.status.borrow();
+        secs_to_micros(status.interval_secs - status.round_remaining_secs)
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        self.status.borrow().state != TimerState::Stopped
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        self.status.borrow().state != TimerState::Stopped
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        false
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+fn playback_status(state: TimerState) -> &'static str {
+    match state {
+        TimerState::Stopped => "Stopped",
+        TimerState::Running => "Playing",
+        TimerState::Paused => "Paused",
+    }
+}
+
+/// The track title shown by media widgets, e.g. "Round 3/20 – 00:42".
+fn title(status: &Snapshot) -> String {
+    if status.lead_in_remaining_secs > 0 {
+        format!("Get Ready – {}", format_time(status.lead_in_remaining_secs))
+    } else {
+        format!(
+            "Round {}/{} – {}",
+            status.round,
+            status.rounds,
+            format_time(status.round_remaining_secs)
+        )
+    }
+}
+
+fn metadata(status: &Snapshot) -> HashMap<String, OwnedValue> {
+    let track_id = ObjectPath::try_from(format!("/org/roundtimer/round/{}", status.round))
+        .expect("round numbers form valid object paths");
+
+    let entries: [(&str, Value<'_>); 4] = [
+        ("mpris:trackid", Value::from(track_id)),
+        (
+            "mpris:length",
+            Value::from(secs_to_micros(status.interval_secs)),
+        ),
+        ("xesam:title", Value::from(title(status))),
+        (
+            "xesam:artist",
+            Value::from(vec![String::from("Round Timer")]),
+        ),
+    ];
+    entries
+        .into_iter()
+        .map(|(key, value)| {
+            let value = value
+                .try_to_owned()
+                .expect("metadata never contains file descriptors");
+            (key.to_string(), value)
+        })
+        .collect()
+}
+
+fn secs_to_micros(secs: u32) -> i64 {
+    i64::from(secs) * 1_000_000
+}
+
+/// Registers the timer as an MPRIS media player so media keys and desktop
+/// media widgets can control it.
+pub fn subscription(status: watch::Receiver<Snapshot>) -> Subscription<Message> {
+    Subscription::run_with_id(
+        "mpris",
+        iced::stream::channel(16, move |output| async move {
+            let result = async {
+                let player = Player {
+                    status: status.clone(),
+                    output,
+                };
+                let connection = connect(Builder::session()?, player).await?;
+                serve(&connection, status).await
+            }
+            .await;
+
+            if let Err(err) = result {
+                eprintln!("round-timer: MPRIS interface unavailable: {}", err);
+            }
+        }),
+    )
+}
+
+async fn connect(builder: Builder<'_>, player: Player) -> zbus::Result<Connection> {
+    builder
+        .serve_at(OBJECT_PATH, Root)?
+        .serve_at(OBJECT_PATH, player)?
+        .name(BUS_NAME)?
+        .build()
+        .await
+}
+
+async fn serve(connection: &Connection, mut status: watch::Receiver<Snapshot>) -> zbus::Result<()> {
+    let iface: InterfaceRef<Player> = connection.object_server().interface(OBJECT_PATH).await?;
+    let mut last_state = status.borrow().state;
+
+    while status.changed().await.is_ok() {
+        let state = status.borrow_and_update().state;
+        let ctxt = iface.signal_context();
+        let player = iface.get().await;
+
+        if state != last_state {
+            player.playback_status_changed(ctxt).await?;
+            player.can_go_next_changed(ctxt).await?;
+            player.can_go_previous_changed(ctxt).await?;
+            last_state = state;
+        }
+        // The title carries the countdown, so it changes every tick
+        player.metadata_changed(ctxt).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus::tests::{client, on_private_bus, running_snapshot};
+    use crate::engine::Engine;
+    use iced::futures::StreamExt;
+
+    #[zbus::proxy(
+        interface = "org.mpris.MediaPlayer2.Player",
+        default_service = "org.mpris.MediaPlayer2.roundtimer",
+        default_path = "/org/mpris/MediaPlayer2"
+    )]
+    trait MediaPlayer {
+        fn play(&self) -> zbus::Result<()>;
+        fn play_pause(&self) -> zbus::Result<()>;
+        fn next(&self) -> zbus::Result<()>;
+        fn previous(&self) -> zbus::Result<()>;
+
+        #[zbus(property)]
+        fn playback_status(&self) -> zbus::Result<String>;
+
+        #[zbus(property)]
+        fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+    }
+
+    #[test]
+    fn test_title_shows_round_and_remaining_time() {
+        assert_eq!(title(&running_snapshot()), "Round 3/20 – 00:42");
+    }
+
+    #[test]
+    fn test_title_during_lead_in() {
+        let mut engine = Engine::new(60, 20, 5);
+        engine.start();
+        assert_eq!(title(&engine.snapshot()), "Get Ready – 00:05");
+    }
+
+    #[test]
+    fn test_metadata() {
+        let metadata = metadata(&running_snapshot());
+
+        let title: String = metadata["xesam:title"]
+            .try_clone()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(title, "Round 3/20 – 00:42");
+        let length: i64 = metadata["mpris:length"]
+            .try_clone()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(length, 60_000_000);
+        let track_id: ObjectPath<'_> = metadata["mpris:trackid"]
+            .try_clone()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(track_id.as_str(), "/org/roundtimer/round/3");
+    }
+
+    #[test]
+    fn test_playback_status() {
+        assert_eq!(playback_status(TimerState::Running), "Playing");
+        assert_eq!(playback_status(TimerState::Paused), "Paused");
+        assert_eq!(playback_status(TimerState::Stopped), "Stopped");
+    }
+
+    #[test]
+    fn test_player_on_private_bus() {
+        on_private_bus(|address| async move {
+            let (status_sender, status) = watch::channel(Engine::new(60, 20, 0).snapshot());
+            let (output, mut messages) = mpsc::channel(4);
+
+            let player = Player {
+                status: status.clone(),
+                output,
+            };
+            let server = connect(Builder::address(address.as_str()).unwrap(), player)
+                .await
+                .unwrap();
+            tokio::spawn(async move { serve(&server, status).await });
+
+            let client = client(&address).await;
+            let proxy = MediaPlayerProxy::builder(&client)
+                .cache_properties(zbus::proxy::CacheProperties::No)
+                .build()
+                .await
+                .unwrap();
+
+            // Play starts a stopped timer; media keys map onto messages
+            proxy.play().await.unwrap();
+            assert!(matches!(messages.next().await, Some(Message::Start)));
+            proxy.play_pause().await.unwrap();
+            assert!(matches!(messages.next().await, Some(Message::Toggle)));
+            proxy.next().await.unwrap();
+            assert!(matches!(messages.next().await, Some(Message::Skip)));
+            proxy.previous().await.unwrap();
+            assert!(matches!(messages.next().await, Some(Message::Previous)));
+
+            status_sender.send_replace(running_snapshot());
+            assert_eq!(proxy.playback_status().await.unwrap(), "Playing");
+            let title: String = proxy.metadata().await.unwrap()["xesam:title"]
+                .try_clone()
+                .unwrap()
+                .try_into()
+                .unwrap();
+            assert_eq!(title, "Round 3/20 – 00:42");
+        });
+    }
+}
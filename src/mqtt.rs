@@ -0,0 +1,595 @@
+use iced::futures::channel::mpsc;
+use iced::futures::SinkExt;
+use iced::Subscription;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, watch};
+
+use crate::config::MqttConfig;
+use crate::engine::Snapshot;
+use crate::events::{self, TimerEvent};
+use crate::Message;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const SUBSCRIBE: u8 = 0x82;
+const PINGREQ: u8 = 0xc0;
+const DISCONNECT: u8 = 0xe0;
+
+/// Encodes an MQTT 3.1.1 packet: the fixed header byte, the variable-length
+/// remaining length, then the body.
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut remaining = body.len();
+    loop {
+        let mut byte = (remaining % 128) as u8;
+        remaining /= 128;
+        if remaining > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if remaining == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+/// Appends a length-prefixed string or binary field.
+fn push_field(body: &mut Vec<u8>, field: &[u8]) {
+    body.extend_from_slice(&(field.len() as u16).to_be_bytes());
+    body.extend_from_slice(field);
+}
+
+/// A CONNECT with a clean session and a retained last will, so subscribers
+/// learn when the timer goes away without saying goodbye.
+fn connect_packet(config: &MqttConfig, will_topic: &str, will_payload: &str) -> Vec<u8> {
+    let mut flags = 0x02 | 0x04 | 0x20;
+    if config.username.is_some() {
+        flags |= 0x80;
+    }
+    if config.password.is_some() {
+        flags |= 0x40;
+    }
+
+    let mut body = Vec::new();
+    push_field(&mut body, b"MQTT");
+    body.push(4);
+    body.push(flags);
+    body.extend_from_slice(&config.keep_alive_secs.to_be_bytes());
+    push_field(&mut body, config.client_id.as_bytes());
+    push_field(&mut body, will_topic.as_bytes());
+    push_field(&mut body, will_payload.as_bytes());
+    if let Some(username) = &config.username {
+        push_field(&mut body, username.as_bytes());
+    }
+    if let Some(password) = &config.password {
+        push_field(&mut body, password.as_bytes());
+    }
+    packet(CONNECT, &body)
+}
+
+/// A QoS 0 PUBLISH.
+fn publish_packet(topic: &str, payload: &str, retain: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_field(&mut body, topic.as_bytes());
+    body.extend_from_slice(payload.as_bytes());
+    packet(PUBLISH | u8::from(retain), &body)
+}
+
+fn subscribe_packet(packet_id: u16, topic: &str) -> Vec<u8> {
+    let mut body = packet_id.to_be_bytes().to_vec();
+    push_field(&mut body, topic.as_bytes());
+    // Maximum QoS 0, so the broker never expects acknowledgements
+    body.push(0);
+    packet(SUBSCRIBE, &body)
+}
+
+async fn read_packet<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<(u8, Vec<u8>)> {
+    let header = reader.read_u8().await?;
+    let mut length = 0usize;
+    for shift in (0..28).step_by(7) {
+        let byte = reader.read_u8().await?;
+        length |= usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await?;
+            return Ok((header, body));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "malformed remaining length",
+    ))
+}
+
+/// Splits an incoming PUBLISH body into its topic and payload.
+fn parse_publish(header: u8, body: &[u8]) -> Option<(String, String)> {
+    let topic_len = usize::from(u16::from_be_bytes([*body.first()?, *body.get(1)?]));
+    let topic = std::str::from_utf8(body.get(2..2 + topic_len)?).ok()?;
+    let qos = (header >> 1) & 0x03;
+    let payload_start = 2 + topic_len + if qos > 0 { 2 } else { 0 };
+    let payload = std::str::from_utf8(body.get(payload_start..)?).ok()?;
+    Some((topic.to_string(), payload.to_string()))
+}
+
+struct Topics {
+    state: String,
+    round: String,
+    remaining: String,
+    event: String,
+    command: String,
+    availability: String,
+}
+
+impl Topics {
+    fn new(prefix: &str) -> Self {
+        let topic = |name: &str| format!("{}/{}", prefix.trim_end_matches('/'), name);
+        Self {
+            state: topic("state"),
+            round: topic("round"),
+            remaining: topic("remaining"),
+            event: topic("event"),
+            command: topic("command"),
+            availability: topic("availability"),
+        }
+    }
+
+    /// Retained messages for whatever changed since `previous`, or for
+    /// everything when there is no previous snapshot.
+    fn state_messages(
+        &self,
+        previous: Option<&Snapshot>,
+        current: &Snapshot,
+    ) -> Vec<(&str, String)> {
+        let mut messages = Vec::new();
+        if previous.map(|previous| previous.state) != Some(current.state) {
+            messages.push((self.state.as_str(), current.state.as_str().to_string()));
+        }
+        if previous.map(|previous| previous.round) != Some(current.round) {
+            messages.push((self.round.as_str(), current.round.to_string()));
+        }
+        let remaining = |snapshot: &Snapshot| {
+            snapshot
+                .lead_in_remaining_secs
+                .max(snapshot.round_remaining_secs)
+        };
+        if previous.map(remaining) != Some(remaining(current)) {
+            messages.push((self.remaining.as_str(), remaining(current).to_string()));
+        }
+        messages
+    }
+}
+
+/// Publishes the timer's state and events to an MQTT broker and turns
+/// messages on the command topic into [`Message`]s, reconnecting as needed.
+pub fn subscription(
+    config: MqttConfig,
+    status: watch::Receiver<Snapshot>,
+    events: broadcast::Receiver<TimerEvent>,
+) -> Subscription<Message> {
+    Subscription::run_with_id(
+        "mqtt",
+        iced::stream::channel(16, move |output| async move {
+            let mut status = status;
+            let mut events = events;
+            let mut warned = false;
+
+            loop {
+                let result = session(&config, &mut status, &mut events, output.clone(), || {
+                    warned = false;
+                })
+                .await;
+                if let Err(err) = result {
+                    // Report a broker that is down once, not every few seconds
+                    if !warned {
+                        eprintln!(
+                            "round-timer: MQTT broker {} unavailable: {}",
+                            config.broker, err
+                        );
+                        warned = true;
+                    }
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }),
+    )
+}
+
+/// Runs one connection to the broker until it fails.
+async fn session(
+    config: &MqttConfig,
+    status: &mut watch::Receiver<Snapshot>,
+    events: &mut broadcast::Receiver<TimerEvent>,
+    output: mpsc::Sender<Message>,
+    on_connect: impl FnOnce(),
+) -> io::Result<()> {
+    let topics = Topics::new(&config.topic_prefix);
+    let stream = TcpStream::connect(&config.broker).await?;
+    let (mut reader, mut writer) = stream.into_split();
+
+    writer
+        .write_all(&connect_packet(config, &topics.availability, "offline"))
+        .await?;
+    match read_packet(&mut reader).await? {
+        (CONNACK, body) if body.get(1) == Some(&0) => {}
+        (CONNACK, body) => {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("connection refused with code {}", body.get(1).unwrap_or(&0)),
+            ))
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "broker did not acknowledge the connection",
+            ))
+        }
+    }
+    on_connect();
+
+    writer
+        .write_all(&subscribe_packet(1, &topics.command))
+        .await?;
+    writer
+        .write_all(&publish_packet(&topics.availability, "online", true))
+        .await?;
+
+    // Reading is not cancel-safe, so it gets its own task
+    let (commands_sender, mut commands) = tokio::sync::mpsc::channel(16);
+    let command_topic = topics.command.clone();
+    let reader_task = tokio::spawn(async move {
+        while let Ok((header, body)) = read_packet(&mut reader).await {
+            if header & 0xf0 != PUBLISH {
+                continue;
+            }
+            if let Some((topic, payload)) = parse_publish(header, &body) {
+                if topic == command_topic && commands_sender.send(payload).await.is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let result = async {
+        let mut last = status.borrow_and_update().clone();
+        for (topic, payload) in topics.state_messages(None, &last) {
+            writer
+                .write_all(&publish_packet(topic, &payload, true))
+                .await?;
+        }
+
+        let keep_alive = Duration::from_secs(u64::from(config.keep_alive_secs.max(1)));
+        let mut ping =
+            tokio::time::interval_at(tokio::time::Instant::now() + keep_alive, keep_alive);
+        let mut output = output;
+
+        loop {
+            tokio::select! {
+                changed = status.changed() => {
+                    if changed.is_err() {
+                        return disconnect(&mut writer, &topics).await;
+                    }
+                    let current = status.borrow_and_update().clone();
+                    for (topic, payload) in topics.state_messages(Some(&last), &current) {
+                        writer.write_all(&publish_packet(topic, &payload, true)).await?;
+                    }
+                    last = current;
+                }
+                event = events.recv() => match event {
+                    Ok(event) => {
+                        let json = events::to_json(&event, events::timestamp_ms());
+                        writer.write_all(&publish_packet(&topics.event, &json, false)).await?;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => {
+                        return disconnect(&mut writer, &topics).await;
+                    }
+                },
+                command = commands.recv() => {
+                    let Some(command) = command else {
+                        return Err(io::Error::new(
+                            io::ErrorKind::ConnectionAborted,
+                            "broker closed the connection",
+                        ));
+                    };
+                    if let Some(message) = Message::from_command(command.trim()) {
+                        if output.send(message).await.is_err() {
+                            return disconnect(&mut writer, &topics).await;
+                        }
+                    }
+                }
+                _ = ping.tick() => {
+                    writer.write_all(&packet(PINGREQ, &[])).await?;
+                }
+            }
+        }
+    }
+    .await;
+
+    reader_task.abort();
+    result
+}
+
+/// Leaves the broker when the timer shuts down. A clean disconnect discards
+/// the last will, so the timer announces that it is offline itself first.
+async fn disconnect(writer: &mut OwnedWriteHalf, topics: &Topics) -> io::Result<()> {
+    writer
+        .write_all(&publish_packet(&topics.availability, "offline", true))
+        .await?;
+    writer.write_all(&packet(DISCONNECT, &[])).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use iced::futures::StreamExt;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_remaining_length_encoding() {
+        assert_eq!(packet(PINGREQ, &[]), vec![0xc0, 0x00]);
+        let long = packet(PUBLISH, &[0; 321]);
+        assert_eq!(&long[..3], &[0x30, 0xc1, 0x02]);
+        assert_eq!(long.len(), 3 + 321);
+    }
+
+    #[test]
+    fn test_publish_packet() {
+        assert_eq!(
+            publish_packet("a/b", "hi", true),
+            vec![0x31, 7, 0, 3, b'a', b'/', b'b', b'h', b'i']
+        );
+    }
+
+    #[test]
+    fn test_parse_publish() {
+        let body = [0, 3, b'a', b'/', b'b', b'g', b'o'];
+        assert_eq!(
+            parse_publish(0x30, &body),
+            Some((String::from("a/b"), String::from("go")))
+        );
+        // QoS 1 messages carry a packet id before the payload
+        let body = [0, 1, b't', 0, 7, b'g', b'o'];
+        assert_eq!(
+            parse_publish(0x32, &body),
+            Some((String::from("t"), String::from("go")))
+        );
+        assert_eq!(parse_publish(0x30, &[0, 9, b'a']), None);
+    }
+
+    #[test]
+    fn test_connect_packet_flags() {
+        let config = MqttConfig {
+            username: Some(String::from("gym")),
+            ..MqttConfig::default()
+        };
+        let connect = connect_packet(&config, "round-timer/availability", "offline");
+        assert_eq!(connect[0], CONNECT);
+        // Username, will retain, will and clean session
+        assert_eq!(connect[9], 0x80 | 0x20 | 0x04 | 0x02);
+    }
+
+    #[test]
+    fn test_state_messages_only_include_changes() {
+        let topics = Topics::new("gym/timer/");
+        let mut engine = Engine::new(60, 20, 0);
+        let stopped = engine.snapshot();
+
+        let all = topics.state_messages(None, &stopped);
+        assert_eq!(
+            all,
+            vec![
+                ("gym/timer/state", String::from("stopped")),
+                ("gym/timer/round", String::from("1")),
+                ("gym/timer/remaining", String::from("60")),
+            ]
+        );
+
+        engine.start();
+        engine.tick();
+        let running = engine.snapshot();
+        assert_eq!(
+            topics.state_messages(Some(&stopped), &running),
+            vec![
+                ("gym/timer/state", String::from("running")),
+                ("gym/timer/remaining", String::from("59")),
+            ]
+        );
+    }
+
+    /// Plays the broker's side of a session: acknowledges the connection,
+    /// collects publishes until the timer disconnects and sends one command.
+    /// `drained` fires once the event after the command is in.
+    async fn stub_broker(
+        listener: TcpListener,
+        drained: tokio::sync::oneshot::Sender<()>,
+    ) -> Vec<(String, String, bool)> {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let (header, body) = read_packet(&mut stream).await.unwrap();
+        assert_eq!(header, CONNECT);
+        assert_eq!(&body[2..6], b"MQTT");
+        stream.write_all(&[CONNACK, 2, 0, 0]).await.unwrap();
+
+        let mut published = Vec::new();
+        let mut drained = Some(drained);
+        loop {
+            let (header, body) = read_packet(&mut stream).await.unwrap();
+            match header & 0xf0 {
+                0x80 => {
+                    stream.write_all(&[0x90, 3, 0, 1, 0]).await.unwrap();
+                }
+                PUBLISH => {
+                    let (topic, payload) = parse_publish(header, &body).unwrap();
+                    published.push((topic, payload, header & 0x01 == 1));
+                    // Once the current state is out, drive the timer
+                    if published.len() == 4 {
+                        stream
+                            .write_all(&publish_packet("round-timer/command", "skip", false))
+                            .await
+                            .unwrap();
+                    }
+                    if published.len() == 5 {
+                        let _ = drained.take().unwrap().send(());
+                    }
+                }
+                DISCONNECT => return published,
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_session_with_stub_broker() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let config = MqttConfig {
+                broker: listener.local_addr().unwrap().to_string(),
+                ..MqttConfig::default()
+            };
+            let (drained_sender, drained) = tokio::sync::oneshot::channel();
+            let broker = tokio::spawn(stub_broker(listener, drained_sender));
+
+            let (status_sender, mut status) = watch::channel(Engine::new(60, 20, 0).snapshot());
+            let (event_sender, mut events) = broadcast::channel(16);
+            let (output, mut messages) = mpsc::channel(4);
+            tokio::spawn(
+                async move { session(&config, &mut status, &mut events, output, || {}).await },
+            );
+
+            assert!(matches!(messages.next().await, Some(Message::Skip)));
+            event_sender
+                .send(TimerEvent::RoundStarted {
+                    round: 2,
+                    label: None,
+                })
+                .unwrap();
+            drained.await.unwrap();
+
+            // Shutting the timer down ends the session
+            drop(status_sender);
+            let published = broker.await.unwrap();
+            assert_eq!(
+                published[0],
+                (
+                    String::from("round-timer/availability"),
+                    String::from("online"),
+                    true
+                )
+            );
+            assert_eq!(
+                published[1],
+                (
+                    String::from("round-timer/state"),
+                    String::from("stopped"),
+                    true
+                )
+            );
+            let (topic, payload, retained) = &published[4];
+            assert_eq!(topic, "round-timer/event");
+            assert!(payload.contains(r#""event":"round_started""#));
+            assert!(!retained);
+            assert_eq!(
+                published.last().unwrap(),
+                &(
+                    String::from("round-timer/availability"),
+                    String::from("offline"),
+                    true
+                )
+            );
+        });
+    }
+
+    #[test]
+    fn test_session_with_mosquitto() {
+        let port = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        let Ok(mut broker) = std::process::Command::new("mosquitto")
+            .args(["-p", &port.to_string()])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+        else {
+            // CI installs mosquitto, so the test must not pass without it there
+            assert!(
+                std::env::var_os("CI").is_none(),
+                "mosquitto is not installed"
+            );
+            eprintln!("mosquitto not available, skipping");
+            return;
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let result = runtime.block_on(async {
+            let address = format!("127.0.0.1:{}", port);
+            // Wait for the broker to start listening
+            let mut observer = loop {
+                if let Ok(stream) = TcpStream::connect(&address).await {
+                    break stream;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            };
+
+            let config = MqttConfig {
+                broker: address,
+                client_id: String::from("round-timer-test"),
+                ..MqttConfig::default()
+            };
+            let (_status_sender, mut status) = watch::channel(Engine::new(60, 20, 0).snapshot());
+            let (_event_sender, mut events) = broadcast::channel(16);
+            let (output, mut messages) = mpsc::channel(4);
+            tokio::spawn(
+                async move { session(&config, &mut status, &mut events, output, || {}).await },
+            );
+
+            // A second client sees the retained state and sends a command
+            let observer_config = MqttConfig {
+                client_id: String::from("observer"),
+                ..MqttConfig::default()
+            };
+            observer
+                .write_all(&connect_packet(&observer_config, "observer/gone", ""))
+                .await
+                .unwrap();
+            read_packet(&mut observer).await.unwrap();
+            observer
+                .write_all(&subscribe_packet(1, "round-timer/state"))
+                .await
+                .unwrap();
+
+            let state = loop {
+                let (header, body) = read_packet(&mut observer).await.unwrap();
+                if header & 0xf0 == PUBLISH {
+                    break parse_publish(header, &body).unwrap();
+                }
+            };
+            observer
+                .write_all(&publish_packet("round-timer/command", "start", false))
+                .await
+                .unwrap();
+            let message = messages.next().await;
+            (state, message)
+        });
+
+        let _ = broker.kill();
+        let _ = broker.wait();
+        let (state, message) = result;
+        assert_eq!(
+            state,
+            (String::from("round-timer/state"), String::from("stopped"))
+        );
+        assert!(matches!(message, Some(Message::Start)));
+    }
+}
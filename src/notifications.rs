@@ -0,0 +1,482 @@
+use iced::futures::channel::mpsc;
+use iced::futures::{SinkExt, StreamExt};
+use iced::Subscription;
+use std::collections::HashMap;
+use tokio::sync::{broadcast, watch};
+use zbus::zvariant::Value;
+use zbus::Connection;
+
+use crate::config::NotificationsConfig;
+use crate::engine::{format_time, Snapshot};
+use crate::events::{EventKind, TimerEvent};
+use crate::Message;
+
+const APP_NAME: &str = "Round Timer";
+
+#[zbus::proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+}
+
+#[derive(Debug, PartialEq)]
+struct Notification {
+    summary: String,
+    body: String,
+    /// Pairs of action key and button label.
+    actions: &'static [(&'static str, &'static str)],
+}
+
+const RUNNING_ACTIONS: &[(&str, &str)] = &[("pause", "Pause"), ("skip", "Skip")];
+const PAUSED_ACTIONS: &[(&str, &str)] = &[("resume", "Resume"), ("skip", "Skip")];
+
+/// Builds the notification for `event`, if `events` asks for one.
+fn notification_for(
+    event: &TimerEvent,
+    events: &[EventKind],
+    status: &Snapshot,
+) -> Option<Notification> {
+    if !events.contains(&event.kind()?) {
+        return None;
+    }
+
+    let rounds = status.rounds;
+    let interval = format_time(status.interval_secs);
+    let (summary, body, actions) = match *event {
+        TimerEvent::SessionStarted {
+            rounds,
+            interval_secs,
+        } => (
+            String::from("Session started"),
+            format!("{} rounds of {}", rounds, format_time(interval_secs)),
+            RUNNING_ACTIONS,
+        ),
+        TimerEvent::RoundStarted { round, ref label } => {
+            let left = rounds.saturating_sub(round - 1) * status.interval_secs;
+            let summary = match label {
+                Some(label) => format!("Round {} of {}: {}", round, rounds, label),
+                None => format!("Round {} of {}", round, rounds),
+            };
+            (
+                summary,
+                format!(
+                    "{} round, {} left in the session",
+                    interval,
+                    format_time(left)
+                ),
+                RUNNING_ACTIONS,
+            )
+        }
+        TimerEvent::Chime { round } => (
+            format!("Round {} of {} finished", round, rounds),
+            if round < rounds {
+                format!("Round {} starts now", round + 1)
+            } else {
+                String::from("That was the last round")
+            },
+            RUNNING_ACTIONS,
+        ),
+        TimerEvent::Warning {
+            round,
+            remaining_secs,
+        } => (
+            format!("Round {} of {} ending", round, rounds),
+            match &status.next_label {
+                Some(next) => format!(
+                    "{} left in the round, then {}",
+                    format_time(remaining_secs),
+                    next
+                ),
+                None => format!("{} left in the round", format_time(remaining_secs)),
+            },
+            RUNNING_ACTIONS,
+        ),
+        TimerEvent::Paused { round } => (
+            String::from("Paused"),
+            format!("Round {} of {}", round, rounds),
+            PAUSED_ACTIONS,
+        ),
+        TimerEvent::Resumed { round } => (
+            String::from("Resumed"),
+            format!("Round {} of {}", round, rounds),
+            RUNNING_ACTIONS,
+        ),
+        TimerEvent::SessionStopped { round } => (
+            String::from("Session stopped"),
+            format!("Stopped in round {} of {}", round, rounds),
+            &[][..],
+        ),
+        TimerEvent::SessionFinished { rounds } => (
+            String::from("Session finished"),
+            format!("{} rounds of {} completed", rounds, interval),
+            &[][..],
+        ),
+    };
+
+    Some(Notification {
+        summary,
+        body,
+        actions,
+    })
+}
+
+fn message_for_action(action: &str) -> Option<Message> {
+    match action {
+        "pause" => Some(Message::Pause),
+        "resume" => Some(Message::Resume),
+        "skip" => Some(Message::Skip),
+        _ => None,
+    }
+}
+
+/// Shows desktop notifications for the configured events, replacing the
+/// previous one, and turns clicks on their buttons into [`Message`]s.
+pub fn subscription(
+    config: NotificationsConfig,
+    status: watch::Receiver<Snapshot>,
+    events: broadcast::Receiver<TimerEvent>,
+) -> Subscription<Message> {
+    Subscription::run_with_id(
+        "notifications",
+        iced::stream::channel(16, move |output| async move {
+            let result = async {
+                let connection = Connection::session().await?;
+                let proxy = NotificationsProxy::new(&connection).await?;
+                serve(&proxy, &config.events, status, events, output).await
+            }
+            .await;
+
+            if let Err(err) = result {
+                eprintln!("round-timer: desktop notifications unavailable: {}", err);
+            }
+        }),
+    )
+}
+
+async fn serve(
+    proxy: &NotificationsProxy<'_>,
+    kinds: &[EventKind],
+    status: watch::Receiver<Snapshot>,
+    mut events: broadcast::Receiver<TimerEvent>,
+    mut output: mpsc::Sender<Message>,
+) -> zbus::Result<()> {
+    let mut actions = proxy.receive_action_invoked().await?;
+    // The server assigns ids; passing the last one back replaces that notification
+    let mut current_id = 0;
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let notification = notification_for(&event, kinds, &status.borrow());
+                    if let Some(notification) = notification {
+                        // A daemon that is restarting will be back for the next one
+                        match show(proxy, current_id, &notification).await {
+                            Ok(id) => current_id = id,
+                            Err(err) => eprintln!("round-timer: cannot show a notification: {}", err),
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            signal = actions.next() => {
+                let Some(signal) = signal else {
+                    return Ok(());
+                };
+                let Ok(args) = signal.args() else {
+                    continue;
+                };
+                if args.id != current_id {
+                    continue;
+                }
+                if let Some(message) = message_for_action(&args.action_key) {
+                    if output.send(message).await.is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn show(
+    proxy: &NotificationsProxy<'_>,
+    replaces_id: u32,
+    notification: &Notification,
+) -> zbus::Result<u32> {
+    let actions: Vec<&str> = notification
+        .actions
+        .iter()
+        .flat_map(|&(key, label)| [key, label])
+        .collect();
+    let hints = HashMap::from([("desktop-entry", Value::from("round-timer"))]);
+
+    proxy
+        .notify(
+            APP_NAME,
+            replaces_id,
+            "",
+            &notification.summary,
+            &notification.body,
+            &actions,
+            hints,
+            -1,
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus::tests::{client, fake_service, on_private_bus};
+    use crate::engine::Engine;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use zbus::object_server::SignalContext;
+    use zbus::zvariant::OwnedValue;
+
+    fn status() -> Snapshot {
+        Engine::new(60, 20, 0).snapshot()
+    }
+
+    fn all_events() -> Vec<EventKind> {
+        EventKind::ALL.to_vec()
+    }
+
+    #[test]
+    fn test_round_start_notification() {
+        let notification = notification_for(
+            &TimerEvent::RoundStarted {
+                round: 3,
+                label: None,
+            },
+            &all_events(),
+            &status(),
+        )
+        .unwrap();
+
+        assert_eq!(notification.summary, "Round 3 of 20");
+        assert_eq!(notification.body, "01:00 round, 18:00 left in the session");
+        assert_eq!(notification.actions, RUNNING_ACTIONS);
+    }
+
+    #[test]
+    fn test_notifications_name_the_exercise() {
+        let notification = notification_for(
+            &TimerEvent::RoundStarted {
+                round: 3,
+                label: Some(String::from("Push-ups")),
+            },
+            &all_events(),
+            &status(),
+        )
+        .unwrap();
+        assert_eq!(notification.summary, "Round 3 of 20: Push-ups");
+
+        let status = Snapshot {
+            next_label: Some(String::from("Squats")),
+            ..status()
+        };
+        let notification = notification_for(
+            &TimerEvent::Warning {
+                round: 3,
+                remaining_secs: 10,
+            },
+            &all_events(),
+            &status,
+        )
+        .unwrap();
+        assert_eq!(notification.body, "00:10 left in the round, then Squats");
+    }
+
+    #[test]
+    fn test_paused_notification_offers_resume() {
+        let notification =
+            notification_for(&TimerEvent::Paused { round: 2 }, &all_events(), &status()).unwrap();
+        assert_eq!(notification.actions, PAUSED_ACTIONS);
+    }
+
+    #[test]
+    fn test_finished_notification_has_no_actions() {
+        let notification = notification_for(
+            &TimerEvent::SessionFinished { rounds: 20 },
+            &all_events(),
+            &status(),
+        )
+        .unwrap();
+
+        assert_eq!(notification.summary, "Session finished");
+        assert_eq!(notification.body, "20 rounds of 01:00 completed");
+        assert!(notification.actions.is_empty());
+    }
+
+    #[test]
+    fn test_unconfigured_events_are_ignored() {
+        let events = NotificationsConfig::default().events;
+        assert!(notification_for(&TimerEvent::Paused { round: 2 }, &events, &status()).is_none());
+        assert!(
+            notification_for(&TimerEvent::Chime { round: 0 }, &all_events(), &status()).is_none()
+        );
+    }
+
+    #[test]
+    fn test_message_for_action() {
+        assert!(matches!(message_for_action("pause"), Some(Message::Pause)));
+        assert!(matches!(message_for_action("skip"), Some(Message::Skip)));
+        assert!(message_for_action("default").is_none());
+    }
+
+    struct FakeServer {
+        shown: mpsc::UnboundedSender<(u32, String, Vec<String>)>,
+        /// Calls to refuse before showing anything.
+        failures: AtomicU32,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.Notifications")]
+    impl FakeServer {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            _app_name: String,
+            replaces_id: u32,
+            _app_icon: String,
+            summary: String,
+            _body: String,
+            actions: Vec<String>,
+            _hints: HashMap<String, OwnedValue>,
+            _expire_timeout: i32,
+        ) -> zbus::fdo::Result<u32> {
+            let _ = self.shown.unbounded_send((replaces_id, summary, actions));
+            let failing = self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if failing {
+                Err(zbus::fdo::Error::Failed(String::from("daemon restarting")))
+            } else if replaces_id == 0 {
+                Ok(7)
+            } else {
+                Ok(replaces_id)
+            }
+        }
+
+        #[zbus(signal)]
+        async fn action_invoked(
+            ctxt: &SignalContext<'_>,
+            id: u32,
+            action_key: &str,
+        ) -> zbus::Result<()>;
+    }
+
+    #[test]
+    fn test_notifications_on_private_bus() {
+        on_private_bus(|address| async move {
+            let (shown_sender, mut shown) = mpsc::unbounded();
+            let server = fake_service(
+                &address,
+                "org.freedesktop.Notifications",
+                "/org/freedesktop/Notifications",
+                FakeServer {
+                    shown: shown_sender,
+                    failures: AtomicU32::new(0),
+                },
+            )
+            .await;
+
+            let client = client(&address).await;
+            let (_status_sender, status) = watch::channel(status());
+            let (event_sender, events) = broadcast::channel(16);
+            let (output, mut messages) = mpsc::channel(4);
+            tokio::spawn(async move {
+                let proxy = NotificationsProxy::new(&client).await.unwrap();
+                serve(&proxy, &all_events(), status, events, output).await
+            });
+
+            event_sender
+                .send(TimerEvent::RoundStarted {
+                    round: 1,
+                    label: None,
+                })
+                .unwrap();
+            let (replaces_id, summary, actions) = shown.next().await.unwrap();
+            assert_eq!(replaces_id, 0);
+            assert_eq!(summary, "Round 1 of 20");
+            assert_eq!(actions, ["pause", "Pause", "skip", "Skip"]);
+
+            // Later notifications replace the first one
+            event_sender
+                .send(TimerEvent::RoundStarted {
+                    round: 2,
+                    label: None,
+                })
+                .unwrap();
+            let (replaces_id, _, _) = shown.next().await.unwrap();
+            assert_eq!(replaces_id, 7);
+
+            // Clicking a button sends the matching message
+            let iface = server
+                .object_server()
+                .interface::<_, FakeServer>("/org/freedesktop/Notifications")
+                .await
+                .unwrap();
+            FakeServer::action_invoked(iface.signal_context(), 7, "skip")
+                .await
+                .unwrap();
+            assert!(matches!(messages.next().await, Some(Message::Skip)));
+        });
+    }
+
+    #[test]
+    fn test_notifications_carry_on_after_a_failure() {
+        on_private_bus(|address| async move {
+            let (shown_sender, mut shown) = mpsc::unbounded();
+            let _server = fake_service(
+                &address,
+                "org.freedesktop.Notifications",
+                "/org/freedesktop/Notifications",
+                FakeServer {
+                    shown: shown_sender,
+                    failures: AtomicU32::new(1),
+                },
+            )
+            .await;
+
+            let client = client(&address).await;
+            let (_status_sender, status) = watch::channel(status());
+            let (event_sender, events) = broadcast::channel(16);
+            let (output, _messages) = mpsc::channel(4);
+            tokio::spawn(async move {
+                let proxy = NotificationsProxy::new(&client).await.unwrap();
+                serve(&proxy, &all_events(), status, events, output).await
+            });
+
+            for round in 1..=2 {
+                event_sender
+                    .send(TimerEvent::RoundStarted { round, label: None })
+                    .unwrap();
+            }
+            let (_, summary, _) = shown.next().await.unwrap();
+            assert_eq!(summary, "Round 1 of 20");
+            let (replaces_id, summary, _) = shown.next().await.unwrap();
+            assert_eq!(replaces_id, 0);
+            assert_eq!(summary, "Round 2 of 20");
+        });
+    }
+}
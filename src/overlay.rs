@@ -0,0 +1,350 @@
+use iced::Subscription;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::sync::watch;
+
+use crate::circular_progress;
+use crate::circular_progress::CircularProgress;
+use crate::config::{OverlayConfig, RingConfig};
+use crate::engine::{format_time, Snapshot, TimerState};
+use crate::theme::Palette;
+use crate::Message;
+
+/// How many numbered frames to keep; older ones are removed as new ones
+/// are written, so a long stream cannot fill the disk.
+const KEPT_FRAMES: u64 = 600;
+
+fn default_directory() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("round-timer").join("overlay"))
+}
+
+fn round_text(status: &Snapshot) -> String {
+    if status.lead_in_remaining_secs > 0 {
+        String::from("Get Ready")
+    } else {
+        format!("Round {} / {}", status.round, status.rounds)
+    }
+}
+
+fn remaining_text(status: &Snapshot) -> String {
+    if status.lead_in_remaining_secs > 0 {
+        format_time(status.lead_in_remaining_secs)
+    } else {
+        format_time(status.round_remaining_secs)
+    }
+}
+
+fn status_text(status: &Snapshot) -> &'static str {
+    match status.state {
+        TimerState::Stopped => "Stopped",
+        TimerState::Running if status.lead_in_remaining_secs > 0 => "Get Ready",
+        TimerState::Running => "Running",
+        TimerState::Paused => "Paused",
+    }
+}
+
+/// The number of a frame file such as `ring-000042.png`.
+fn frame_number(name: &str) -> Option<u64> {
+    name.strip_prefix("ring-")?
+        .strip_suffix(".png")?
+        .parse()
+        .ok()
+}
+
+fn remove_if_present(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Replaces `path` in one step, so readers never see a half-written file.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    std::fs::write(&temporary, contents)?;
+    std::fs::rename(&temporary, path)
+}
+
+struct Writer {
+    config: OverlayConfig,
+    ring: RingConfig,
+    palette: Palette,
+    directory: PathBuf,
+    /// The last frame written, once the directory has been looked at.
+    frame: Option<u64>,
+    kept_frames: u64,
+    warned: bool,
+}
+
+impl Writer {
+    fn new(config: OverlayConfig, ring: RingConfig, palette: Palette, directory: PathBuf) -> Self {
+        Self {
+            config,
+            ring,
+            palette,
+            directory,
+            frame: None,
+            kept_frames: KEPT_FRAMES,
+            warned: false,
+        }
+    }
+
+    fn write(&mut self, status: &Snapshot) {
+        if let Err(err) = self.try_write(status) {
+            // Report a missing or full disk once, not on every tick
+            if !self.warned {
+                eprintln!(
+                    "round-timer: cannot write overlay to {}: {}",
+                    self.directory.display(),
+                    err
+                );
+                self.warned = true;
+            }
+        }
+    }
+
+    fn try_write(&mut self, status: &Snapshot) -> io::Result<()> {
+        std::fs::create_dir_all(&self.directory)?;
+
+        if self.config.text {
+            let files = [
+                ("round.txt", round_text(status)),
+                ("remaining.txt", remaining_text(status)),
+                ("status.txt", status_text(status).to_string()),
+            ];
+            for (name, contents) in files {
+                write_atomically(&self.directory.join(name), contents.as_bytes())?;
+            }
+        }
+
+        if self.config.png {
+            let ring = CircularProgress::for_status(status, &self.ring, &self.palette);
+            let png = circular_progress::render(&ring, self.config.png_size)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid png_size"))?
+                .encode_png()
+                .map_err(io::Error::other)?;
+
+            if self.config.png_sequence {
+                let frame = match self.frame {
+                    Some(frame) => frame,
+                    None => self.resume_frames()?,
+                } + 1;
+                std::fs::write(self.frame_path(frame), png)?;
+                self.frame = Some(frame);
+                if let Some(old) = frame.checked_sub(self.kept_frames) {
+                    remove_if_present(&self.frame_path(old))?;
+                }
+            } else {
+                write_atomically(&self.directory.join("ring.png"), &png)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn frame_path(&self, frame: u64) -> PathBuf {
+        self.directory.join(format!("ring-{:06}.png", frame))
+    }
+
+    /// Returns the last frame an earlier run wrote, so numbering carries on
+    /// after it instead of overwriting, and removes all but the newest frames.
+    fn resume_frames(&self) -> io::Result<u64> {
+        let mut frames = Vec::new();
+        for entry in std::fs::read_dir(&self.directory)? {
+            if let Some(frame) = entry?.file_name().to_str().and_then(frame_number) {
+                frames.push(frame);
+            }
+        }
+
+        let last = frames.iter().copied().max().unwrap_or(0);
+        for frame in frames {
+            if frame + self.kept_frames <= last {
+                remove_if_present(&self.frame_path(frame))?;
+            }
+        }
+        Ok(last)
+    }
+}
+
+/// Writes the overlay files on every change to the timer's state.
+pub fn subscription(
+    config: OverlayConfig,
+    ring: RingConfig,
+    palette: Palette,
+    status: watch::Receiver<Snapshot>,
+) -> Subscription<Message> {
+    Subscription::run_with_id(
+        "overlay",
+        iced::stream::channel(1, move |_output| async move {
+            let mut status = status;
+            match config.directory.clone().or_else(default_directory) {
+                Some(directory) => {
+                    let mut writer = Writer::new(config, ring, palette, directory);
+                    loop {
+                        let snapshot = status.borrow_and_update().clone();
+                        // Rendering and writing the files blocks, so keep it off the runtime
+                        writer = tokio::task::spawn_blocking(move || {
+                            writer.write(&snapshot);
+                            writer
+                        })
+                        .await
+                        .expect("writing the overlay does not panic");
+
+                        if status.changed().await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                None => eprintln!("round-timer: no directory for the overlay files"),
+            }
+            std::future::pending::<()>().await;
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+
+    fn temporary_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "round-timer-overlay-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    fn test_texts_while_running() {
+        let mut engine = Engine::new(60, 20, 0);
+        engine.start();
+        engine.skip();
+        for _ in 0..15 {
+            engine.tick();
+        }
+        let status = engine.snapshot();
+
+        assert_eq!(round_text(&status), "Round 2 / 20");
+        assert_eq!(remaining_text(&status), "00:45");
+        assert_eq!(status_text(&status), "Running");
+    }
+
+    #[test]
+    fn test_texts_during_lead_in() {
+        let mut engine = Engine::new(60, 20, 10);
+        engine.start();
+        engine.tick();
+        let status = engine.snapshot();
+
+        assert_eq!(round_text(&status), "Get Ready");
+        assert_eq!(remaining_text(&status), "00:09");
+        assert_eq!(status_text(&status), "Get Ready");
+    }
+
+    #[test]
+    fn test_writer_writes_text_and_png() {
+        let directory = temporary_directory("files");
+        let mut writer = Writer::new(
+            OverlayConfig::default(),
+            RingConfig::default(),
+            Palette::dark(),
+            directory.clone(),
+        );
+        let mut engine = Engine::new(90, 3, 0);
+        engine.start();
+
+        writer.write(&engine.snapshot());
+
+        let read = |name: &str| std::fs::read_to_string(directory.join(name)).unwrap();
+        assert_eq!(read("round.txt"), "Round 1 / 3");
+        assert_eq!(read("remaining.txt"), "01:30");
+        assert_eq!(read("status.txt"), "Running");
+        let png = std::fs::read(directory.join("ring.png")).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert!(!directory.join("ring.png.tmp").exists());
+        assert!(!writer.warned);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_writer_numbers_png_frames() {
+        let directory = temporary_directory("sequence");
+        let config = OverlayConfig {
+            text: false,
+            png_size: 32,
+            png_sequence: true,
+            ..OverlayConfig::default()
+        };
+        let mut writer = Writer::new(
+            config,
+            RingConfig::default(),
+            Palette::dark(),
+            directory.clone(),
+        );
+        let mut engine = Engine::new(60, 2, 0);
+        engine.start();
+
+        writer.write(&engine.snapshot());
+        engine.tick();
+        writer.write(&engine.snapshot());
+
+        assert!(directory.join("ring-000001.png").exists());
+        assert!(directory.join("ring-000002.png").exists());
+        assert!(!directory.join("ring.png").exists());
+        assert!(!directory.join("round.txt").exists());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_png_frames_carry_on_and_are_pruned() {
+        let directory = temporary_directory("pruned");
+        std::fs::create_dir_all(&directory).unwrap();
+        // Frames from an earlier run
+        for frame in [1, 2, 3, 7] {
+            std::fs::write(directory.join(format!("ring-{:06}.png", frame)), b"").unwrap();
+        }
+        let config = OverlayConfig {
+            text: false,
+            png_size: 32,
+            png_sequence: true,
+            ..OverlayConfig::default()
+        };
+        let mut writer = Writer::new(
+            config,
+            RingConfig::default(),
+            Palette::dark(),
+            directory.clone(),
+        );
+        writer.kept_frames = 3;
+        let engine = Engine::new(60, 2, 0);
+
+        writer.write(&engine.snapshot());
+        writer.write(&engine.snapshot());
+        assert!(!writer.warned);
+
+        let mut frames: Vec<u64> = std::fs::read_dir(&directory)
+            .unwrap()
+            .filter_map(|entry| frame_number(entry.unwrap().file_name().to_str().unwrap()))
+            .collect();
+        frames.sort();
+        assert_eq!(frames, [7, 8, 9]);
+        assert!(!std::fs::read(directory.join("ring-000009.png"))
+            .unwrap()
+            .is_empty());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_frame_number() {
+        assert_eq!(frame_number("ring-000042.png"), Some(42));
+        assert_eq!(frame_number("ring-1234567.png"), Some(1234567));
+        assert_eq!(frame_number("ring.png"), None);
+        assert_eq!(frame_number("ring-000042.png.tmp"), None);
+    }
+}
@@ -0,0 +1,80 @@
+use iced::widget::image::{Handle, Image};
+use iced::{ContentFit, Element, Length};
+use std::path::Path;
+
+/// Pictures are shrunk to at most this many pixels across, which keeps a
+/// schedule full of photos small in memory.
+const MAX_SIZE: u32 = 512;
+
+/// Decodes a round's picture up front, so a missing or broken file is
+/// reported when the session is set up rather than when its round comes.
+pub fn load(path: &Path) -> Result<Handle, String> {
+    let picture = image::open(path).map_err(|err| err.to_string())?;
+    Ok(shrink(picture))
+}
+
+fn shrink(picture: image::DynamicImage) -> Handle {
+    let picture = if picture.width().max(picture.height()) > MAX_SIZE {
+        picture.thumbnail(MAX_SIZE, MAX_SIZE)
+    } else {
+        picture
+    }
+    .into_rgba8();
+    Handle::from_rgba(picture.width(), picture.height(), picture.into_raw())
+}
+
+/// The picture scaled to fit in a `size` square, keeping its shape.
+pub fn picture<'a, Message: 'a>(handle: Handle, size: Length) -> Element<'a, Message> {
+    Image::new(handle)
+        .width(size)
+        .height(size)
+        .content_fit(ContentFit::Contain)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn size(handle: &Handle) -> (u32, u32) {
+        match handle {
+            Handle::Rgba { width, height, .. } => (*width, *height),
+            handle => panic!("pictures are decoded up front: {:?}", handle),
+        }
+    }
+
+    #[test]
+    fn test_small_pictures_keep_their_size() {
+        let picture = image::DynamicImage::new_rgba8(40, 20);
+        assert_eq!(size(&shrink(picture)), (40, 20));
+    }
+
+    #[test]
+    fn test_large_pictures_shrink_keeping_their_shape() {
+        let picture = image::DynamicImage::new_rgba8(2048, 1024);
+        assert_eq!(size(&shrink(picture)), (512, 256));
+    }
+
+    #[test]
+    fn test_load_png() {
+        let path = std::env::temp_dir().join(format!("round-timer-{}.png", std::process::id()));
+        let mut pixmap = tiny_skia::Pixmap::new(4, 2).unwrap();
+        pixmap.fill(tiny_skia::Color::WHITE);
+        pixmap.save_png(&path).unwrap();
+
+        let handle = load(&path).unwrap();
+        assert_eq!(size(&handle), (4, 2));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_or_broken_pictures_are_errors() {
+        let path = std::env::temp_dir().join(format!("round-timer-{}.jpg", std::process::id()));
+        assert!(load(&path).is_err());
+
+        std::fs::write(&path, b"not a picture").unwrap();
+        assert!(load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
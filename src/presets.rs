@@ -0,0 +1,69 @@
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub interval_secs: u32,
+    pub num_rounds: u32,
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "boxing",
+        description: "12 rounds of 3 minutes",
+        interval_secs: 180,
+        num_rounds: 12,
+    },
+    Preset {
+        name: "emom",
+        description: "Every minute on the minute for 10 minutes",
+        interval_secs: 60,
+        num_rounds: 10,
+    },
+    Preset {
+        name: "mma",
+        description: "5 rounds of 5 minutes",
+        interval_secs: 300,
+        num_rounds: 5,
+    },
+    Preset {
+        name: "pomodoro",
+        description: "4 blocks of 25 minutes",
+        interval_secs: 1500,
+        num_rounds: 4,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS
+        .iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_known_preset() {
+        let preset = find("boxing").unwrap();
+        assert_eq!(preset.interval_secs, 180);
+        assert_eq!(preset.num_rounds, 12);
+    }
+
+    #[test]
+    fn test_find_is_case_insensitive() {
+        assert!(find("EMOM").is_some());
+    }
+
+    #[test]
+    fn test_find_unknown_preset() {
+        assert!(find("marathon").is_none());
+    }
+
+    #[test]
+    fn test_presets_are_valid() {
+        for preset in PRESETS {
+            assert!(preset.interval_secs > 0, "{}", preset.name);
+            assert!(preset.num_rounds > 0, "{}", preset.name);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::config::RoundConfig;
+
+/// Labels, notes and pictures for the rounds of a session.
+///
+/// When there are more rounds than entries the schedule starts over, so a
+/// circuit of five exercises covers a session of twenty rounds.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schedule {
+    rounds: Vec<RoundConfig>,
+}
+
+/// A schedule file given with `--schedule`, laid out like the `[[rounds]]`
+/// of the configuration file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleFile {
+    rounds: Vec<RoundConfig>,
+}
+
+impl Schedule {
+    /// Pictures are looked up relative to `base`, the directory of the file
+    /// that listed them.
+    pub fn new(mut rounds: Vec<RoundConfig>, base: Option<&Path>) -> Self {
+        if let Some(base) = base {
+            for round in &mut rounds {
+                if let Some(image) = &mut round.image {
+                    *image = base.join(&*image);
+                }
+            }
+        }
+        Self { rounds }
+    }
+
+    pub fn parse(contents: &str, base: Option<&Path>) -> Result<Self, String> {
+        let file: ScheduleFile =
+            toml::from_str(contents).map_err(|err| err.message().to_string())?;
+        Ok(Self::new(file.rounds, base))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Self::parse(&contents, path.parent())
+            .map_err(|message| format!("{}: {}", path.display(), message))
+    }
+
+    pub fn entries(&self) -> &[RoundConfig] {
+        &self.rounds
+    }
+
+    /// The entry for `round`, counting from 1, and its index in `entries`.
+    pub fn entry(&self, round: u32) -> Option<(usize, &RoundConfig)> {
+        if self.rounds.is_empty() || round == 0 {
+            return None;
+        }
+        let index = (round as usize - 1) % self.rounds.len();
+        Some((index, &self.rounds[index]))
+    }
+
+    pub fn round(&self, round: u32) -> Option<&RoundConfig> {
+        self.entry(round).map(|(_, entry)| entry)
+    }
+
+    /// The label of `round`, unless it has none.
+    pub fn label(&self, round: u32) -> Option<String> {
+        self.round(round)
+            .map(|entry| entry.label.clone())
+            .filter(|label| !label.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn round(label: &str) -> RoundConfig {
+        RoundConfig {
+            label: label.to_string(),
+            ..RoundConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_rounds_cycle_through_the_schedule() {
+        let schedule = Schedule::new(vec![round("Push-ups"), round("Squats")], None);
+        assert_eq!(schedule.label(1).as_deref(), Some("Push-ups"));
+        assert_eq!(schedule.label(2).as_deref(), Some("Squats"));
+        assert_eq!(schedule.label(3).as_deref(), Some("Push-ups"));
+        assert_eq!(schedule.entry(4).map(|(index, _)| index), Some(1));
+        assert_eq!(schedule.label(0), None);
+    }
+
+    #[test]
+    fn test_empty_schedule_has_no_labels() {
+        let schedule = Schedule::default();
+        assert!(schedule.entries().is_empty());
+        assert_eq!(schedule.round(1), None);
+        assert_eq!(schedule.label(1), None);
+
+        let unlabelled = Schedule::new(vec![round("")], None);
+        assert_eq!(unlabelled.label(1), None);
+    }
+
+    #[test]
+    fn test_parse_schedule_file() {
+        let schedule = Schedule::parse(
+            "[[rounds]]\nlabel = \"Burpees\"\nimage = \"burpee.png\"\n\n[[rounds]]\nlabel = \"Rest\"\nimage = \"/pictures/rest.png\"",
+            Some(Path::new("/home/coach/circuits")),
+        )
+        .unwrap();
+
+        assert_eq!(schedule.entries().len(), 2);
+        assert_eq!(
+            schedule.round(1).unwrap().image,
+            Some(PathBuf::from("/home/coach/circuits/burpee.png"))
+        );
+        // Absolute paths stay as they are
+        assert_eq!(
+            schedule.round(2).unwrap().image,
+            Some(PathBuf::from("/pictures/rest.png"))
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_keys() {
+        assert!(Schedule::parse("[[rounds]]\ntitle = \"Burpees\"", None).is_err());
+        assert!(Schedule::parse("", None).is_err());
+    }
+
+    #[test]
+    fn test_load_names_the_file() {
+        let err = Schedule::load(Path::new("/nonexistent/circuit.toml")).unwrap_err();
+        assert!(err.starts_with("/nonexistent/circuit.toml: "));
+    }
+}
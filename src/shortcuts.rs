@@ -0,0 +1,355 @@
+use iced::keyboard::{key::Named, Key, Modifiers};
+
+use crate::config::KeysConfig;
+
+/// Something the window can do from the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Toggle,
+    Stop,
+    Next,
+    Previous,
+    MoreTime,
+    LessTime,
+    BigClock,
+    Mute,
+    Help,
+}
+
+impl Action {
+    /// What the help overlay says the key does.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Toggle => "Start, pause or resume",
+            Action::Stop => "Stop",
+            Action::Next => "Next round",
+            Action::Previous => "Previous round",
+            Action::MoreTime => "More time",
+            Action::LessTime => "Less time",
+            Action::BigClock => "Big clock",
+            Action::Mute => "Mute or unmute the chime",
+            Action::Help => "Show or hide this help",
+        }
+    }
+}
+
+/// A key that can be bound to an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Named(Named),
+    /// A character key, in lower case.
+    Character(char),
+}
+
+const NAMES: [(&str, Named); 26] = [
+    ("space", Named::Space),
+    ("escape", Named::Escape),
+    ("enter", Named::Enter),
+    ("tab", Named::Tab),
+    ("backspace", Named::Backspace),
+    ("delete", Named::Delete),
+    ("home", Named::Home),
+    ("end", Named::End),
+    ("page_up", Named::PageUp),
+    ("page_down", Named::PageDown),
+    ("up", Named::ArrowUp),
+    ("down", Named::ArrowDown),
+    ("left", Named::ArrowLeft),
+    ("right", Named::ArrowRight),
+    ("f1", Named::F1),
+    ("f2", Named::F2),
+    ("f3", Named::F3),
+    ("f4", Named::F4),
+    ("f5", Named::F5),
+    ("f6", Named::F6),
+    ("f7", Named::F7),
+    ("f8", Named::F8),
+    ("f9", Named::F9),
+    ("f10", Named::F10),
+    ("f11", Named::F11),
+    ("f12", Named::F12),
+];
+
+impl Binding {
+    /// Reads a key as written in the configuration: a single character, or a
+    /// name such as `space`, `escape`, `up` or `f1`.
+    pub fn parse(name: &str) -> Option<Self> {
+        let lower = name.to_lowercase();
+        let mut chars = lower.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return (!c.is_whitespace()).then_some(Binding::Character(c));
+        }
+        match lower.as_str() {
+            "esc" => Some(Binding::Named(Named::Escape)),
+            "return" => Some(Binding::Named(Named::Enter)),
+            _ => NAMES
+                .iter()
+                .find(|(known, _)| *known == lower)
+                .map(|&(_, named)| Binding::Named(named)),
+        }
+    }
+
+    /// How the help overlay shows the key.
+    pub fn label(self) -> String {
+        match self {
+            Binding::Named(Named::Escape) => String::from("Esc"),
+            Binding::Named(Named::ArrowUp) => String::from("↑"),
+            Binding::Named(Named::ArrowDown) => String::from("↓"),
+            Binding::Named(Named::ArrowLeft) => String::from("←"),
+            Binding::Named(Named::ArrowRight) => String::from("→"),
+            Binding::Named(named) => format!("{:?}", named),
+            Binding::Character(c) => c.to_uppercase().to_string(),
+        }
+    }
+
+    fn matches(self, key: &Key) -> bool {
+        match (self, key.as_ref()) {
+            (Binding::Named(named), Key::Named(pressed)) => named == pressed,
+            (Binding::Character(c), Key::Character(pressed)) => {
+                let mut pressed = pressed.chars().flat_map(char::to_lowercase);
+                pressed.next() == Some(c) && pressed.next().is_none()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The keys bound to each action.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shortcuts {
+    bindings: Vec<(Action, Vec<Binding>)>,
+}
+
+impl Shortcuts {
+    pub fn from_config(config: &KeysConfig) -> Result<Self, String> {
+        let keys = [
+            (Action::Toggle, &config.toggle),
+            (Action::Stop, &config.stop),
+            (Action::Next, &config.next),
+            (Action::Previous, &config.previous),
+            (Action::MoreTime, &config.more_time),
+            (Action::LessTime, &config.less_time),
+            (Action::BigClock, &config.big_clock),
+            (Action::Mute, &config.mute),
+            (Action::Help, &config.help),
+        ];
+
+        let mut bindings: Vec<(Action, Vec<Binding>)> = Vec::new();
+        for (action, names) in keys {
+            let mut action_keys: Vec<Binding> = Vec::new();
+            for name in names.names() {
+                let binding =
+                    Binding::parse(name).ok_or_else(|| format!("'{}' is not a key", name))?;
+                let other = bindings
+                    .iter()
+                    .find(|(_, bound)| bound.contains(&binding))
+                    .map(|&(other, _)| other)
+                    .or_else(|| action_keys.contains(&binding).then_some(action));
+                if let Some(other) = other {
+                    return Err(format!(
+                        "key '{}' is bound to both {:?} and {:?}",
+                        name, other, action
+                    ));
+                }
+                action_keys.push(binding);
+            }
+            bindings.push((action, action_keys));
+        }
+        Ok(Self { bindings })
+    }
+
+    /// The action for a key press. Presses with Ctrl, Alt or the logo key
+    /// held are left to the desktop.
+    pub fn action(&self, key: &Key, modifiers: Modifiers) -> Option<Action> {
+        if modifiers.control() || modifiers.alt() || modifiers.logo() {
+            return None;
+        }
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|binding| binding.matches(key)))
+            .map(|&(action, _)| action)
+    }
+
+    /// The first key bound to `action`, if any.
+    pub fn binding(&self, action: Action) -> Option<Binding> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .and_then(|(_, keys)| keys.first().copied())
+    }
+
+    /// The keys of every action, in order; actions may have none.
+    pub fn bindings(&self) -> &[(Action, Vec<Binding>)] {
+        &self.bindings
+    }
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self::from_config(&KeysConfig::default()).expect("the default keys are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::KeyNames;
+
+    fn character(c: &str) -> Key {
+        Key::Character(c.into())
+    }
+
+    #[test]
+    fn test_default_keys() {
+        let shortcuts = Shortcuts::default();
+        let none = Modifiers::empty();
+
+        assert_eq!(
+            shortcuts.action(&Key::Named(Named::Space), none),
+            Some(Action::Toggle)
+        );
+        assert_eq!(
+            shortcuts.action(&Key::Named(Named::Escape), none),
+            Some(Action::Stop)
+        );
+        assert_eq!(shortcuts.action(&character("n"), none), Some(Action::Next));
+        assert_eq!(
+            shortcuts.action(&character("p"), none),
+            Some(Action::Previous)
+        );
+        assert_eq!(
+            shortcuts.action(&character("+"), Modifiers::SHIFT),
+            Some(Action::MoreTime)
+        );
+        assert_eq!(
+            shortcuts.action(&character("-"), none),
+            Some(Action::LessTime)
+        );
+        assert_eq!(
+            shortcuts.action(&character("f"), none),
+            Some(Action::BigClock)
+        );
+        assert_eq!(
+            shortcuts.action(&Key::Named(Named::F11), none),
+            Some(Action::BigClock)
+        );
+        assert_eq!(shortcuts.action(&character("m"), none), Some(Action::Mute));
+        assert_eq!(
+            shortcuts.action(&character("?"), Modifiers::SHIFT),
+            Some(Action::Help)
+        );
+        assert_eq!(shortcuts.action(&character("x"), none), None);
+    }
+
+    #[test]
+    fn test_characters_ignore_case() {
+        let shortcuts = Shortcuts::default();
+        assert_eq!(
+            shortcuts.action(&character("N"), Modifiers::SHIFT),
+            Some(Action::Next)
+        );
+    }
+
+    #[test]
+    fn test_desktop_shortcuts_are_left_alone() {
+        let shortcuts = Shortcuts::default();
+        assert_eq!(shortcuts.action(&character("m"), Modifiers::CTRL), None);
+        assert_eq!(shortcuts.action(&character("n"), Modifiers::ALT), None);
+        assert_eq!(shortcuts.action(&character("f"), Modifiers::LOGO), None);
+    }
+
+    #[test]
+    fn test_parse_bindings() {
+        assert_eq!(Binding::parse("Space"), Some(Binding::Named(Named::Space)));
+        assert_eq!(Binding::parse("esc"), Some(Binding::Named(Named::Escape)));
+        assert_eq!(Binding::parse("F1"), Some(Binding::Named(Named::F1)));
+        assert_eq!(Binding::parse("f12"), Some(Binding::Named(Named::F12)));
+        assert_eq!(Binding::parse("K"), Some(Binding::Character('k')));
+        assert_eq!(Binding::parse("="), Some(Binding::Character('=')));
+        assert_eq!(Binding::parse(""), None);
+        assert_eq!(Binding::parse(" "), None);
+        assert_eq!(Binding::parse("hyper"), None);
+    }
+
+    #[test]
+    fn test_labels() {
+        assert_eq!(Binding::Named(Named::Space).label(), "Space");
+        assert_eq!(Binding::Named(Named::Escape).label(), "Esc");
+        assert_eq!(Binding::Named(Named::F1).label(), "F1");
+        assert_eq!(Binding::Character('n').label(), "N");
+    }
+
+    #[test]
+    fn test_remapping() {
+        let config = KeysConfig {
+            toggle: KeyNames::from("enter"),
+            next: KeyNames::from("right"),
+            ..KeysConfig::default()
+        };
+        let shortcuts = Shortcuts::from_config(&config).unwrap();
+        let none = Modifiers::empty();
+
+        assert_eq!(
+            shortcuts.action(&Key::Named(Named::Enter), none),
+            Some(Action::Toggle)
+        );
+        assert_eq!(
+            shortcuts.action(&Key::Named(Named::ArrowRight), none),
+            Some(Action::Next)
+        );
+        assert_eq!(shortcuts.action(&Key::Named(Named::Space), none), None);
+        assert_eq!(
+            shortcuts.binding(Action::Next),
+            Some(Binding::Named(Named::ArrowRight))
+        );
+    }
+
+    #[test]
+    fn test_keys_must_be_distinct_and_known() {
+        let twice = KeysConfig {
+            mute: KeyNames::from("n"),
+            ..KeysConfig::default()
+        };
+        assert!(Shortcuts::from_config(&twice)
+            .unwrap_err()
+            .contains("both Next and Mute"));
+
+        let unknown = KeysConfig {
+            help: KeyNames::from("meta"),
+            ..KeysConfig::default()
+        };
+        assert!(Shortcuts::from_config(&unknown).is_err());
+
+        let repeated = KeysConfig {
+            mute: KeyNames::Several(vec![String::from("m"), String::from("M")]),
+            ..KeysConfig::default()
+        };
+        assert!(Shortcuts::from_config(&repeated)
+            .unwrap_err()
+            .contains("both Mute and Mute"));
+    }
+
+    #[test]
+    fn test_several_keys_or_none() {
+        let config = KeysConfig {
+            big_clock: KeyNames::Several(vec![String::from("b"), String::from("f12")]),
+            help: KeyNames::Several(Vec::new()),
+            ..KeysConfig::default()
+        };
+        let shortcuts = Shortcuts::from_config(&config).unwrap();
+        let none = Modifiers::empty();
+
+        assert_eq!(
+            shortcuts.action(&character("b"), none),
+            Some(Action::BigClock)
+        );
+        assert_eq!(
+            shortcuts.action(&Key::Named(Named::F12), none),
+            Some(Action::BigClock)
+        );
+        // F11 is only a default, and can be taken away
+        assert_eq!(shortcuts.action(&Key::Named(Named::F11), none), None);
+        assert_eq!(shortcuts.action(&character("?"), Modifiers::SHIFT), None);
+        assert_eq!(shortcuts.binding(Action::Help), None);
+    }
+}
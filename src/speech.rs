@@ -0,0 +1,129 @@
+use std::process::Stdio;
+use tokio::process::Command;
+
+use crate::config::SpeechConfig;
+use crate::events::TimerEvent;
+
+/// Reads round labels aloud in the background, so a circuit can be followed
+/// without looking at the screen.
+pub struct Speech {
+    config: SpeechConfig,
+    quiet: bool,
+}
+
+impl Speech {
+    /// When `quiet` is set, failures are discarded instead of going to
+    /// stderr, e.g. while the terminal front-end owns the screen.
+    pub fn new(config: SpeechConfig, quiet: bool) -> Self {
+        Self { config, quiet }
+    }
+
+    /// What to say for `event`: the label of a round as it starts.
+    fn text_for(event: &TimerEvent) -> Option<&str> {
+        match event {
+            TimerEvent::RoundStarted {
+                label: Some(label), ..
+            } => Some(label),
+            _ => None,
+        }
+    }
+
+    /// Speaks the label of a starting round, if speech is enabled, on the
+    /// current tokio runtime. Never waits for the speech to finish.
+    pub fn say(&self, event: &TimerEvent) {
+        if !self.config.enabled {
+            return;
+        }
+        let Some(text) = Self::text_for(event) else {
+            return;
+        };
+        let quiet = self.quiet;
+
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            if !quiet {
+                eprintln!("round-timer: speech skipped: no async runtime");
+            }
+            return;
+        };
+
+        let stderr = if quiet {
+            Stdio::null()
+        } else {
+            Stdio::inherit()
+        };
+        // The label is a single argument, never parsed by a shell, and `--`
+        // keeps one starting with a dash from being read as an option
+        let child = Command::new(&self.config.command)
+            .arg("--")
+            .arg(text)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(stderr)
+            .spawn();
+        let command = self.config.command.clone();
+
+        runtime.spawn(async move {
+            let result = match child {
+                Ok(mut child) => child.wait().await.map(|_| ()),
+                Err(err) => Err(err),
+            };
+            if let Err(err) = result {
+                if !quiet {
+                    eprintln!("round-timer: cannot speak with {}: {}", command, err);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_started(label: Option<&str>) -> TimerEvent {
+        TimerEvent::RoundStarted {
+            round: 2,
+            label: label.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_only_labels_of_starting_rounds_are_spoken() {
+        assert_eq!(
+            Speech::text_for(&round_started(Some("Push-ups"))),
+            Some("Push-ups")
+        );
+        assert_eq!(Speech::text_for(&round_started(None)), None);
+        assert_eq!(Speech::text_for(&TimerEvent::Chime { round: 2 }), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_say_runs_the_command_with_the_label() {
+        let spoken =
+            std::env::temp_dir().join(format!("round-timer-spoken-{}", std::process::id()));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+
+        // `touch` stands in for a speech program, and the label for a file to create
+        let speech = Speech::new(
+            SpeechConfig {
+                enabled: true,
+                command: String::from("touch"),
+            },
+            true,
+        );
+        speech.say(&round_started(spoken.to_str()));
+        runtime.block_on(async {
+            for _ in 0..100 {
+                if spoken.exists() {
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        });
+        assert!(spoken.exists());
+
+        std::fs::remove_file(&spoken).unwrap();
+    }
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What the app remembers between runs by itself, unlike the configuration
+/// file, which only the user edits.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    /// Top-left corner of the mini window on the screen.
+    pub mini_position: Option<(f32, f32)>,
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("round-timer").join("state.json"))
+}
+
+impl State {
+    /// Loads the state, starting afresh if it is missing or unreadable.
+    pub fn load(path: &Path) -> Self {
+        std::fs::read(path)
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let contents = serde_json::to_vec_pretty(self).expect("the state always serializes");
+        std::fs::write(path, contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir()
+            .join(format!("round-timer-state-{}", std::process::id()))
+            .join("state.json");
+        let state = State {
+            mini_position: Some((1200.0, 40.0)),
+        };
+
+        state.save(&path).unwrap();
+        assert_eq!(State::load(&path), state);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_missing_or_corrupt_state_starts_afresh() {
+        assert_eq!(
+            State::load(Path::new("/nonexistent/state.json")),
+            State::default()
+        );
+
+        let path = std::env::temp_dir().join(format!(
+            "round-timer-corrupt-state-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(&path, "{not json").unwrap();
+        assert_eq!(State::load(&path), State::default());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,637 @@
+use iced::futures::SinkExt;
+use iced::Subscription;
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+use tokio::time::{sleep_until, Instant};
+
+use crate::config::{SyncConfig, SyncRole};
+use crate::engine::{Engine, Snapshot, TimerState};
+use crate::events::{self, TimerEvent};
+use crate::Message;
+
+const TICK: Duration = Duration::from_secs(1);
+
+/// How often the leader repeats itself while nothing changes.
+const HEARTBEAT: Duration = Duration::from_secs(1);
+
+/// Larger gaps are jumped over rather than replayed tick by tick.
+const MAX_REPLAY: u32 = 3;
+
+/// Clock differences above this mean the two wall clocks disagree, not that
+/// the network is slow.
+const MAX_LATENCY_MS: u64 = 1000;
+
+/// The leader announces itself every second, so its last tick can never be
+/// much older than that.
+const MAX_TICK_AGE_MS: u64 = 2000;
+
+/// The leader's state, announced on every change and once a second.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Beacon {
+    pub session: String,
+    pub state: TimerState,
+    pub interval_secs: u32,
+    pub rounds: u32,
+    pub lead_in_secs: u32,
+    pub warning_secs: u32,
+    pub elapsed_secs: u32,
+    pub round: u32,
+    pub lead_in_remaining_secs: u32,
+    /// Time since the leader's last tick when the beacon was sent.
+    pub tick_age_ms: u64,
+    pub sent_ms: u64,
+}
+
+impl Beacon {
+    fn new(session: &str, snapshot: &Snapshot, tick_age_ms: u64, sent_ms: u64) -> Self {
+        Self {
+            session: session.to_string(),
+            state: snapshot.state,
+            interval_secs: snapshot.interval_secs,
+            rounds: snapshot.rounds,
+            lead_in_secs: snapshot.lead_in_secs,
+            warning_secs: snapshot.warning_secs,
+            elapsed_secs: snapshot.elapsed_secs,
+            round: snapshot.round,
+            lead_in_remaining_secs: snapshot.lead_in_remaining_secs,
+            tick_age_ms,
+            sent_ms,
+        }
+    }
+
+    /// Whether the beacon describes a session this instance could be in.
+    /// Beacons come from anyone on the network, so nothing else is trusted.
+    fn is_valid(&self) -> bool {
+        let Some(total_secs) = self.interval_secs.checked_mul(self.rounds) else {
+            return false;
+        };
+        self.interval_secs > 0
+            && (1..=self.rounds).contains(&self.round)
+            && self.elapsed_secs <= total_secs
+            && self.lead_in_remaining_secs <= self.lead_in_secs
+            && self.tick_age_ms <= MAX_TICK_AGE_MS
+    }
+
+    /// The leader's engine as of the last tick before the beacon was sent.
+    pub fn engine(&self) -> Engine {
+        let mut engine = Engine::new(self.interval_secs, self.rounds, self.lead_in_secs);
+        engine.set_warning(self.warning_secs);
+        engine.timer_state = self.state;
+        engine.elapsed_secs = self.elapsed_secs;
+        engine.round_number = self.round;
+        engine.lead_in_remaining = self.lead_in_remaining_secs;
+        engine
+    }
+
+    /// How far the leader is past the tick described by this beacon, received
+    /// at `now_ms` on the wall clock.
+    ///
+    /// The network latency is only counted when the two clocks plausibly
+    /// agree; otherwise it is assumed to be negligible.
+    fn delay(&self, now_ms: u64) -> Duration {
+        let latency_ms = now_ms
+            .checked_sub(self.sent_ms)
+            .filter(|&latency| latency < MAX_LATENCY_MS)
+            .unwrap_or(0);
+        Duration::from_millis(self.tick_age_ms.saturating_add(latency_ms))
+    }
+
+    /// The beacon the leader would send after `delay`, for ticks it has had
+    /// since.
+    fn compensated(&self, delay: Duration) -> Beacon {
+        let mut engine = self.engine();
+        engine.advance(u32::try_from(delay.as_secs()).unwrap_or(u32::MAX));
+        Beacon {
+            tick_age_ms: delay.subsec_millis().into(),
+            ..Beacon::new(&self.session, &engine.snapshot(), 0, self.sent_ms)
+        }
+    }
+}
+
+/// Ticks since the session started, counting the lead-in.
+fn position(engine: &Engine) -> u32 {
+    engine
+        .lead_in_secs
+        .saturating_sub(engine.lead_in_remaining)
+        .saturating_add(engine.elapsed_secs)
+}
+
+/// Brings a follower's engine in line with the leader's, returning the events
+/// the follower would have seen on the way so it chimes along.
+pub fn follow(engine: &mut Engine, leader: &Engine) -> Vec<TimerEvent> {
+    if engine.interval_secs != leader.interval_secs {
+        engine.set_interval(leader.interval_secs);
+    }
+    if engine.num_rounds != leader.num_rounds {
+        engine.set_rounds(leader.num_rounds);
+    }
+    engine.lead_in_secs = leader.lead_in_secs;
+    // A longer lead-in of our own cannot have more left than the leader's
+    engine.lead_in_remaining = engine.lead_in_remaining.min(leader.lead_in_secs);
+    engine.set_warning(leader.warning_secs);
+
+    let mut events = Vec::new();
+    match leader.timer_state {
+        TimerState::Stopped => {
+            if engine.timer_state != TimerState::Stopped {
+                // A session that ran to the end stops with all of it elapsed
+                if leader.elapsed_secs >= leader.total_duration_secs {
+                    events.extend(catch_up(engine, leader));
+                }
+                events.extend(engine.stop());
+            }
+        }
+        TimerState::Running | TimerState::Paused => {
+            if engine.timer_state == TimerState::Stopped {
+                events.extend(engine.start());
+            }
+            if leader.timer_state == TimerState::Running {
+                events.extend(engine.resume());
+            }
+            events.extend(catch_up(engine, leader));
+            if leader.timer_state == TimerState::Paused {
+                events.extend(engine.pause());
+            }
+        }
+    }
+    events
+}
+
+fn catch_up(engine: &mut Engine, leader: &Engine) -> Vec<TimerEvent> {
+    let mut events = Vec::new();
+    let ours = position(engine);
+    let theirs = position(leader);
+
+    // Our own tick can land just before the leader's beacon for it
+    if ours == theirs + 1 && engine.timer_state == TimerState::Running {
+        return events;
+    }
+
+    if engine.timer_state == TimerState::Running && theirs > ours && theirs - ours <= MAX_REPLAY {
+        for _ in ours..theirs {
+            events.extend(engine.tick());
+        }
+    }
+    if position(engine) == position(leader) && engine.round_number == leader.round_number {
+        return events;
+    }
+
+    // Too far apart to replay: the leader skipped, or we joined mid-session
+    let skipped_lead_in = engine.in_lead_in() && !leader.in_lead_in() && leader.round_number == 1;
+    if skipped_lead_in || leader.round_number == engine.round_number + 1 {
+        events.extend(engine.skip());
+    }
+    engine.elapsed_secs = leader.elapsed_secs;
+    engine.round_number = leader.round_number;
+    engine.lead_in_remaining = leader.lead_in_remaining;
+    events
+}
+
+/// Announces this instance's state to followers, or follows a leader, as
+/// configured.
+pub fn subscription(
+    config: SyncConfig,
+    status: watch::Receiver<Snapshot>,
+) -> Subscription<Message> {
+    let group: SocketAddrV4 = config
+        .group
+        .parse()
+        .expect("the sync group is checked when the configuration is loaded");
+
+    Subscription::run_with_id(
+        "sync",
+        iced::stream::channel(16, move |output| async move {
+            match config.role {
+                SyncRole::Leader => lead(&config.session, group, status).await,
+                SyncRole::Follower => {
+                    let socket = match follower_socket(group) {
+                        Ok(socket) => Some(socket),
+                        Err(err) => {
+                            eprintln!(
+                                "round-timer: cannot listen for a leader on {}: {}",
+                                group, err
+                            );
+                            None
+                        }
+                    };
+                    let timeout = Duration::from_secs(config.leader_timeout_secs);
+                    follow_leader(&config.session, socket, timeout, status, output).await;
+                }
+                SyncRole::Off => {}
+            }
+            std::future::pending::<()>().await;
+        }),
+    )
+}
+
+async fn lead(session: &str, group: SocketAddrV4, mut status: watch::Receiver<Snapshot>) {
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await {
+        Ok(socket) => socket,
+        Err(err) => {
+            eprintln!("round-timer: cannot announce to followers: {}", err);
+            return;
+        }
+    };
+
+    let mut warned = false;
+    let mut tick_at = Instant::now();
+    let mut ticks = {
+        let snapshot = status.borrow();
+        (snapshot.elapsed_secs, snapshot.lead_in_remaining_secs)
+    };
+
+    loop {
+        let snapshot = status.borrow_and_update().clone();
+        let now = Instant::now();
+        if (snapshot.elapsed_secs, snapshot.lead_in_remaining_secs) != ticks {
+            ticks = (snapshot.elapsed_secs, snapshot.lead_in_remaining_secs);
+            tick_at = now;
+        }
+
+        let tick_age_ms = (now - tick_at).as_millis() as u64;
+        let beacon = Beacon::new(session, &snapshot, tick_age_ms, events::timestamp_ms());
+        let datagram = serde_json::to_vec(&beacon).expect("beacons always serialize");
+        if let Err(err) = socket.send_to(&datagram, group).await {
+            if !warned {
+                eprintln!("round-timer: cannot announce to followers: {}", err);
+                warned = true;
+            }
+        }
+
+        tokio::select! {
+            changed = status.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+            _ = tokio::time::sleep(HEARTBEAT) => {}
+        }
+    }
+}
+
+/// Binds the group's port, shared with other followers on this computer.
+fn follower_socket(group: SocketAddrV4) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, group.port())).into())?;
+    if group.ip().is_multicast() {
+        socket.join_multicast_v4(group.ip(), &Ipv4Addr::UNSPECIFIED)?;
+    }
+    socket.set_nonblocking(true)?;
+    UdpSocket::from_std(socket.into())
+}
+
+/// Mirrors the leader's beacons and ticks in step with it, carrying on alone
+/// when it goes quiet.
+async fn follow_leader(
+    session: &str,
+    socket: Option<UdpSocket>,
+    timeout: Duration,
+    status: watch::Receiver<Snapshot>,
+    mut output: iced::futures::channel::mpsc::Sender<Message>,
+) {
+    let mut buffer = [0; 1024];
+    let mut leader: Option<SocketAddr> = None;
+    let mut last_heard = Instant::now();
+    let mut next_tick = Instant::now() + TICK;
+
+    loop {
+        let message = tokio::select! {
+            received = async { socket.as_ref()?.recv_from(&mut buffer).await.ok() }, if socket.is_some() => {
+                let Some((len, from)) = received else {
+                    continue;
+                };
+                let Ok(beacon) = serde_json::from_slice::<Beacon>(&buffer[..len]) else {
+                    continue;
+                };
+                // Stick with one leader until it goes quiet
+                if beacon.session != session
+                    || !beacon.is_valid()
+                    || leader.is_some_and(|leader| leader != from)
+                {
+                    continue;
+                }
+
+                let now = Instant::now();
+                let delay = beacon.delay(events::timestamp_ms());
+                let phase = Duration::from_millis(u64::from(delay.subsec_millis()));
+                next_tick = now.checked_sub(phase).unwrap_or(now) + TICK;
+                leader = Some(from);
+                last_heard = now;
+                Message::Follow(beacon.compensated(delay))
+            }
+            _ = sleep_until(next_tick) => {
+                next_tick += TICK;
+                if status.borrow().state != TimerState::Running {
+                    continue;
+                }
+                Message::Tick
+            }
+            _ = sleep_until(last_heard + timeout), if leader.is_some() => {
+                leader = None;
+                Message::LeaderLost
+            }
+        };
+
+        if output.send(message).await.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::futures::channel::mpsc;
+    use iced::futures::StreamExt;
+
+    fn running(interval_secs: u32, rounds: u32, lead_in_secs: u32, ticks: u32) -> Engine {
+        let mut engine = Engine::new(interval_secs, rounds, lead_in_secs);
+        engine.start();
+        for _ in 0..ticks {
+            engine.tick();
+        }
+        engine
+    }
+
+    #[test]
+    fn test_beacon_round_trip() {
+        let leader = running(60, 20, 10, 25);
+        let beacon = Beacon::new("gym", &leader.snapshot(), 120, 1000);
+        let json = serde_json::to_string(&beacon).unwrap();
+        let parsed: Beacon = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, beacon);
+        assert_eq!(position(&parsed.engine()), position(&leader));
+    }
+
+    #[test]
+    fn test_delay_counts_plausible_latency_only() {
+        let beacon = Beacon::new("gym", &Engine::new(60, 20, 0).snapshot(), 300, 10_000);
+        assert_eq!(beacon.delay(10_040), Duration::from_millis(340));
+        // Clocks too far apart, in either direction
+        assert_eq!(beacon.delay(15_000), Duration::from_millis(300));
+        assert_eq!(beacon.delay(9_000), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_compensated_beacon_includes_missed_ticks() {
+        let leader = running(60, 20, 0, 10);
+        let beacon = Beacon::new("gym", &leader.snapshot(), 0, 0);
+        let compensated = beacon.compensated(Duration::from_millis(2300));
+        assert_eq!(compensated.elapsed_secs, 12);
+        assert_eq!(compensated.tick_age_ms, 300);
+    }
+
+    #[test]
+    fn test_compensated_beacon_jumps_long_delays() {
+        let leader = running(60, 20, 0, 10);
+        let beacon = Beacon::new("gym", &leader.snapshot(), 0, 0);
+        let compensated = beacon.compensated(Duration::from_secs(u64::MAX));
+        assert_eq!(compensated.state, TimerState::Stopped);
+        assert_eq!(compensated.elapsed_secs, 1200);
+    }
+
+    #[test]
+    fn test_invalid_beacons_are_rejected() {
+        let beacon = Beacon::new("gym", &running(60, 20, 10, 25).snapshot(), 120, 1000);
+        assert!(beacon.is_valid());
+
+        let invalid = [
+            Beacon {
+                lead_in_remaining_secs: 11,
+                ..beacon.clone()
+            },
+            Beacon {
+                interval_secs: u32::MAX,
+                ..beacon.clone()
+            },
+            Beacon {
+                interval_secs: 0,
+                ..beacon.clone()
+            },
+            Beacon {
+                round: 21,
+                ..beacon.clone()
+            },
+            Beacon {
+                round: 0,
+                ..beacon.clone()
+            },
+            Beacon {
+                elapsed_secs: 1201,
+                ..beacon.clone()
+            },
+            Beacon {
+                tick_age_ms: u64::MAX,
+                ..beacon.clone()
+            },
+        ];
+        for beacon in invalid {
+            assert!(!beacon.is_valid(), "{:?}", beacon);
+        }
+    }
+
+    #[test]
+    fn test_follower_with_a_longer_lead_in() {
+        let mut follower = running(60, 20, 30, 2);
+        let leader = running(60, 20, 10, 5);
+
+        follow(&mut follower, &leader);
+
+        assert_eq!(follower.lead_in_secs, 10);
+        assert_eq!(position(&follower), position(&leader));
+        assert_eq!(follower.lead_in_remaining, 5);
+    }
+
+    #[test]
+    fn test_follower_starts_and_replays_ticks() {
+        let mut follower = Engine::new(30, 5, 0);
+        let leader = running(60, 20, 0, 2);
+
+        let events = follow(&mut follower, &leader);
+
+        assert_eq!(follower.interval_secs, 60);
+        assert_eq!(follower.num_rounds, 20);
+        assert_eq!(follower.elapsed_secs, 2);
+        assert_eq!(follower.timer_state, TimerState::Running);
+        assert_eq!(
+            events,
+            vec![
+                TimerEvent::SessionStarted {
+                    rounds: 20,
+                    interval_secs: 60
+                },
+                TimerEvent::RoundStarted {
+                    round: 1,
+                    label: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_follower_chimes_with_the_leader() {
+        let mut follower = running(10, 3, 0, 9);
+        let leader = running(10, 3, 0, 10);
+
+        let events = follow(&mut follower, &leader);
+
+        assert_eq!(
+            events,
+            vec![
+                TimerEvent::Chime { round: 1 },
+                TimerEvent::RoundStarted {
+                    round: 2,
+                    label: None
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_follower_ahead_by_one_tick_is_left_alone() {
+        let mut follower = running(10, 3, 0, 5);
+        let leader = running(10, 3, 0, 4);
+
+        assert!(follow(&mut follower, &leader).is_empty());
+        assert_eq!(follower.elapsed_secs, 5);
+    }
+
+    #[test]
+    fn test_follower_mirrors_skip() {
+        let mut follower = running(60, 3, 0, 4);
+        let mut leader = running(60, 3, 0, 4);
+        leader.skip();
+        leader.tick();
+
+        let events = follow(&mut follower, &leader);
+
+        assert_eq!(
+            events,
+            vec![
+                TimerEvent::Chime { round: 1 },
+                TimerEvent::RoundStarted {
+                    round: 2,
+                    label: None
+                }
+            ]
+        );
+        assert_eq!(follower.elapsed_secs, 61);
+        assert_eq!(follower.round_number, 2);
+    }
+
+    #[test]
+    fn test_follower_joining_mid_session_jumps_quietly() {
+        let mut follower = Engine::new(60, 20, 0);
+        let leader = running(60, 20, 0, 500);
+
+        let events = follow(&mut follower, &leader);
+
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], TimerEvent::SessionStarted { .. }));
+        assert_eq!(follower.elapsed_secs, 500);
+        assert_eq!(follower.round_number, 9);
+    }
+
+    #[test]
+    fn test_follower_pauses_resumes_and_stops() {
+        let mut follower = running(60, 20, 0, 5);
+        let mut leader = running(60, 20, 0, 5);
+
+        leader.pause();
+        assert_eq!(
+            follow(&mut follower, &leader),
+            vec![TimerEvent::Paused { round: 1 }]
+        );
+
+        leader.resume();
+        leader.tick();
+        assert_eq!(
+            follow(&mut follower, &leader),
+            vec![TimerEvent::Resumed { round: 1 }]
+        );
+        assert_eq!(follower.elapsed_secs, 6);
+
+        leader.stop();
+        assert_eq!(
+            follow(&mut follower, &leader),
+            vec![TimerEvent::SessionStopped { round: 1 }]
+        );
+    }
+
+    #[test]
+    fn test_follower_finishes_with_the_leader() {
+        let mut follower = running(10, 2, 0, 19);
+        let leader = running(10, 2, 0, 20);
+        assert_eq!(leader.timer_state, TimerState::Stopped);
+
+        let events = follow(&mut follower, &leader);
+
+        assert_eq!(
+            events,
+            vec![
+                TimerEvent::Chime { round: 2 },
+                TimerEvent::SessionFinished { rounds: 2 }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_leader_and_follower_over_udp() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            // Unicast to the follower's port stands in for the multicast group,
+            // which needs a multicast route
+            let socket = follower_socket(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+            let port = socket.local_addr().unwrap().port();
+            let group = SocketAddrV4::new(Ipv4Addr::LOCALHOST, port);
+
+            let (leader_status, leader_receiver) =
+                watch::channel(Engine::new(60, 20, 0).snapshot());
+            tokio::spawn(async move { lead("gym", group, leader_receiver).await });
+
+            let (_follower_status, follower_receiver) =
+                watch::channel(Engine::new(60, 20, 0).snapshot());
+            let (output, mut messages) = mpsc::channel(4);
+            tokio::spawn(async move {
+                follow_leader(
+                    "gym",
+                    Some(socket),
+                    Duration::from_millis(1500),
+                    follower_receiver,
+                    output,
+                )
+                .await
+            });
+
+            let Some(Message::Follow(beacon)) = messages.next().await else {
+                panic!("expected a beacon");
+            };
+            assert_eq!(beacon.state, TimerState::Stopped);
+
+            leader_status.send_replace(running(60, 20, 0, 3).snapshot());
+            let beacon = loop {
+                if let Some(Message::Follow(beacon)) = messages.next().await {
+                    if beacon.state == TimerState::Running {
+                        break beacon;
+                    }
+                }
+            };
+            assert_eq!(beacon.elapsed_secs, 3);
+
+            // A leader that goes away is noticed
+            drop(leader_status);
+            loop {
+                if let Some(Message::LeaderLost) = messages.next().await {
+                    break;
+                }
+            }
+        });
+    }
+}
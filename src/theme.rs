@@ -0,0 +1,202 @@
+use iced::{Color, Theme};
+
+use crate::config::{ColorsConfig, ThemeConfig, ThemeMode};
+
+/// Every colour the app draws with, so that all views agree.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub background: Color,
+    pub text: Color,
+    /// Buttons, inputs and the progress bar.
+    pub accent: Color,
+    pub success: Color,
+    pub danger: Color,
+    pub ring: Color,
+    /// The unfilled part of the ring, and rounds still to go.
+    pub track: Color,
+    pub ticks: Color,
+    /// Backgrounds of the big clock in each phase.
+    pub stopped: Color,
+    pub paused: Color,
+    pub lead_in: Color,
+    pub running: Color,
+    pub warning: Color,
+    /// Text on the big clock's backgrounds.
+    pub phase_text: Color,
+}
+
+impl Palette {
+    pub fn dark() -> Self {
+        let base = iced::theme::Palette::DARK;
+        Self {
+            background: base.background,
+            text: base.text,
+            accent: base.primary,
+            success: base.success,
+            danger: base.danger,
+            ring: Color::from_rgb(0.2, 0.7, 0.9),
+            track: Color::from_rgb(0.3, 0.3, 0.3),
+            ticks: Color::from_rgba(0.5, 0.5, 0.5, 0.8),
+            stopped: Color::from_rgb(0.1, 0.1, 0.1),
+            paused: Color::from_rgb(0.2, 0.2, 0.3),
+            lead_in: Color::from_rgb(0.45, 0.3, 0.0),
+            running: Color::from_rgb(0.05, 0.3, 0.15),
+            warning: Color::from_rgb(0.5, 0.1, 0.1),
+            phase_text: Color::WHITE,
+        }
+    }
+
+    pub fn light() -> Self {
+        let base = iced::theme::Palette::LIGHT;
+        Self {
+            background: base.background,
+            text: base.text,
+            accent: base.primary,
+            success: base.success,
+            danger: base.danger,
+            ring: Color::from_rgb(0.1, 0.55, 0.8),
+            track: Color::from_rgb(0.82, 0.82, 0.82),
+            ticks: Color::from_rgba(0.4, 0.4, 0.4, 0.8),
+            stopped: Color::from_rgb(0.92, 0.92, 0.92),
+            paused: Color::from_rgb(0.85, 0.85, 0.95),
+            lead_in: Color::from_rgb(1.0, 0.85, 0.5),
+            running: Color::from_rgb(0.65, 0.9, 0.72),
+            warning: Color::from_rgb(1.0, 0.62, 0.6),
+            phase_text: Color::BLACK,
+        }
+    }
+
+    /// Pure colours on black, for low vision and bright rooms.
+    pub fn high_contrast() -> Self {
+        Self {
+            background: Color::BLACK,
+            text: Color::WHITE,
+            accent: Color::from_rgb(1.0, 1.0, 0.0),
+            success: Color::from_rgb(0.0, 1.0, 0.0),
+            danger: Color::from_rgb(1.0, 0.2, 0.2),
+            ring: Color::from_rgb(1.0, 1.0, 0.0),
+            track: Color::from_rgb(0.6, 0.6, 0.6),
+            ticks: Color::WHITE,
+            stopped: Color::BLACK,
+            paused: Color::from_rgb(0.0, 0.0, 0.55),
+            lead_in: Color::from_rgb(0.6, 0.4, 0.0),
+            running: Color::from_rgb(0.0, 0.45, 0.0),
+            warning: Color::from_rgb(0.75, 0.0, 0.0),
+            phase_text: Color::WHITE,
+        }
+    }
+
+    /// The palette of the configured mode with the configured colours in
+    /// place of its own.
+    pub fn from_config(config: &ThemeConfig) -> Self {
+        let mut palette = match config.mode {
+            // iced asks the desktop for its preference
+            ThemeMode::System if Theme::default() == Theme::Dark => Self::dark(),
+            ThemeMode::System | ThemeMode::Light => Self::light(),
+            ThemeMode::Dark => Self::dark(),
+            ThemeMode::HighContrast => Self::high_contrast(),
+        };
+        palette.apply(&config.colors);
+        palette
+    }
+
+    fn apply(&mut self, colors: &ColorsConfig) {
+        for (name, value) in colors.entries() {
+            // The config was checked when it was read
+            let Some(color) = Color::parse(value) else {
+                continue;
+            };
+            let slot = match name {
+                "background" => &mut self.background,
+                "text" => &mut self.text,
+                "accent" => &mut self.accent,
+                "ring" => &mut self.ring,
+                "track" => &mut self.track,
+                "ticks" => &mut self.ticks,
+                "stopped" => &mut self.stopped,
+                "paused" => &mut self.paused,
+                "lead_in" => &mut self.lead_in,
+                "running" => &mut self.running,
+                "warning" => &mut self.warning,
+                "phase_text" => &mut self.phase_text,
+                _ => continue,
+            };
+            *slot = color;
+        }
+    }
+
+    /// The iced theme for the window's widgets.
+    pub fn theme(&self) -> Theme {
+        Theme::custom(
+            String::from("Round Timer"),
+            iced::theme::Palette {
+                background: self.background,
+                text: self.text,
+                primary: self.accent,
+                success: self.success,
+                danger: self.danger,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modes() {
+        let config = |mode| ThemeConfig {
+            mode,
+            ..ThemeConfig::default()
+        };
+        assert_eq!(
+            Palette::from_config(&config(ThemeMode::Dark)),
+            Palette::dark()
+        );
+        assert_eq!(
+            Palette::from_config(&config(ThemeMode::Light)),
+            Palette::light()
+        );
+        assert_eq!(
+            Palette::from_config(&config(ThemeMode::HighContrast)),
+            Palette::high_contrast()
+        );
+    }
+
+    #[test]
+    fn test_system_mode_is_light_or_dark() {
+        let palette = Palette::from_config(&ThemeConfig::default());
+        assert!(palette == Palette::dark() || palette == Palette::light());
+    }
+
+    #[test]
+    fn test_colors_replace_the_mode() {
+        let config = ThemeConfig {
+            mode: ThemeMode::Dark,
+            colors: ColorsConfig {
+                ring: Some(String::from("#ff8800")),
+                warning: Some(String::from("#c00")),
+                phase_text: Some(String::from("#00000080")),
+                ..ColorsConfig::default()
+            },
+        };
+        let palette = Palette::from_config(&config);
+
+        assert_eq!(palette.ring, Color::from_rgb8(0xff, 0x88, 0x00));
+        assert_eq!(palette.warning, Color::from_rgb8(0xcc, 0x00, 0x00));
+        assert_eq!(
+            palette.phase_text,
+            Color::from_rgba8(0, 0, 0, 128.0 / 255.0)
+        );
+        assert_eq!(palette.background, Palette::dark().background);
+    }
+
+    #[test]
+    fn test_theme_uses_the_palette() {
+        let palette = Palette::high_contrast();
+        let theme = palette.theme();
+        assert_eq!(theme.palette().background, Color::BLACK);
+        assert_eq!(theme.palette().primary, palette.accent);
+    }
+}
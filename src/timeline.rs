@@ -0,0 +1,571 @@
+use iced::alignment;
+use iced::widget::canvas::{self, Canvas, Geometry, Path, Stroke};
+use iced::{event, mouse, Color, Element, Length, Pixels, Point, Rectangle, Renderer, Size, Theme};
+use std::rc::Rc;
+
+use crate::engine::{Snapshot, TimerState};
+use crate::schedule::Schedule;
+use crate::theme::Palette;
+
+/// Room left between blocks so neighbouring rounds stay apart.
+const GAP: f32 = 1.0;
+
+/// Most blocks a timeline is drawn with. Longer sessions share each block
+/// between several rounds, since there would not be a pixel for every one.
+const MAX_BLOCKS: u32 = 200;
+
+/// A stretch of the session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    LeadIn,
+    Round(u32),
+}
+
+/// A phase, or `rounds` rounds from it on, and where they lie in seconds
+/// from the start of the session.
+#[derive(Debug, Clone, PartialEq)]
+struct Block {
+    phase: Phase,
+    rounds: u32,
+    start: u32,
+    length: u32,
+    label: Option<String>,
+}
+
+impl Block {
+    /// The last round in the block, if it holds any.
+    fn last_round(&self) -> Option<u32> {
+        match self.phase {
+            Phase::Round(first) => Some(first + (self.rounds - 1)),
+            Phase::LeadIn => None,
+        }
+    }
+}
+
+/// The blocks of a session, which only change with its settings, so they are
+/// built once and shared by every frame drawn until then.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blocks {
+    blocks: Vec<Block>,
+    lead_in_secs: u32,
+    interval_secs: u32,
+    rounds: u32,
+    schedule: Schedule,
+}
+
+impl Blocks {
+    /// The blocks for the session in `status`, with the rounds labelled from
+    /// `schedule`.
+    pub fn new(status: &Snapshot, schedule: &Schedule) -> Self {
+        let mut blocks = Vec::new();
+        if status.lead_in_secs > 0 {
+            blocks.push(Block {
+                phase: Phase::LeadIn,
+                rounds: 0,
+                start: 0,
+                length: status.lead_in_secs,
+                label: None,
+            });
+        }
+        let per_block = status.rounds.div_ceil(MAX_BLOCKS).max(1);
+        for first in (1..=status.rounds).step_by(per_block as usize) {
+            let rounds = per_block.min(status.rounds - (first - 1));
+            blocks.push(Block {
+                phase: Phase::Round(first),
+                rounds,
+                start: status
+                    .lead_in_secs
+                    .saturating_add((first - 1).saturating_mul(status.interval_secs)),
+                length: status.interval_secs.saturating_mul(rounds),
+                // A shared block has no room for the labels
+                label: (rounds == 1)
+                    .then(|| schedule.label(first).unwrap_or_else(|| first.to_string())),
+            });
+        }
+
+        Self {
+            blocks,
+            lead_in_secs: status.lead_in_secs,
+            interval_secs: status.interval_secs,
+            rounds: status.rounds,
+            schedule: schedule.clone(),
+        }
+    }
+
+    /// Whether these are still the blocks for `status` and `schedule`.
+    pub fn fit(&self, status: &Snapshot, schedule: &Schedule) -> bool {
+        self.lead_in_secs == status.lead_in_secs
+            && self.interval_secs == status.interval_secs
+            && self.rounds == status.rounds
+            && self.schedule == *schedule
+    }
+
+    fn total_secs(&self) -> u32 {
+        self.lead_in_secs
+            .saturating_add(self.interval_secs.saturating_mul(self.rounds))
+    }
+}
+
+/// The shapes a timeline is made of, in pixels across its width.
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    Block { x: f32, width: f32, color: Color },
+    Marker { x: f32, width: f32, color: Color },
+    Label { content: String, x: f32 },
+}
+
+/// The whole session laid out from left to right: the lead-in and every
+/// round as coloured blocks, with the warnings marked and a playhead at the
+/// current time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timeline {
+    blocks: Rc<Blocks>,
+    total_secs: u32,
+    warning_secs: u32,
+    /// Seconds into the session, lead-in included, unless stopped.
+    position: Option<u32>,
+    /// The phase being timed, which earlier phases are done before.
+    current: Option<Phase>,
+    /// Whether a click jumps to the round under the pointer.
+    clickable: bool,
+    palette: Palette,
+}
+
+impl Timeline {
+    /// The timeline for the timer's `status`, drawn with `blocks` built for
+    /// the same session.
+    pub fn for_status(blocks: Rc<Blocks>, status: &Snapshot, palette: &Palette) -> Self {
+        let lead_in = status.lead_in_remaining_secs > 0;
+        let (position, current) = match status.state {
+            TimerState::Stopped => (None, None),
+            _ if lead_in => (
+                Some(
+                    status
+                        .lead_in_secs
+                        .saturating_sub(status.lead_in_remaining_secs),
+                ),
+                Some(Phase::LeadIn),
+            ),
+            _ => (
+                Some(status.lead_in_secs.saturating_add(status.elapsed_secs)),
+                Some(Phase::Round(status.round)),
+            ),
+        };
+
+        Self {
+            total_secs: blocks.total_secs(),
+            blocks,
+            warning_secs: status.warning_secs,
+            position,
+            current,
+            clickable: status.state == TimerState::Paused,
+            palette: *palette,
+        }
+    }
+
+    fn x(&self, secs: u32, width: f32) -> f32 {
+        if self.total_secs > 0 {
+            secs as f32 / self.total_secs as f32 * width
+        } else {
+            0.0
+        }
+    }
+
+    /// Blocks before the current phase are done.
+    fn is_done(&self, block: &Block) -> bool {
+        match (self.current, block.last_round()) {
+            (Some(Phase::Round(_)), None) => true,
+            (Some(Phase::Round(current)), Some(last)) => last < current,
+            _ => false,
+        }
+    }
+
+    /// Whether the current phase is in `block`.
+    fn is_current(&self, block: &Block) -> bool {
+        match (self.current, block.phase) {
+            (Some(Phase::Round(current)), Phase::Round(first)) => {
+                (first..=first + (block.rounds - 1)).contains(&current)
+            }
+            (current, phase) => current == Some(phase),
+        }
+    }
+
+    fn color(&self, block: &Block) -> Color {
+        if self.is_done(block) {
+            return self.palette.track;
+        }
+        if self.is_current(block) {
+            return self.palette.ring;
+        }
+        match block.phase {
+            Phase::LeadIn => self.palette.lead_in,
+            // Every other block a shade lighter, so that rounds stand apart
+            Phase::Round(first) if (first - 1) / block.rounds % 2 == 1 => {
+                mix(self.palette.running, Color::WHITE, 0.2)
+            }
+            Phase::Round(_) => self.palette.running,
+        }
+    }
+
+    fn shapes(&self, size: Size) -> Vec<Shape> {
+        let mut shapes = Vec::new();
+        let label_size = label_size(size);
+
+        for block in &self.blocks.blocks {
+            let x = self.x(block.start, size.width);
+            let width = self.x(block.length, size.width);
+            shapes.push(Shape::Block {
+                x,
+                width: (width - GAP).max(GAP),
+                color: self.color(block),
+            });
+            // Labels that do not fit are left out rather than cut off
+            if let Some(label) = &block.label {
+                if label.chars().count() as f32 * label_size * 0.6 < width - 4.0 {
+                    shapes.push(Shape::Label {
+                        content: label.clone(),
+                        x: x + width / 2.0,
+                    });
+                }
+            }
+        }
+
+        if self.warning_secs > 0 {
+            for block in &self.blocks.blocks {
+                // A warning as long as the round would mark its start, and a
+                // shared block has no room for one in every round
+                if let (Phase::Round(_), 1) = (block.phase, block.rounds) {
+                    if self.warning_secs >= block.length {
+                        continue;
+                    }
+                    shapes.push(Shape::Marker {
+                        x: self.x(
+                            block.start.saturating_add(block.length - self.warning_secs),
+                            size.width,
+                        ),
+                        width: 2.0,
+                        color: self.palette.warning,
+                    });
+                }
+            }
+        }
+
+        if let Some(position) = self.position {
+            shapes.push(Shape::Marker {
+                x: self.x(position, size.width),
+                width: 3.0,
+                color: self.palette.text,
+            });
+        }
+        shapes
+    }
+
+    /// The round at `x` pixels across a timeline `width` wide.
+    fn round_at(&self, x: f32, width: f32) -> Option<u32> {
+        if width <= 0.0 {
+            return None;
+        }
+        let secs = x / width * self.total_secs as f32;
+        let block = self.blocks.blocks.iter().find(|block| {
+            secs >= block.start as f32 && secs < block.start as f32 + block.length as f32
+        })?;
+        match block.phase {
+            // The round within a shared block
+            Phase::Round(first) => {
+                let offset =
+                    ((secs - block.start as f32) / self.blocks.interval_secs as f32) as u32;
+                Some(first + offset.min(block.rounds - 1))
+            }
+            Phase::LeadIn => None,
+        }
+    }
+}
+
+fn label_size(size: Size) -> f32 {
+    (size.height * 0.4).clamp(8.0, 24.0)
+}
+
+fn mix(from: Color, to: Color, amount: f32) -> Color {
+    Color {
+        r: from.r + (to.r - from.r) * amount,
+        g: from.g + (to.g - from.g) * amount,
+        b: from.b + (to.b - from.b) * amount,
+        a: from.a,
+    }
+}
+
+impl canvas::Program<u32> for Timeline {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: canvas::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<u32>) {
+        let clicked = matches!(
+            event,
+            canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+        );
+        match cursor.position_in(bounds) {
+            Some(position) if clicked && self.clickable => (
+                event::Status::Captured,
+                self.round_at(position.x, bounds.width),
+            ),
+            _ => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        let height = bounds.height;
+
+        for shape in self.shapes(bounds.size()) {
+            match shape {
+                Shape::Block { x, width, color } => frame.fill(
+                    &Path::rectangle(Point::new(x, 0.0), Size::new(width, height)),
+                    color,
+                ),
+                Shape::Marker { x, width, color } => frame.stroke(
+                    &Path::line(Point::new(x, 0.0), Point::new(x, height)),
+                    Stroke::default().with_width(width).with_color(color),
+                ),
+                Shape::Label { content, x } => frame.fill_text(canvas::Text {
+                    content,
+                    position: Point::new(x, height / 2.0),
+                    color: self.palette.phase_text,
+                    size: Pixels(label_size(bounds.size())),
+                    horizontal_alignment: alignment::Horizontal::Center,
+                    vertical_alignment: alignment::Vertical::Center,
+                    ..canvas::Text::default()
+                }),
+            }
+        }
+
+        vec![frame.into_geometry()]
+    }
+
+    fn mouse_interaction(
+        &self,
+        _state: &Self::State,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        match cursor.position_in(bounds) {
+            Some(position)
+                if self.clickable && self.round_at(position.x, bounds.width).is_some() =>
+            {
+                mouse::Interaction::Pointer
+            }
+            _ => mouse::Interaction::default(),
+        }
+    }
+}
+
+/// The timeline across the available width, `height` tall. Clicks while
+/// paused produce the round to jump to.
+pub fn timeline(timeline: Timeline, height: Length) -> Element<'static, u32> {
+    Canvas::new(timeline)
+        .width(Length::Fill)
+        .height(height)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RoundConfig;
+    use crate::engine::Engine;
+
+    const SIZE: Size = Size::new(400.0, 30.0);
+
+    fn timeline(engine: &Engine) -> Timeline {
+        let status = engine.snapshot();
+        let blocks = Blocks::new(&status, &engine.schedule);
+        Timeline::for_status(Rc::new(blocks), &status, &Palette::dark())
+    }
+
+    fn blocks(shapes: &[Shape]) -> Vec<(f32, f32, Color)> {
+        shapes
+            .iter()
+            .filter_map(|shape| match *shape {
+                Shape::Block { x, width, color } => Some((x, width, color)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn markers(shapes: &[Shape]) -> Vec<(f32, Color)> {
+        shapes
+            .iter()
+            .filter_map(|shape| match *shape {
+                Shape::Marker { x, color, .. } => Some((x, color)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_blocks_for_the_lead_in_and_every_round() {
+        let engine = Engine::new(30, 3, 30);
+        let palette = Palette::dark();
+        let shapes = timeline(&engine).shapes(SIZE);
+
+        let blocks = blocks(&shapes);
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[0], (0.0, 99.0, palette.lead_in));
+        assert_eq!(blocks[1], (100.0, 99.0, palette.running));
+        assert_eq!(blocks[3].0, 300.0);
+        // Stopped, so there is no playhead
+        assert!(markers(&shapes).is_empty());
+    }
+
+    #[test]
+    fn test_rounds_are_labelled_from_the_schedule() {
+        let mut engine = Engine::new(60, 2, 0);
+        engine.schedule = Schedule::new(
+            vec![RoundConfig {
+                label: String::from("Burpees"),
+                ..RoundConfig::default()
+            }],
+            None,
+        );
+        let labels: Vec<String> = timeline(&engine)
+            .shapes(SIZE)
+            .into_iter()
+            .filter_map(|shape| match shape {
+                Shape::Label { content, .. } => Some(content),
+                _ => None,
+            })
+            .collect();
+        // The schedule starts over
+        assert_eq!(labels, ["Burpees", "Burpees"]);
+
+        // Labels too long for their block are left out
+        let narrow = Size::new(40.0, 30.0);
+        assert!(!timeline(&engine)
+            .shapes(narrow)
+            .iter()
+            .any(|shape| matches!(shape, Shape::Label { .. })));
+    }
+
+    #[test]
+    fn test_playhead_and_done_rounds() {
+        let mut engine = Engine::new(10, 4, 0);
+        engine.start();
+        engine.skip();
+        for _ in 0..5 {
+            engine.tick();
+        }
+        let palette = Palette::dark();
+        let shapes = timeline(&engine).shapes(SIZE);
+
+        let blocks = blocks(&shapes);
+        assert_eq!(blocks[0].2, palette.track);
+        assert_eq!(blocks[1].2, palette.ring);
+        assert_eq!(markers(&shapes), [(150.0, palette.text)]);
+    }
+
+    #[test]
+    fn test_playhead_during_the_lead_in() {
+        let mut engine = Engine::new(10, 3, 10);
+        engine.start();
+        for _ in 0..4 {
+            engine.tick();
+        }
+        let timeline = timeline(&engine);
+        assert_eq!(timeline.current, Some(Phase::LeadIn));
+        assert_eq!(
+            markers(&timeline.shapes(SIZE)),
+            [(40.0, Palette::dark().text)]
+        );
+    }
+
+    #[test]
+    fn test_warning_markers() {
+        let mut engine = Engine::new(20, 2, 0);
+        engine.warning_secs = 5;
+        let palette = Palette::dark();
+        assert_eq!(
+            markers(&timeline(&engine).shapes(SIZE)),
+            [(150.0, palette.warning), (350.0, palette.warning)]
+        );
+    }
+
+    #[test]
+    fn test_no_warning_markers_when_the_warning_outlasts_the_round() {
+        // The interval was shortened after the warning was set
+        let mut engine = Engine::new(5, 2, 0);
+        engine.warning_secs = 10;
+        assert!(markers(&timeline(&engine).shapes(SIZE)).is_empty());
+
+        engine.warning_secs = 5;
+        assert!(markers(&timeline(&engine).shapes(SIZE)).is_empty());
+    }
+
+    #[test]
+    fn test_round_at() {
+        let engine = Engine::new(30, 3, 30);
+        let timeline = timeline(&engine);
+        assert_eq!(timeline.round_at(50.0, 400.0), None);
+        assert_eq!(timeline.round_at(100.0, 400.0), Some(1));
+        assert_eq!(timeline.round_at(250.0, 400.0), Some(2));
+        assert_eq!(timeline.round_at(399.0, 400.0), Some(3));
+        assert_eq!(timeline.round_at(400.0, 400.0), None);
+    }
+
+    #[test]
+    fn test_many_rounds_share_blocks() {
+        let mut engine = Engine::new(1, 4_000_000_000, 0);
+        let palette = Palette::dark();
+        let shared = Blocks::new(&engine.snapshot(), &engine.schedule);
+        assert_eq!(shared.blocks.len(), MAX_BLOCKS as usize);
+        assert_eq!(shared.blocks[1].phase, Phase::Round(20_000_001));
+        assert!(shared.blocks.iter().all(|block| block.label.is_none()));
+
+        engine.start();
+        engine.pause();
+        engine.jump_to(30_000_000);
+        let timeline = timeline(&engine);
+        let shapes = blocks(&timeline.shapes(SIZE));
+        assert_eq!(shapes.len(), MAX_BLOCKS as usize);
+        assert_eq!(shapes[0].2, palette.track);
+        assert_eq!(shapes[1].2, palette.ring);
+        assert_eq!(shapes[2].2, palette.running);
+
+        // Clicks still pick out a single round
+        assert_eq!(timeline.round_at(0.0, 400.0), Some(1));
+        let round = timeline.round_at(3.0, 400.0).unwrap();
+        assert!((20_000_001..=40_000_000).contains(&round));
+    }
+
+    #[test]
+    fn test_blocks_fit_until_the_settings_change() {
+        let mut engine = Engine::new(30, 3, 0);
+        let blocks = Blocks::new(&engine.snapshot(), &engine.schedule);
+        engine.start();
+        engine.tick();
+        assert!(blocks.fit(&engine.snapshot(), &engine.schedule));
+
+        engine.set_rounds(4);
+        assert!(!blocks.fit(&engine.snapshot(), &engine.schedule));
+    }
+
+    #[test]
+    fn test_only_paused_timelines_are_clickable() {
+        let mut engine = Engine::new(30, 3, 0);
+        assert!(!timeline(&engine).clickable);
+        engine.start();
+        assert!(!timeline(&engine).clickable);
+        engine.pause();
+        assert!(timeline(&engine).clickable);
+    }
+}
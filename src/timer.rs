@@ -4,6 +4,83 @@ use std::time::Duration;
 
 use crate::Message;
 
+/// Sleeps shorter than a tick make no difference to the count.
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(1);
+
 pub fn timer_subscription() -> Subscription<Message> {
     time::every(Duration::from_secs(1)).map(|_| Message::Tick)
 }
+
+/// How long the computer has spent suspended since it booted.
+///
+/// The monotonic clock behind `time::every` stops while the system sleeps,
+/// but the boot-time clock keeps counting, so the difference is the time
+/// asleep. Unlike the wall clock, it does not jump when the time is set.
+#[cfg(target_os = "linux")]
+pub fn time_asleep() -> Duration {
+    fn read(clock: libc::clockid_t) -> Duration {
+        let mut time = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // SAFETY: `time` is a valid timespec for the call to fill in, and
+        // both clocks exist on every supported kernel
+        unsafe { libc::clock_gettime(clock, &mut time) };
+        Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+    }
+
+    let monotonic = read(libc::CLOCK_MONOTONIC);
+    read(libc::CLOCK_BOOTTIME).saturating_sub(monotonic)
+}
+
+/// Suspends go unnoticed where there is no boot-time clock to compare with.
+#[cfg(not(target_os = "linux"))]
+pub fn time_asleep() -> Duration {
+    Duration::ZERO
+}
+
+/// Returns how many ticks were missed while the computer was suspended,
+/// given the [`time_asleep`] at two ticks.
+pub fn missed_ticks(last_tick: Duration, now: Duration) -> u32 {
+    let slept = now.saturating_sub(last_tick);
+    if slept < SUSPEND_THRESHOLD {
+        return 0;
+    }
+    u32::try_from(slept.as_secs()).unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_awake_ticks_miss_nothing() {
+        let last = Duration::from_secs(30);
+        assert_eq!(missed_ticks(last, last), 0);
+        assert_eq!(missed_ticks(last, last + Duration::from_millis(20)), 0);
+    }
+
+    #[test]
+    fn test_suspend_gap() {
+        let last = Duration::from_secs(30);
+        assert_eq!(
+            missed_ticks(last, last + Duration::from_millis(600_400)),
+            600
+        );
+    }
+
+    #[test]
+    fn test_very_long_suspend_does_not_wrap() {
+        assert_eq!(
+            missed_ticks(Duration::ZERO, Duration::from_secs(1 << 40)),
+            u32::MAX
+        );
+    }
+
+    #[test]
+    fn test_time_asleep_never_goes_backwards() {
+        let before = time_asleep();
+        // Reading the two clocks one after the other leaves a little jitter
+        assert!(time_asleep() + Duration::from_millis(50) >= before);
+    }
+}
@@ -0,0 +1,667 @@
+use iced::futures::channel::mpsc;
+use iced::futures::SinkExt;
+use iced::Subscription;
+use std::collections::HashMap;
+use tokio::sync::watch;
+use zbus::connection::Builder;
+use zbus::object_server::{InterfaceRef, SignalContext};
+use zbus::zvariant::{ObjectPath, OwnedValue, Structure, Value};
+use zbus::{fdo, interface, Connection};
+
+use crate::dbus::send;
+use crate::engine::{format_time, Snapshot, TimerState};
+use crate::presets::PRESETS;
+use crate::Message;
+
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU_PATH: &str = "/MenuBar";
+
+/// Icon pixmaps as `(width, height, ARGB32 data)`; the icon comes from the theme.
+type Pixmaps = Vec<(i32, i32, Vec<u8>)>;
+
+#[zbus::proxy(
+    interface = "org.kde.StatusNotifierWatcher",
+    default_service = "org.kde.StatusNotifierWatcher",
+    default_path = "/StatusNotifierWatcher"
+)]
+trait StatusNotifierWatcher {
+    fn register_status_notifier_item(&self, service: &str) -> zbus::Result<()>;
+}
+
+/// The `org.kde.StatusNotifierItem` interface shown by the desktop's tray.
+struct Item {
+    status: watch::Receiver<Snapshot>,
+    output: mpsc::Sender<Message>,
+}
+
+#[interface(name = "org.kde.StatusNotifierItem")]
+impl Item {
+    async fn activate(&self, _x: i32, _y: i32) -> fdo::Result<()> {
+        send(&self.output, Message::ShowWindow).await
+    }
+
+    // Middle click
+    async fn secondary_activate(&self, _x: i32, _y: i32) -> fdo::Result<()> {
+        send(&self.output, Message::Toggle).await
+    }
+
+    fn context_menu(&self, _x: i32, _y: i32) {}
+
+    fn scroll(&self, _delta: i32, _orientation: &str) {}
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn category(&self) -> &str {
+        "ApplicationStatus"
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn id(&self) -> &str {
+        "round-timer"
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn title(&self) -> &str {
+        "Round Timer"
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn status(&self) -> &str {
+        "Active"
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn window_id(&self) -> i32 {
+        0
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn icon_name(&self) -> &str {
+        "round-timer"
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn icon_pixmap(&self) -> Pixmaps {
+        Vec::new()
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn overlay_icon_name(&self) -> &str {
+        overlay_icon(self.status.borrow().state)
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn overlay_icon_pixmap(&self) -> Pixmaps {
+        Vec::new()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn attention_icon_name(&self) -> &str {
+        ""
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn attention_icon_pixmap(&self) -> Pixmaps {
+        Vec::new()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn attention_movie_name(&self) -> &str {
+        ""
+    }
+
+    // Hosts listen for NewToolTip rather than PropertiesChanged
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn tool_tip(&self) -> (String, Pixmaps, String, String) {
+        (
+            String::new(),
+            Vec::new(),
+            String::from("Round Timer"),
+            tooltip(&self.status.borrow()),
+        )
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn item_is_menu(&self) -> bool {
+        false
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn menu(&self) -> ObjectPath<'_> {
+        ObjectPath::from_static_str_unchecked(MENU_PATH)
+    }
+
+    /// Text shown next to the icon by hosts that support it.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn x_ayatana_label(&self) -> String {
+        label(&self.status.borrow())
+    }
+
+    /// The widest label, so the panel doesn't resize every second.
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn x_ayatana_label_guide(&self) -> &str {
+        "20/20 00:00"
+    }
+
+    #[zbus(signal)]
+    async fn new_tool_tip(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn new_overlay_icon(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn x_ayatana_new_label(
+        ctxt: &SignalContext<'_>,
+        label: &str,
+        guide: &str,
+    ) -> zbus::Result<()>;
+}
+
+fn overlay_icon(state: TimerState) -> &'static str {
+    match state {
+        TimerState::Paused => "media-playback-pause",
+        _ => "",
+    }
+}
+
+fn tooltip(status: &Snapshot) -> String {
+    let remaining = format_time(status.round_remaining_secs);
+    match status.state {
+        TimerState::Stopped => format!(
+            "Stopped – {} rounds of {}",
+            status.rounds,
+            format_time(status.interval_secs)
+        ),
+        TimerState::Running if status.lead_in_remaining_secs > 0 => {
+            format!("Get Ready – {}", format_time(status.lead_in_remaining_secs))
+        }
+        TimerState::Running => format!(
+            "Round {} of {} – {} left",
+            status.round, status.rounds, remaining
+        ),
+        TimerState::Paused => format!(
+            "Paused in round {} of {} – {} left",
+            status.round, status.rounds, remaining
+        ),
+    }
+}
+
+fn label(status: &Snapshot) -> String {
+    match status.state {
+        TimerState::Stopped => String::new(),
+        _ if status.lead_in_remaining_secs > 0 => format_time(status.lead_in_remaining_secs),
+        _ => format!(
+            "{}/{} {}",
+            status.round,
+            status.rounds,
+            format_time(status.round_remaining_secs)
+        ),
+    }
+}
+
+const TOGGLE_ID: i32 = 1;
+const SKIP_ID: i32 = 2;
+const STOP_ID: i32 = 3;
+const PRESETS_ID: i32 = 5;
+const SHOW_ID: i32 = 7;
+const QUIT_ID: i32 = 8;
+/// Preset entries use this id plus their index in [`PRESETS`].
+const FIRST_PRESET_ID: i32 = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+struct MenuItem {
+    id: i32,
+    label: String,
+    enabled: bool,
+    separator: bool,
+    children: Vec<MenuItem>,
+}
+
+impl MenuItem {
+    fn new(id: i32, label: impl Into<String>, enabled: bool) -> Self {
+        Self {
+            id,
+            label: label.into(),
+            enabled,
+            separator: false,
+            children: Vec::new(),
+        }
+    }
+
+    fn separator(id: i32) -> Self {
+        Self {
+            separator: true,
+            ..Self::new(id, "", true)
+        }
+    }
+
+    fn find(&self, id: i32) -> Option<&MenuItem> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(id))
+    }
+
+    fn properties(&self) -> HashMap<String, OwnedValue> {
+        let mut properties = Vec::new();
+        if self.separator {
+            properties.push(("type", Value::from("separator")));
+        } else {
+            properties.push(("label", Value::from(self.label.as_str())));
+            properties.push(("enabled", Value::from(self.enabled)));
+        }
+        if !self.children.is_empty() {
+            properties.push(("children-display", Value::from("submenu")));
+        }
+        properties
+            .into_iter()
+            .map(|(key, value)| {
+                let value = value
+                    .try_to_owned()
+                    .expect("menu properties never contain file descriptors");
+                (key.to_string(), value)
+            })
+            .collect()
+    }
+
+    /// Encodes the item as a `(ia{sv}av)` layout, descending `depth` levels
+    /// (all of them when negative).
+    fn layout(&self, depth: i32) -> (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>) {
+        let children = if depth == 0 {
+            Vec::new()
+        } else {
+            self.children
+                .iter()
+                .map(|child| {
+                    let structure = Structure::from(child.layout(depth - 1));
+                    Value::from(structure)
+                        .try_to_owned()
+                        .expect("menu layouts never contain file descriptors")
+                })
+                .collect()
+        };
+        (self.id, self.properties(), children)
+    }
+}
+
+fn menu(state: TimerState) -> MenuItem {
+    let toggle = match state {
+        TimerState::Stopped => "Start",
+        TimerState::Running => "Pause",
+        TimerState::Paused => "Resume",
+    };
+    let active = state != TimerState::Stopped;
+
+    let mut presets = MenuItem::new(PRESETS_ID, "Presets", !active);
+    presets.children = PRESETS
+        .iter()
+        .zip(FIRST_PRESET_ID..)
+        .map(|(preset, id)| {
+            let label = format!("{} – {}", preset.name, preset.description);
+            MenuItem::new(id, label, !active)
+        })
+        .collect();
+
+    let mut root = MenuItem::new(0, "", true);
+    root.children = vec![
+        MenuItem::new(TOGGLE_ID, toggle, true),
+        MenuItem::new(SKIP_ID, "Skip", active),
+        MenuItem::new(STOP_ID, "Stop", active),
+        MenuItem::separator(4),
+        presets,
+        MenuItem::separator(6),
+        MenuItem::new(SHOW_ID, "Show Window", true),
+        MenuItem::new(QUIT_ID, "Quit", true),
+    ];
+    root
+}
+
+fn message_for_item(id: i32) -> Option<Message> {
+    match id {
+        TOGGLE_ID => Some(Message::Toggle),
+        SKIP_ID => Some(Message::Skip),
+        STOP_ID => Some(Message::Stop),
+        SHOW_ID => Some(Message::ShowWindow),
+        QUIT_ID => Some(Message::Quit),
+        id => {
+            let index = usize::try_from(id.checked_sub(FIRST_PRESET_ID)?).ok()?;
+            PRESETS
+                .get(index)
+                .map(|preset| Message::ApplyPreset(preset.name))
+        }
+    }
+}
+
+/// The `com.canonical.dbusmenu` interface that hosts render as the tray menu.
+struct Menu {
+    status: watch::Receiver<Snapshot>,
+    output: mpsc::Sender<Message>,
+    revision: u32,
+}
+
+type Layout = (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>);
+
+#[interface(name = "com.canonical.dbusmenu")]
+impl Menu {
+    fn get_layout(
+        &self,
+        parent_id: i32,
+        recursion_depth: i32,
+        _property_names: Vec<String>,
+    ) -> fdo::Result<(u32, Layout)> {
+        let menu = menu(self.status.borrow().state);
+        let parent = menu
+            .find(parent_id)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("no menu item {}", parent_id)))?;
+        Ok((self.revision, parent.layout(recursion_depth)))
+    }
+
+    fn get_group_properties(
+        &self,
+        ids: Vec<i32>,
+        _property_names: Vec<String>,
+    ) -> Vec<(i32, HashMap<String, OwnedValue>)> {
+        let menu = menu(self.status.borrow().state);
+        ids.into_iter()
+            .filter_map(|id| menu.find(id).map(|item| (id, item.properties())))
+            .collect()
+    }
+
+    fn get_property(&self, id: i32, name: &str) -> fdo::Result<OwnedValue> {
+        menu(self.status.borrow().state)
+            .find(id)
+            .and_then(|item| item.properties().remove(name))
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("no property {} on {}", name, id)))
+    }
+
+    async fn event(&self, id: i32, event_id: &str, _data: OwnedValue, _timestamp: u32) {
+        if event_id != "clicked" {
+            return;
+        }
+        if let Some(message) = message_for_item(id) {
+            let _ = send(&self.output, message).await;
+        }
+    }
+
+    async fn event_group(&self, events: Vec<(i32, String, OwnedValue, u32)>) -> Vec<i32> {
+        let menu = menu(self.status.borrow().state);
+        let mut missing = Vec::new();
+        for (id, event_id, data, timestamp) in events {
+            if menu.find(id).is_none() {
+                missing.push(id);
+            } else {
+                self.event(id, &event_id, data, timestamp).await;
+            }
+        }
+        missing
+    }
+
+    fn about_to_show(&self, _id: i32) -> bool {
+        false
+    }
+
+    fn about_to_show_group(&self, _ids: Vec<i32>) -> (Vec<i32>, Vec<i32>) {
+        (Vec::new(), Vec::new())
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn version(&self) -> u32 {
+        3
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn text_direction(&self) -> &str {
+        "ltr"
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn status(&self) -> &str {
+        "normal"
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn icon_theme_path(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(signal)]
+    async fn layout_updated(
+        ctxt: &SignalContext<'_>,
+        revision: u32,
+        parent: i32,
+    ) -> zbus::Result<()>;
+}
+
+/// Puts an icon with a live countdown and a control menu in the system tray.
+/// Sends [`Message::TrayReady`] once the tray has accepted the icon.
+pub fn subscription(status: watch::Receiver<Snapshot>) -> Subscription<Message> {
+    Subscription::run_with_id(
+        "tray",
+        iced::stream::channel(16, move |mut output| async move {
+            let result = async {
+                let connection =
+                    connect(Builder::session()?, status.clone(), output.clone()).await?;
+                register(&connection).await?;
+                let _ = output.send(Message::TrayReady).await;
+                serve(&connection, status).await
+            }
+            .await;
+
+            if let Err(err) = result {
+                eprintln!("round-timer: system tray unavailable: {}", err);
+            }
+        }),
+    )
+}
+
+async fn connect(
+    builder: Builder<'_>,
+    status: watch::Receiver<Snapshot>,
+    output: mpsc::Sender<Message>,
+) -> zbus::Result<Connection> {
+    let item = Item {
+        status: status.clone(),
+        output: output.clone(),
+    };
+    let menu = Menu {
+        status,
+        output,
+        revision: 1,
+    };
+    builder
+        .serve_at(ITEM_PATH, item)?
+        .serve_at(MENU_PATH, menu)?
+        .name(format!(
+            "org.kde.StatusNotifierItem-{}-1",
+            std::process::id()
+        ))?
+        .build()
+        .await
+}
+
+async fn register(connection: &Connection) -> zbus::Result<()> {
+    let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+    StatusNotifierWatcherProxy::new(connection)
+        .await?
+        .register_status_notifier_item(&name)
+        .await
+}
+
+async fn serve(connection: &Connection, mut status: watch::Receiver<Snapshot>) -> zbus::Result<()> {
+    let item: InterfaceRef<Item> = connection.object_server().interface(ITEM_PATH).await?;
+    let menu: InterfaceRef<Menu> = connection.object_server().interface(MENU_PATH).await?;
+    let mut last_state = status.borrow().state;
+
+    while status.changed().await.is_ok() {
+        let current = status.borrow_and_update().clone();
+        let ctxt = item.signal_context();
+        Item::new_tool_tip(ctxt).await?;
+        Item::x_ayatana_new_label(ctxt, &label(&current), "20/20 00:00").await?;
+
+        if current.state != last_state {
+            Item::new_overlay_icon(ctxt).await?;
+            // Labels and enabled items depend on the state
+            let revision = {
+                let mut menu = menu.get_mut().await;
+                menu.revision += 1;
+                menu.revision
+            };
+            Menu::layout_updated(menu.signal_context(), revision, 0).await?;
+            last_state = current.state;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dbus::tests::{client, fake_service, on_private_bus, running_snapshot};
+    use crate::engine::Engine;
+    use iced::futures::StreamExt;
+
+    #[test]
+    fn test_label_and_tooltip_while_running() {
+        let status = running_snapshot();
+        assert_eq!(label(&status), "3/20 00:42");
+        assert_eq!(tooltip(&status), "Round 3 of 20 – 00:42 left");
+    }
+
+    #[test]
+    fn test_label_and_tooltip_while_stopped() {
+        let status = Engine::new(60, 20, 0).snapshot();
+        assert_eq!(label(&status), "");
+        assert_eq!(tooltip(&status), "Stopped – 20 rounds of 01:00");
+    }
+
+    #[test]
+    fn test_menu_follows_state() {
+        let stopped = menu(TimerState::Stopped);
+        assert_eq!(stopped.find(TOGGLE_ID).unwrap().label, "Start");
+        assert!(!stopped.find(STOP_ID).unwrap().enabled);
+        assert!(stopped.find(FIRST_PRESET_ID).unwrap().enabled);
+
+        let running = menu(TimerState::Running);
+        assert_eq!(running.find(TOGGLE_ID).unwrap().label, "Pause");
+        assert!(running.find(STOP_ID).unwrap().enabled);
+        // Presets would change the session underneath the running timer
+        assert!(!running.find(FIRST_PRESET_ID).unwrap().enabled);
+
+        assert_eq!(
+            menu(TimerState::Paused).find(TOGGLE_ID).unwrap().label,
+            "Resume"
+        );
+    }
+
+    #[test]
+    fn test_message_for_item() {
+        assert!(matches!(message_for_item(TOGGLE_ID), Some(Message::Toggle)));
+        assert!(matches!(message_for_item(QUIT_ID), Some(Message::Quit)));
+        assert!(matches!(
+            message_for_item(FIRST_PRESET_ID + 1),
+            Some(Message::ApplyPreset(name)) if name == PRESETS[1].name
+        ));
+        assert!(message_for_item(PRESETS_ID).is_none());
+        assert!(message_for_item(FIRST_PRESET_ID + PRESETS.len() as i32).is_none());
+    }
+
+    #[test]
+    fn test_layout_depth() {
+        let menu = menu(TimerState::Stopped);
+        let (id, properties, children) = menu.layout(1);
+        assert_eq!(id, 0);
+        assert_eq!(
+            properties["children-display"],
+            OwnedValue::from(zbus::zvariant::Str::from_static("submenu"))
+        );
+        assert_eq!(children.len(), 8);
+        assert!(menu.layout(0).2.is_empty());
+    }
+
+    #[zbus::proxy(interface = "com.canonical.dbusmenu", default_path = "/MenuBar")]
+    trait DBusMenu {
+        fn event(
+            &self,
+            id: i32,
+            event_id: &str,
+            data: &Value<'_>,
+            timestamp: u32,
+        ) -> zbus::Result<()>;
+
+        fn get_layout(
+            &self,
+            parent_id: i32,
+            recursion_depth: i32,
+            property_names: &[&str],
+        ) -> zbus::Result<(u32, Layout)>;
+    }
+
+    struct FakeWatcher {
+        registered: mpsc::UnboundedSender<String>,
+    }
+
+    #[zbus::interface(name = "org.kde.StatusNotifierWatcher")]
+    impl FakeWatcher {
+        fn register_status_notifier_item(&self, service: String) {
+            let _ = self.registered.unbounded_send(service);
+        }
+    }
+
+    #[test]
+    fn test_tray_on_private_bus() {
+        on_private_bus(|address| async move {
+            let (registered_sender, mut registered) = mpsc::unbounded();
+            let _watcher = fake_service(
+                &address,
+                "org.kde.StatusNotifierWatcher",
+                "/StatusNotifierWatcher",
+                FakeWatcher {
+                    registered: registered_sender,
+                },
+            )
+            .await;
+
+            let (status_sender, status) = watch::channel(Engine::new(60, 20, 0).snapshot());
+            let (output, mut messages) = mpsc::channel(4);
+            let server = connect(
+                Builder::address(address.as_str()).unwrap(),
+                status.clone(),
+                output,
+            )
+            .await
+            .unwrap();
+            register(&server).await.unwrap();
+            let name = registered.next().await.unwrap();
+            assert!(name.starts_with("org.kde.StatusNotifierItem-"));
+            tokio::spawn(async move { serve(&server, status).await });
+
+            let client = client(&address).await;
+            let proxy = DBusMenuProxy::builder(&client)
+                .destination(name)
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+
+            // Clicking a menu entry sends the matching message
+            proxy
+                .event(TOGGLE_ID, "clicked", &Value::from(0), 0)
+                .await
+                .unwrap();
+            assert!(matches!(messages.next().await, Some(Message::Toggle)));
+
+            // The layout is rebuilt when the state changes
+            let (revision, _) = proxy.get_layout(0, -1, &[]).await.unwrap();
+            let mut layout_updated = proxy.inner().receive_signal("LayoutUpdated").await.unwrap();
+            status_sender.send_replace(running_snapshot());
+            layout_updated.next().await.unwrap();
+
+            let (new_revision, (_, _, children)) = proxy.get_layout(0, -1, &[]).await.unwrap();
+            assert!(new_revision > revision);
+            let toggle: Structure<'_> = children[0].try_clone().unwrap().try_into().unwrap();
+            let properties: HashMap<String, OwnedValue> =
+                toggle.fields()[1].try_clone().unwrap().try_into().unwrap();
+            let label: String = properties["label"].try_clone().unwrap().try_into().unwrap();
+            assert_eq!(label, "Pause");
+        });
+    }
+}
@@ -0,0 +1,467 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::{cursor, execute, queue, terminal};
+
+use crate::audio::AudioPlayer;
+use crate::cli::Options;
+use crate::config::{AfterSuspend, Config};
+use crate::engine::{format_time, Engine, TimerState};
+use crate::events::TimerEvent;
+use crate::hooks::Hooks;
+use crate::meeting::{self, Meeting};
+use crate::schedule::Schedule;
+use crate::speech::Speech;
+use crate::timer;
+use crate::webhooks::{Configuration, Webhooks};
+
+const TICK: Duration = Duration::from_secs(1);
+const MAX_BAR_WIDTH: usize = 50;
+
+#[derive(Debug, PartialEq)]
+enum Action {
+    Toggle,
+    Stop,
+    Skip,
+    Quit,
+}
+
+/// Restores the terminal when the TUI exits, including on error or panic.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter(stdout: &mut io::Stdout) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(
+            stdout,
+            terminal::EnterAlternateScreen,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::Hide
+        )?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+pub fn run(
+    options: Options,
+    config: Config,
+    schedule: Schedule,
+    meeting: Option<Meeting>,
+) -> io::Result<()> {
+    // Hooks, webhooks and speech run on this runtime so they never hold up the countdown
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()?;
+    let _runtime_guard = runtime.enter();
+    let hooks = Hooks::new(config.hooks, true);
+    let webhooks = Webhooks::new(config.webhooks, true);
+    let speech = Speech::new(config.speech, true);
+
+    let mut audio_player = AudioPlayer::new();
+    audio_player.set_muted(options.mute);
+
+    let mut engine = Engine::new(
+        options.interval_secs,
+        options.num_rounds,
+        options.lead_in_secs,
+    );
+    engine.set_warning(options.warning_secs);
+    engine.schedule = schedule;
+    // The terminal has no inputs, so the session's configuration never changes
+    let configuration = Configuration::from(&engine);
+    let mut events = Vec::new();
+    if options.auto_start {
+        events = start(&mut engine, meeting.as_ref());
+    }
+
+    let mut stdout = io::stdout();
+    let _guard = TerminalGuard::enter(&mut stdout)?;
+    let mut next_tick = Instant::now() + TICK;
+    // The time asleep at the last tick, while the timer runs
+    let mut last_tick = None;
+    let mut suspend_notice = None;
+    dispatch(
+        &mut stdout,
+        &audio_player,
+        &hooks,
+        &webhooks,
+        &speech,
+        &configuration,
+        &events,
+    )?;
+
+    loop {
+        render(
+            &mut stdout,
+            &engine,
+            meeting.is_some(),
+            suspend_notice.as_deref(),
+        )?;
+
+        let timeout = next_tick.saturating_duration_since(Instant::now());
+        if event::poll(timeout)? {
+            let event = event::read()?;
+            if let Event::Resize(..) = event {
+                execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+            }
+            if let Event::Key(key) = event {
+                let events = match action_for(key) {
+                    Some(Action::Toggle) => {
+                        // Like the window's subscription, restart the clock on (re)start
+                        next_tick = Instant::now() + TICK;
+                        match engine.timer_state {
+                            TimerState::Stopped => start(&mut engine, meeting.as_ref()),
+                            TimerState::Running => engine.pause(),
+                            TimerState::Paused => engine.resume(),
+                        }
+                    }
+                    Some(Action::Stop) => engine.stop(),
+                    Some(Action::Skip) => engine.skip(),
+                    Some(Action::Quit) => return Ok(()),
+                    None => Vec::new(),
+                };
+                dispatch(
+                    &mut stdout,
+                    &audio_player,
+                    &hooks,
+                    &webhooks,
+                    &speech,
+                    &configuration,
+                    &events,
+                )?;
+            }
+        }
+
+        if Instant::now() >= next_tick {
+            let events = tick(
+                &mut engine,
+                &mut last_tick,
+                timer::time_asleep(),
+                config.power.after_suspend,
+                &mut suspend_notice,
+            );
+            dispatch(
+                &mut stdout,
+                &audio_player,
+                &hooks,
+                &webhooks,
+                &speech,
+                &configuration,
+                &events,
+            )?;
+            next_tick += TICK;
+            // After the process was stopped, e.g. with ctrl-z, carry on
+            // from now instead of replaying every tick it missed
+            let now = Instant::now();
+            if next_tick < now {
+                next_tick = now + TICK;
+            }
+        }
+
+        match engine.timer_state {
+            TimerState::Running => suspend_notice = None,
+            TimerState::Paused => last_tick = None,
+            TimerState::Stopped => {
+                last_tick = None;
+                suspend_notice = None;
+            }
+        }
+    }
+}
+
+/// Ticks the engine, first dealing with time the computer spent suspended
+/// since the last tick as `after_suspend` says, like the window does.
+fn tick(
+    engine: &mut Engine,
+    last_tick: &mut Option<Duration>,
+    asleep: Duration,
+    after_suspend: AfterSuspend,
+    suspend_notice: &mut Option<String>,
+) -> Vec<TimerEvent> {
+    let missed = last_tick.map_or(0, |last_tick| timer::missed_ticks(last_tick, asleep));
+    *last_tick = Some(asleep);
+
+    let mut events = Vec::new();
+    if missed > 0 {
+        match after_suspend {
+            AfterSuspend::Pause => {
+                *suspend_notice = Some(format!(
+                    "Paused after the computer was asleep for {}",
+                    format_time(missed)
+                ));
+                return engine.pause();
+            }
+            AfterSuspend::CatchUp => events = engine.advance(missed),
+        }
+    }
+    events.extend(engine.tick());
+    events
+}
+
+fn start(engine: &mut Engine, meeting: Option<&Meeting>) -> Vec<TimerEvent> {
+    // Shuffled meetings get a new order each time
+    if let Some(meeting) = meeting {
+        if engine.timer_state == TimerState::Stopped {
+            engine.schedule = meeting.schedule();
+        }
+    }
+    engine.start()
+}
+
+fn action_for(key: KeyEvent) -> Option<Action> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+
+    match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Action::Quit),
+        KeyCode::Char(' ') | KeyCode::Char('p') | KeyCode::Enter => Some(Action::Toggle),
+        KeyCode::Char('s') => Some(Action::Stop),
+        KeyCode::Char('n') => Some(Action::Skip),
+        KeyCode::Char('q') | KeyCode::Esc => Some(Action::Quit),
+        _ => None,
+    }
+}
+
+fn dispatch(
+    stdout: &mut io::Stdout,
+    audio_player: &AudioPlayer,
+    hooks: &Hooks,
+    webhooks: &Webhooks,
+    speech: &Speech,
+    configuration: &Configuration,
+    events: &[TimerEvent],
+) -> io::Result<()> {
+    for event in events {
+        hooks.run(event);
+        webhooks.send(event, configuration);
+        if !audio_player.is_muted() {
+            speech.say(event);
+        }
+        if let TimerEvent::Chime { .. } = event {
+            if audio_player.is_available() {
+                audio_player.play_chime();
+            } else if !audio_player.is_muted() {
+                // Fall back to the terminal bell, e.g. over SSH
+                execute!(stdout, Print('\x07'))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn render(
+    stdout: &mut io::Stdout,
+    engine: &Engine,
+    meeting: bool,
+    suspend_notice: Option<&str>,
+) -> io::Result<()> {
+    // Some pseudo-terminals report a zero size; fall back to the widest bar
+    let bar_width = match terminal::size()? {
+        (0, _) => MAX_BAR_WIDTH,
+        (columns, _) => (columns as usize).saturating_sub(4).min(MAX_BAR_WIDTH),
+    };
+
+    let round_line = if engine.in_lead_in() {
+        format!("Starting In: {}", format_time(engine.lead_in_remaining))
+    } else {
+        format!(
+            "Round Time Remaining: {}",
+            format_time(engine.round_remaining_secs())
+        )
+    };
+    let toggle_hint = match engine.timer_state {
+        TimerState::Stopped => "start",
+        TimerState::Running => "pause",
+        TimerState::Paused => "resume",
+    };
+
+    let skip_hint = if meeting { "done" } else { "skip" };
+
+    let mut lines = vec![
+        format!("Round Timer - {}", engine.status_text()),
+        match engine.current_label() {
+            Some(label) => format!(
+                "Round {} of {}: {}",
+                engine.round_number, engine.num_rounds, label
+            ),
+            None => format!("Round {} of {}", engine.round_number, engine.num_rounds),
+        },
+        engine
+            .next_label()
+            .map(|next| format!("Next: {}", next))
+            .unwrap_or_default(),
+        round_line,
+        progress_bar(engine.round_progress(), bar_width),
+        String::new(),
+        format!(
+            "Total Time Remaining: {}",
+            format_time(engine.remaining_secs())
+        ),
+        progress_bar(engine.progress(), bar_width),
+        String::new(),
+        format!("space {}   n {}   s stop   q quit", toggle_hint, skip_hint),
+    ];
+    if let Some(notice) = suspend_notice {
+        lines.push(String::new());
+        lines.push(notice.to_string());
+    }
+    // How long each speaker took, once the meeting is over
+    if meeting && engine.timer_state == TimerState::Stopped && !engine.round_times.is_empty() {
+        lines.push(String::new());
+        lines.push(String::from("Time Taken"));
+        lines.extend(meeting::summary(&meeting::turns(engine)));
+    }
+
+    // Overwrite the previous frame in place, which does not flicker the way
+    // clearing the whole screen does over slow links
+    for (row, line) in lines.iter().enumerate() {
+        queue!(
+            stdout,
+            cursor::MoveTo(2, row as u16 + 1),
+            Print(line),
+            terminal::Clear(terminal::ClearType::UntilNewLine)
+        )?;
+    }
+    queue!(stdout, terminal::Clear(terminal::ClearType::FromCursorDown))?;
+    stdout.flush()
+}
+
+/// Renders `fraction` as a bar of `width` cells using eighth-block characters.
+fn progress_bar(fraction: f32, width: usize) -> String {
+    const PARTIAL: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+    let eighths = (fraction.clamp(0.0, 1.0) * width as f32 * 8.0).round() as usize;
+    let full = eighths / 8;
+
+    let mut bar = String::from("[");
+    bar.extend(std::iter::repeat_n('█', full));
+    if full < width {
+        bar.push(PARTIAL[eighths % 8]);
+        bar.extend(std::iter::repeat_n(' ', width - full - 1));
+    }
+    bar.push(']');
+    bar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_progress_bar_empty() {
+        assert_eq!(progress_bar(0.0, 4), "[    ]");
+    }
+
+    #[test]
+    fn test_progress_bar_full() {
+        assert_eq!(progress_bar(1.0, 4), "[████]");
+    }
+
+    #[test]
+    fn test_progress_bar_partial_cell() {
+        assert_eq!(progress_bar(0.5, 3), "[█▌ ]");
+    }
+
+    #[test]
+    fn test_progress_bar_clamps_fraction() {
+        assert_eq!(progress_bar(2.0, 2), "[██]");
+        assert_eq!(progress_bar(-1.0, 2), "[  ]");
+    }
+
+    #[test]
+    fn test_key_bindings() {
+        assert_eq!(action_for(press(KeyCode::Char(' '))), Some(Action::Toggle));
+        assert_eq!(action_for(press(KeyCode::Char('n'))), Some(Action::Skip));
+        assert_eq!(action_for(press(KeyCode::Char('s'))), Some(Action::Stop));
+        assert_eq!(action_for(press(KeyCode::Char('q'))), Some(Action::Quit));
+        assert_eq!(action_for(press(KeyCode::Char('x'))), None);
+    }
+
+    #[test]
+    fn test_suspend_pauses_the_timer() {
+        let mut engine = Engine::new(60, 3, 0);
+        engine.start();
+        let mut last_tick = None;
+        let mut notice = None;
+
+        let asleep = Duration::from_secs(5);
+        tick(
+            &mut engine,
+            &mut last_tick,
+            asleep,
+            AfterSuspend::Pause,
+            &mut notice,
+        );
+        let asleep = asleep + Duration::from_secs(3600);
+        let events = tick(
+            &mut engine,
+            &mut last_tick,
+            asleep,
+            AfterSuspend::Pause,
+            &mut notice,
+        );
+
+        assert_eq!(events, [TimerEvent::Paused { round: 1 }]);
+        assert_eq!(engine.elapsed_secs, 1);
+        assert_eq!(
+            notice.as_deref(),
+            Some("Paused after the computer was asleep for 60:00")
+        );
+    }
+
+    #[test]
+    fn test_suspend_catches_up_in_one_step() {
+        let mut engine = Engine::new(60, 3, 0);
+        engine.start();
+        let mut last_tick = None;
+        let mut notice = None;
+
+        let asleep = Duration::from_secs(5);
+        tick(
+            &mut engine,
+            &mut last_tick,
+            asleep,
+            AfterSuspend::CatchUp,
+            &mut notice,
+        );
+        let asleep = asleep + Duration::from_secs(90);
+        let events = tick(
+            &mut engine,
+            &mut last_tick,
+            asleep,
+            AfterSuspend::CatchUp,
+            &mut notice,
+        );
+
+        assert_eq!(
+            events,
+            [TimerEvent::RoundStarted {
+                round: 2,
+                label: None
+            }]
+        );
+        assert_eq!(engine.elapsed_secs, 92);
+        assert_eq!(notice, None);
+    }
+
+    #[test]
+    fn test_ctrl_c_quits() {
+        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(action_for(key), Some(Action::Quit));
+    }
+}
@@ -0,0 +1,467 @@
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::WebhooksConfig;
+use crate::engine::Engine;
+use crate::events::{self, TimerEvent};
+
+/// Retry delays stop doubling here.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Past this size the delivery log is moved aside to `<log>.1`, replacing
+/// the one before, so at most twice this much is kept.
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// The session settings sent along with every event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Configuration {
+    pub interval_secs: u32,
+    pub rounds: u32,
+    pub lead_in_secs: u32,
+    pub warning_secs: u32,
+}
+
+impl From<&Engine> for Configuration {
+    fn from(engine: &Engine) -> Self {
+        Self {
+            interval_secs: engine.interval_secs,
+            rounds: engine.num_rounds,
+            lead_in_secs: engine.lead_in_secs,
+            warning_secs: engine.warning_secs,
+        }
+    }
+}
+
+/// Posts timer events to the user's webhook URLs in the background.
+pub struct Webhooks {
+    config: WebhooksConfig,
+    /// Shared by all deliveries, which reuse its connections.
+    client: reqwest::Client,
+    log_path: Option<PathBuf>,
+    quiet: bool,
+}
+
+impl Webhooks {
+    /// When `quiet` is set, failures are only written to the delivery log,
+    /// not to stderr.
+    pub fn new(config: WebhooksConfig, quiet: bool) -> Self {
+        let log_path = config.log.clone().or_else(default_log_path);
+        Self {
+            config,
+            client: client(),
+            log_path,
+            quiet,
+        }
+    }
+
+    fn url_for(&self, event: &TimerEvent) -> Option<&str> {
+        self.config.url(event.kind()?)
+    }
+
+    /// Delivers `event` to its webhook, if one is configured, on the current
+    /// tokio runtime. Never waits for the request to finish.
+    pub fn send(&self, event: &TimerEvent, configuration: &Configuration) {
+        let Some(url) = self.url_for(event) else {
+            return;
+        };
+
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            if !self.quiet {
+                eprintln!("round-timer: webhook skipped: no async runtime");
+            }
+            return;
+        };
+
+        let delivery = Delivery {
+            client: self.client.clone(),
+            url: url.to_string(),
+            event: event_name(event),
+            body: payload(event, events::timestamp_ms(), configuration),
+            retries: self.config.retries,
+            retry_delay: Duration::from_secs(self.config.retry_delay_secs).min(MAX_RETRY_DELAY),
+            timeout: Duration::from_secs(self.config.timeout_secs),
+        };
+        let log_path = self.log_path.clone();
+        let quiet = self.quiet;
+
+        runtime.spawn(async move {
+            if let Err(message) = delivery.run(log_path.as_deref()).await {
+                if !quiet {
+                    eprintln!("round-timer: webhook {} {}", delivery.url, message);
+                }
+            }
+        });
+    }
+}
+
+fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(concat!("round-timer/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .expect("the TLS backend is built in")
+}
+
+fn default_log_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("round-timer").join("webhooks.log"))
+}
+
+fn event_name(event: &TimerEvent) -> String {
+    match serde_json::to_value(event) {
+        Ok(serde_json::Value::Object(fields)) => fields
+            .get("event")
+            .and_then(|name| name.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        _ => String::new(),
+    }
+}
+
+/// The event as in the JSON event stream, plus the session's configuration.
+fn payload(event: &TimerEvent, timestamp_ms: u64, configuration: &Configuration) -> String {
+    let mut json: serde_json::Value = serde_json::from_str(&events::to_json(event, timestamp_ms))
+        .expect("timer events are valid JSON");
+    json["configuration"] =
+        serde_json::to_value(configuration).expect("configurations always serialize");
+    json.to_string()
+}
+
+struct Delivery {
+    client: reqwest::Client,
+    url: String,
+    event: String,
+    body: String,
+    retries: u32,
+    retry_delay: Duration,
+    timeout: Duration,
+}
+
+#[derive(Serialize)]
+struct LogEntry<'a> {
+    timestamp_ms: u64,
+    event: &'a str,
+    url: &'a str,
+    attempt: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+impl Delivery {
+    /// Posts the payload until the server accepts it or the retries run out,
+    /// doubling the delay between attempts up to [`MAX_RETRY_DELAY`]. Every
+    /// attempt is logged.
+    async fn run(&self, log_path: Option<&Path>) -> Result<(), String> {
+        let mut delay = self.retry_delay;
+        let mut attempt = 1;
+
+        loop {
+            let response = self.post().await;
+            let outcome = match &response {
+                Ok(status) if (200..300).contains(status) => Ok(()),
+                Ok(status) => Err(format!("HTTP {}", status)),
+                Err(message) => Err(message.clone()),
+            };
+
+            if let Some(log_path) = log_path {
+                let entry = LogEntry {
+                    timestamp_ms: events::timestamp_ms(),
+                    event: &self.event,
+                    url: &self.url,
+                    attempt,
+                    status: response.as_ref().ok().copied(),
+                    error: outcome.as_ref().err().map(String::as_str),
+                };
+                let line = serde_json::to_string(&entry).expect("log entries always serialize");
+                let log_path = log_path.to_path_buf();
+                // Writing the log blocks, so keep it off the runtime; a broken
+                // log must not stop deliveries
+                let _ = tokio::task::spawn_blocking(move || {
+                    append_log(&log_path, &line, MAX_LOG_BYTES)
+                })
+                .await;
+            }
+
+            match outcome {
+                Ok(()) => return Ok(()),
+                Err(message) if attempt > self.retries => {
+                    return Err(format!("failed after {} attempts: {}", attempt, message));
+                }
+                Err(_) => {
+                    tokio::time::sleep(delay).await;
+                    delay = next_delay(delay);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Sends one POST request and returns the response's status code.
+    async fn post(&self) -> Result<u16, String> {
+        let response = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(self.body.clone())
+            .timeout(self.timeout)
+            .send()
+            .await
+            .map_err(|err| {
+                if err.is_timeout() {
+                    format!("timed out after {:?}", self.timeout)
+                } else {
+                    error_chain(&err)
+                }
+            })?;
+        Ok(response.status().as_u16())
+    }
+}
+
+/// The error with its causes, which say what actually went wrong, such as a
+/// refused connection or an untrusted certificate.
+fn error_chain(err: &dyn std::error::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(cause) = source {
+        message = format!("{}: {}", message, cause);
+        source = cause.source();
+    }
+    message
+}
+
+fn next_delay(delay: Duration) -> Duration {
+    delay.saturating_mul(2).min(MAX_RETRY_DELAY)
+}
+
+fn append_log(path: &Path, line: &str, max_bytes: u64) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    if std::fs::metadata(path).is_ok_and(|metadata| metadata.len() >= max_bytes) {
+        std::fs::rename(path, rotated_log_path(path))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", line)
+}
+
+fn rotated_log_path(path: &Path) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    PathBuf::from(rotated)
+}
+
+/// Whether `url` is one deliveries can be made to.
+pub fn is_supported_url(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn configuration() -> Configuration {
+        Configuration::from(&Engine::new(60, 20, 10))
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_the_limit() {
+        assert_eq!(next_delay(Duration::from_secs(2)), Duration::from_secs(4));
+        assert_eq!(next_delay(Duration::from_secs(200)), MAX_RETRY_DELAY);
+        assert_eq!(next_delay(Duration::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_url_for_configured_event() {
+        let webhooks = Webhooks::new(
+            WebhooksConfig {
+                on_finish: Some(String::from("http://localhost/finish")),
+                ..WebhooksConfig::default()
+            },
+            true,
+        );
+        assert_eq!(
+            webhooks.url_for(&TimerEvent::SessionFinished { rounds: 3 }),
+            Some("http://localhost/finish")
+        );
+        assert_eq!(webhooks.url_for(&TimerEvent::Paused { round: 1 }), None);
+    }
+
+    #[test]
+    fn test_payload_includes_configuration() {
+        let payload = payload(
+            &TimerEvent::RoundStarted {
+                round: 2,
+                label: None,
+            },
+            1234,
+            &configuration(),
+        );
+        let json: serde_json::Value = serde_json::from_str(&payload).unwrap();
+
+        assert_eq!(json["event"], "round_started");
+        assert_eq!(json["timestamp_ms"], 1234);
+        assert_eq!(json["round"], 2);
+        assert_eq!(json["configuration"]["interval_secs"], 60);
+        assert_eq!(json["configuration"]["rounds"], 20);
+        assert_eq!(json["configuration"]["lead_in_secs"], 10);
+    }
+
+    #[test]
+    fn test_supported_urls() {
+        assert!(is_supported_url("http://dashboard.local:8080/hooks/timer"));
+        assert!(is_supported_url("https://dashboard.example.com/hooks"));
+        assert!(!is_supported_url("ftp://dashboard.local"));
+        assert!(!is_supported_url("http://"));
+        assert!(!is_supported_url("dashboard.local"));
+    }
+
+    /// A stub server that answers each request with the next status code and
+    /// records the request bodies.
+    async fn stub_server(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 4096];
+                // The request is small and sent in one go, headers and body
+                while !String::from_utf8_lossy(&request).contains("}") {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&request).into_owned());
+                let reply = format!(
+                    "HTTP/1.1 {} Stub\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                stream.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn delivery(url: String, retries: u32) -> Delivery {
+        Delivery {
+            client: client(),
+            url,
+            event: String::from("session_finished"),
+            body: payload(
+                &TimerEvent::SessionFinished { rounds: 20 },
+                0,
+                &configuration(),
+            ),
+            retries,
+            retry_delay: Duration::from_millis(10),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    fn log_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "round-timer-webhooks-{}-{}.log",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_delivery_retries_until_accepted() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let log = log_path("retry");
+
+        runtime.block_on(async {
+            let (url, requests) = stub_server(vec![500, 503, 200]).await;
+            assert_eq!(delivery(url, 3).run(Some(&log)).await, Ok(()));
+
+            let requests = requests.lock().unwrap();
+            assert_eq!(requests.len(), 3);
+            assert!(requests[0].starts_with("POST /hook HTTP/1.1\r\n"));
+            assert!(requests[0]
+                .to_lowercase()
+                .contains("content-type: application/json"));
+            assert!(requests[0].contains(r#""event":"session_finished""#));
+        });
+
+        let entries = std::fs::read_to_string(&log).unwrap();
+        let entries: Vec<serde_json::Value> = entries
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0]["attempt"], 1);
+        assert_eq!(entries[0]["status"], 500);
+        assert_eq!(entries[2]["status"], 200);
+        assert!(entries[2].get("error").is_none());
+        std::fs::remove_file(&log).unwrap();
+    }
+
+    #[test]
+    fn test_delivery_gives_up_after_retries() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let log = log_path("give-up");
+
+        runtime.block_on(async {
+            let (url, requests) = stub_server(vec![500, 500]).await;
+            let result = delivery(url, 1).run(Some(&log)).await;
+            assert_eq!(
+                result,
+                Err(String::from("failed after 2 attempts: HTTP 500"))
+            );
+            assert_eq!(requests.lock().unwrap().len(), 2);
+        });
+
+        assert_eq!(std::fs::read_to_string(&log).unwrap().lines().count(), 2);
+        std::fs::remove_file(&log).unwrap();
+    }
+
+    #[test]
+    fn test_log_is_moved_aside_when_full() {
+        let log = log_path("rotate");
+        let rotated = rotated_log_path(&log);
+        let _ = std::fs::remove_file(&rotated);
+
+        append_log(&log, "first", 8).unwrap();
+        append_log(&log, "second", 8).unwrap();
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "first\nsecond\n");
+
+        append_log(&log, "third", 8).unwrap();
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "third\n");
+        assert_eq!(
+            std::fs::read_to_string(&rotated).unwrap(),
+            "first\nsecond\n"
+        );
+
+        std::fs::remove_file(&log).unwrap();
+        std::fs::remove_file(&rotated).unwrap();
+    }
+
+    #[test]
+    fn test_unreachable_server_is_an_error() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(async {
+            // Nothing listens on a port that was just released
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/hook", listener.local_addr().unwrap());
+            drop(listener);
+            delivery(url, 0).run(None).await
+        });
+        assert!(result.is_err());
+    }
+}